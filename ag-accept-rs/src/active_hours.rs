@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Result};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, FixedOffset, Local, NaiveDateTime, NaiveTime,
+    TimeZone, Timelike, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+const DAY_NAMES: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Days and a time of day, written like `Mon-Fri 22:00-06:00`,
+/// `Sat,Sun` (all day) or `Daily 09:00-17:30`. A range that ends before it
+/// starts runs past midnight and belongs to the day it starts on.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeRange {
+    source: String,
+    /// Indexed by `Weekday::num_days_from_monday`.
+    days: [bool; 7],
+    /// Minutes after midnight; `end` is 1440 for `24:00`.
+    start: u16,
+    end: u16,
+}
+
+impl TimeRange {
+    fn has_day(&self, day: Weekday) -> bool {
+        self.days[day.num_days_from_monday() as usize]
+    }
+
+    fn covers(&self, at: NaiveDateTime) -> bool {
+        let minute = (at.hour() * 60 + at.minute()) as u16;
+        let day = at.weekday();
+        if self.start < self.end {
+            self.has_day(day) && minute >= self.start && minute < self.end
+        } else {
+            (self.has_day(day) && minute >= self.start)
+                || (self.has_day(day.pred()) && minute < self.end)
+        }
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl From<TimeRange> for String {
+    fn from(range: TimeRange) -> Self {
+        range.source
+    }
+}
+
+impl TryFrom<String> for TimeRange {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Self> {
+        source.parse()
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let error = |what: &str| anyhow!("Invalid time range '{}': {}", source, what);
+        let mut parts = source.split_whitespace();
+        let days = parts.next().ok_or_else(|| error("no days"))?;
+        let (start, end) = match parts.next() {
+            None => (0, MINUTES_PER_DAY),
+            Some(times) => {
+                let (start, end) = times
+                    .split_once('-')
+                    .ok_or_else(|| error("expected HH:MM-HH:MM"))?;
+                let start = parse_time(start).ok_or_else(|| error("invalid start time"))?;
+                let end = parse_time(end).ok_or_else(|| error("invalid end time"))?;
+                (start, end)
+            }
+        };
+        if parts.next().is_some() {
+            return Err(error("expected days and one HH:MM-HH:MM"));
+        }
+        if start == end || start == MINUTES_PER_DAY {
+            return Err(error("the range is empty"));
+        }
+        Ok(Self {
+            source: source.trim().to_string(),
+            days: parse_days(days).ok_or_else(|| error("invalid days"))?,
+            start,
+            end: if end == 0 { MINUTES_PER_DAY } else { end },
+        })
+    }
+}
+
+/// A day name or its abbreviation of at least three letters.
+fn parse_day(name: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    DAY_NAMES
+        .iter()
+        .position(|day| name.len() >= 3 && day.starts_with(&name))
+}
+
+/// `Daily`, `Mon`, `Mon-Fri`, `Sat,Sun` or combinations like `Mon-Wed,Fri`.
+fn parse_days(text: &str) -> Option<[bool; 7]> {
+    let mut days = [false; 7];
+    if matches!(text.to_lowercase().as_str(), "daily" | "*") {
+        return Some([true; 7]);
+    }
+    for part in text.split(',') {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (parse_day(first)?, parse_day(last)?),
+            None => (parse_day(part)?, parse_day(part)?),
+        };
+        // Ranges may wrap around the week, e.g. Fri-Mon
+        let mut day = first;
+        loop {
+            days[day] = true;
+            if day == last {
+                break;
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    Some(days)
+}
+
+/// `HH:MM` as minutes after midnight, up to `24:00`.
+fn parse_time(text: &str) -> Option<u16> {
+    let (hour, minute) = text.split_once(':')?;
+    let (hour, minute): (u16, u16) = (hour.parse().ok()?, minute.parse().ok()?);
+    let minutes = hour * 60 + minute;
+    (minute < 60 && minutes <= MINUTES_PER_DAY).then_some(minutes)
+}
+
+/// When prompts are acted on. Outside these ranges ag-accept only observes.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ActiveHours {
+    /// IANA name such as `Europe/Berlin`; the system timezone when unset.
+    pub timezone: Option<String>,
+    /// Empty means always active.
+    pub ranges: Vec<TimeRange>,
+}
+
+/// Whether actions are allowed now and when that changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduleState {
+    pub active: bool,
+    /// In the schedule's timezone; `None` when it never changes.
+    pub next_change: Option<DateTime<FixedOffset>>,
+}
+
+impl ActiveHours {
+    /// Fails on timezone names chrono-tz does not know.
+    pub fn check(&self) -> Result<()> {
+        self.zone().map(|_| ())
+    }
+
+    fn zone(&self) -> Result<Option<Tz>> {
+        self.timezone
+            .as_deref()
+            .map(|name| {
+                name.parse::<Tz>()
+                    .map_err(|_| anyhow!("Unknown timezone '{}' in active_hours", name))
+            })
+            .transpose()
+    }
+
+    fn covers(&self, at: NaiveDateTime) -> bool {
+        self.ranges.is_empty() || self.ranges.iter().any(|r| r.covers(at))
+    }
+
+    /// State at `now`. An unknown timezone falls back to the system one.
+    pub fn state_at(&self, now: DateTime<Utc>) -> ScheduleState {
+        match self.zone().ok().flatten() {
+            Some(zone) => self.state_in(&zone, now),
+            None => self.state_in(&Local, now),
+        }
+    }
+
+    fn state_in<Z: TimeZone>(&self, zone: &Z, now: DateTime<Utc>) -> ScheduleState {
+        let local = now.with_timezone(zone).naive_local();
+        let active = self.covers(local);
+        ScheduleState {
+            active,
+            next_change: self
+                .next_change(local, active)
+                .and_then(|at| zone.from_local_datetime(&at).earliest())
+                .map(|at| at.fixed_offset()),
+        }
+    }
+
+    /// First range boundary after `after` where coverage differs from
+    /// `active`, looking one week ahead.
+    fn next_change(&self, after: NaiveDateTime, active: bool) -> Option<NaiveDateTime> {
+        let today = after.date();
+        let mut boundaries: Vec<NaiveDateTime> = (-1..=7)
+            .map(|offset| today + ChronoDuration::days(offset))
+            .flat_map(|date| {
+                self.ranges.iter().flat_map(move |r| {
+                    [r.start, r.end].map(|minutes| {
+                        date.and_time(NaiveTime::MIN) + ChronoDuration::minutes(i64::from(minutes))
+                    })
+                })
+            })
+            .filter(|at| *at > after)
+            .collect();
+        boundaries.sort();
+        boundaries.dedup();
+        boundaries.into_iter().find(|at| self.covers(*at) != active)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().to_utc()
+    }
+
+    #[test]
+    fn evaluates_ranges_in_the_configured_timezone() {
+        let hours: ActiveHours = serde_json::from_str(
+            r#"{"timezone": "Europe/Berlin", "ranges": ["Mon-Fri 22:00-06:00", "Sat,Sun"]}"#,
+        )
+        .unwrap();
+
+        // Friday 23:30 in Berlin (CEST): the overnight range runs into the
+        // weekend, which ends at Monday 00:00 since Sunday starts no range
+        let state = hours.state_at(utc("2026-10-16T21:30:00Z"));
+        assert!(state.active);
+        assert_eq!(
+            state.next_change.unwrap().to_rfc3339(),
+            "2026-10-19T00:00:00+02:00"
+        );
+
+        // Monday noon: inactive until 22:00
+        let state = hours.state_at(utc("2026-10-19T10:00:00Z"));
+        assert!(!state.active);
+        assert_eq!(
+            state.next_change.unwrap().to_rfc3339(),
+            "2026-10-19T22:00:00+02:00"
+        );
+
+        assert_eq!(
+            ActiveHours::default().state_at(Utc::now()).next_change,
+            None
+        );
+        assert!("Mon 09:00-09:00".parse::<TimeRange>().is_err());
+        assert!("Funday".parse::<TimeRange>().is_err());
+        let unknown = ActiveHours {
+            timezone: Some("Mars/Olympus".to_string()),
+            ranges: Vec::new(),
+        };
+        assert!(unknown.check().is_err());
+    }
+}
@@ -1,12 +1,20 @@
-use crate::config::AppConfig;
-use crate::services::query::QueryService;
-use crate::services::window::WindowService;
-use crate::platform::{Element, PlatformElement};
-use std::collections::HashMap;
-use std::sync::mpsc::Sender;
+use crate::active_hours::ScheduleState;
+use crate::breaker::{self, CircuitBreaker, Trip};
+use crate::config::{AppConfig, Rule};
+use crate::pipeline::{self, ActionMethod, StepKind, StepResult};
+use crate::platform::{is_timeout, Backend, Element, PlatformBackend};
+use crate::scheduler::{ScheduleInfo, Scheduler};
+use crate::services::query::{ContextRelation, QueryService, ScanMatch, ScanStats};
+use crate::services::window::{FocusRestore, WindowService};
+use crate::text;
+use chrono::{DateTime, Local, Utc};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use std::time::{Duration, Instant};
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 
 #[derive(Clone, Debug)]
 pub struct WindowStat {
@@ -15,48 +23,378 @@ pub struct WindowStat {
     #[allow(dead_code)]
     pub is_target: bool,
     pub is_focused: bool,
+    /// Breakdown of the last full scan of this window.
+    pub scan: ScanStats,
+    /// Time left in quarantine when the window stopped responding.
+    pub hung_for: Option<Duration>,
+    pub schedule: Option<ScheduleInfo>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogCategory {
+    System,
+    Scan,
+    Cache,
+    Action,
+}
+
+impl LogCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogCategory::System => "system",
+            LogCategory::Scan => "scan",
+            LogCategory::Cache => "cache",
+            LogCategory::Action => "action",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Local>,
+    pub level: LogLevel,
+    pub category: LogCategory,
+    pub window: Option<String>,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn new(level: LogLevel, category: LogCategory, message: String) -> Self {
+        Self {
+            timestamp: Local::now(),
+            level,
+            category,
+            window: None,
+            message,
+        }
+    }
+
+    pub fn with_window(mut self, window: &str) -> Self {
+        self.window = Some(window.to_string());
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ActionOutcome {
+    /// The prompt was gone when re-scanned.
+    Verified { method: ActionMethod, attempts: u32 },
+    /// Every attempt reported success but the prompt stayed open.
+    Unverified { method: ActionMethod, attempts: u32 },
+    /// Verification is disabled; the action reported success.
+    Performed { method: ActionMethod },
+    /// No method could be executed.
+    Failed { attempts: u32 },
+    /// Held back by the focus policy, active hours or the circuit breaker;
+    /// the prompt is left open for later.
+    Deferred { reason: String },
+}
+
+impl ActionOutcome {
+    /// True when the prompt is considered dealt with.
+    pub fn is_handled(&self) -> bool {
+        matches!(
+            self,
+            ActionOutcome::Verified { .. } | ActionOutcome::Performed { .. }
+        )
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ActionOutcome::Verified { method, attempts } => {
+                format!("VERIFIED via {} ({} attempt(s))", method, attempts)
+            }
+            ActionOutcome::Unverified { method, attempts } => {
+                format!("UNVERIFIED, last {} ({} attempt(s))", method, attempts)
+            }
+            ActionOutcome::Performed { method } => format!("PERFORMED via {}", method),
+            ActionOutcome::Failed { attempts } => format!("FAILED ({} attempt(s))", attempts),
+            ActionOutcome::Deferred { reason } => format!("DEFERRED ({})", reason),
+        }
+    }
+}
+
+/// Where one queued prompt stands.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueueState {
+    Pending,
+    /// Already handled within the dedup window.
+    Suppressed,
+    /// The button disappeared before its turn.
+    Gone,
+    Done(ActionOutcome),
+}
+
+impl QueueState {
+    pub fn label(&self) -> String {
+        match self {
+            QueueState::Pending => "PENDING".to_string(),
+            QueueState::Suppressed => "SUPPRESSED".to_string(),
+            QueueState::Gone => "GONE".to_string(),
+            QueueState::Done(outcome) => outcome.label(),
+        }
+    }
+}
+
+/// One prompt found in a window, in processing order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueuedPrompt {
+    pub button: String,
+    pub rule: String,
+    pub context: Option<String>,
+    pub state: QueueState,
+}
+
+/// Counters shown in the TUI.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+    pub actions: u64,
+    /// Repeat detections of an already-handled prompt that were not acted on.
+    pub suppressed: u64,
 }
 
 pub enum Event {
-    Log(String),
+    Log(LogEntry),
     Status(String),
     VisibleWindows(Vec<WindowStat>),
     AllWindows(Vec<String>),
     ContextData {
         button: String,
         neighbors: Vec<String>,
+        /// Matched context and how it relates to the button.
+        context: Option<(String, ContextRelation)>,
+    },
+    ActionResult {
+        window: String,
+        button: String,
+        outcome: ActionOutcome,
+        /// Pipeline steps in the order they ran.
+        steps: Vec<StepResult>,
+    },
+    Metrics(Metrics),
+    /// Prompts found in one window by its last scan and how each went.
+    PromptQueue {
+        window: String,
+        prompts: Vec<QueuedPrompt>,
     },
     ProcessingWindow(String), // The window currently being checked (cursor)
     Timing(u64),              // Last scan duration in ms
+    Sleep(Duration),          // Time until the next cycle
+    /// Focus could not be returned to where it was before an action.
+    FocusRestoreFailed(String),
+    /// Whether actions are within active hours changed.
+    Schedule(ScheduleState),
+    /// A window went over its accept limits and is only observed.
+    BreakerTripped {
+        window: String,
+        trip: Trip,
+    },
+    BreakerReset(String),
+}
+
+/// Requests from the TUI to the automation loop.
+pub enum Control {
+    /// Clears the circuit breaker of a window, or of every window.
+    ResetBreaker(Option<String>),
 }
 
-pub struct Automation {
-    window_service: WindowService,
-    query_service: QueryService,
+/// Tier 1 cache entry: a matched button whose prompt is still open after an
+/// action, the rule it satisfied and the subtree it was found in (re-scanned
+/// to verify actions). Buttons of handled or deferred prompts are never cached.
+struct CachedButton<E> {
+    button: E,
+    rule: Rule,
+    context: Option<String>,
+    root: E,
+    /// Top-level window, for `focus_window` steps.
+    window_element: E,
+    window: String,
+    runtime_id: String,
+    cached_at: Instant,
+}
+
+/// Identifies one prompt: a button (by identity) under a given context text
+/// in a given window.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PromptFingerprint {
+    window: String,
+    button_id: String,
+    context_hash: u64,
+}
+
+impl PromptFingerprint {
+    fn new<E: Element>(window: &str, button: &E, context: Option<&str>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        context.hash(&mut hasher);
+        Self {
+            window: window.to_string(),
+            // Fall back to the name for elements without an identity
+            button_id: button
+                .runtime_id()
+                .or_else(|_| button.get_name())
+                .unwrap_or_default(),
+            context_hash: hasher.finish(),
+        }
+    }
+}
+
+/// What the focus policy allows for the next action.
+enum FocusGate {
+    Free,
+    /// Only steps that leave focus and the pointer alone may run.
+    NoFocus(String),
+    /// Do not act yet.
+    Defer(String),
+}
+
+/// A window whose application stopped answering, skipped until `until`.
+struct Quarantine {
+    /// Consecutive timed-out scans.
+    strikes: u32,
+    until: Instant,
+}
+
+pub struct Automation<B: Backend = PlatformBackend> {
+    window_service: WindowService<B>,
+    query_service: QueryService<B>,
     config: AppConfig,
+    rules: Vec<Rule>,
     sender: Option<Sender<Event>>,
-    cached_button: Option<PlatformElement>,
-    cached_ancestry: Vec<PlatformElement>, // Layered Cache: Parent -> Grandparent -> ...
+    cached_button: Option<CachedButton<B::Element>>,
+    cached_ancestry: Vec<B::Element>, // Layered Cache: Parent -> Grandparent -> ...
+    cached_ancestry_at: Instant,
+    /// Window the cached ancestry belongs to.
+    cached_ancestry_window: String,
+    cached_ancestry_window_element: Option<B::Element>,
+    /// Title of the window that had keyboard focus at the start of the cycle.
+    foreground_title: Option<String>,
+    /// Whether the missing idle time was already reported.
+    idle_unknown_logged: bool,
+    /// Active hours as of the last `update_schedule`; `None` before the
+    /// first, which counts as active.
+    schedule: Option<ScheduleState>,
+    breaker: CircuitBreaker,
+    controls: Option<Receiver<Control>>,
+    /// Recently handled prompts and when they were handled.
+    handled_prompts: HashMap<PromptFingerprint, Instant>,
+    /// Open prompts whose deferral was already reported; dropped once they
+    /// are acted on or gone.
+    deferred_prompts: HashSet<PromptFingerprint>,
+    metrics: Metrics,
     last_durations: HashMap<String, u64>,
+    last_scans: HashMap<String, ScanStats>,
+    /// Unresponsive windows by title.
+    quarantined: HashMap<String, Quarantine>,
+    scheduler: Scheduler,
 }
 
 impl Automation {
     pub fn new(config: AppConfig, sender: Option<Sender<Event>>) -> anyhow::Result<Self> {
-        Ok(Self {
-            window_service: WindowService::new()?,
-            query_service: QueryService::new()?,
+        let mut automation =
+            Self::with_services(config, sender, WindowService::new()?, QueryService::new()?);
+        automation.breaker.persist_to(breaker::state_path());
+        Ok(automation)
+    }
+}
+
+impl<B: Backend> Automation<B> {
+    pub fn with_services(
+        config: AppConfig,
+        sender: Option<Sender<Event>>,
+        mut window_service: WindowService<B>,
+        mut query_service: QueryService<B>,
+    ) -> Self {
+        query_service.set_batched(config.batched_scan);
+        let call_timeout = Duration::from_millis(config.call_timeout_ms);
+        window_service.set_call_timeout(call_timeout);
+        query_service.set_call_timeout(call_timeout);
+        Self {
+            window_service,
+            query_service,
+            rules: config.effective_rules(),
+            scheduler: Scheduler::new(&config),
+            breaker: CircuitBreaker::new(config.circuit_breaker.clone()),
             config,
             sender,
             cached_button: None,
             cached_ancestry: Vec::new(),
+            cached_ancestry_at: Instant::now(),
+            cached_ancestry_window: String::new(),
+            cached_ancestry_window_element: None,
+            foreground_title: None,
+            idle_unknown_logged: false,
+            schedule: None,
+            controls: None,
+            handled_prompts: HashMap::new(),
+            deferred_prompts: HashSet::new(),
+            metrics: Metrics::default(),
             last_durations: HashMap::new(),
-        })
+            last_scans: HashMap::new(),
+            quarantined: HashMap::new(),
+        }
+    }
+
+    /// Takes requests from the TUI, read at the start of each cycle.
+    pub fn set_controls(&mut self, controls: Receiver<Control>) {
+        self.controls = Some(controls);
     }
 
-    fn log(&self, msg: String) {
-        info!("{}", msg);
+    fn log(&self, entry: LogEntry) {
+        let window = entry.window.as_deref().unwrap_or("-");
+        match entry.level {
+            LogLevel::Error => error!(
+                "[{}] [{}] {}",
+                entry.category.label(),
+                window,
+                entry.message
+            ),
+            LogLevel::Warn => warn!(
+                "[{}] [{}] {}",
+                entry.category.label(),
+                window,
+                entry.message
+            ),
+            LogLevel::Info => info!(
+                "[{}] [{}] {}",
+                entry.category.label(),
+                window,
+                entry.message
+            ),
+            LogLevel::Debug => debug!(
+                "[{}] [{}] {}",
+                entry.category.label(),
+                window,
+                entry.message
+            ),
+        }
         if let Some(tx) = &self.sender {
-            let _ = tx.send(Event::Log(msg));
+            let _ = tx.send(Event::Log(entry));
         }
     }
 
@@ -75,21 +413,41 @@ impl Automation {
     }
 
     pub fn run(&mut self) -> anyhow::Result<()> {
-        self.log("Starting Automation Loop...".to_string());
-        let interval = Duration::from_secs_f64(self.config.interval);
+        self.log(LogEntry::new(
+            LogLevel::Info,
+            LogCategory::System,
+            "Starting Automation Loop...".to_string(),
+        ));
+        let interval = Duration::from_secs_f64(self.config.interval.max(0.0));
+        let min_sleep = Duration::from_secs_f64(self.config.min_interval_secs.max(0.0));
+
+        for trip in self.breaker.trips().clone().into_values() {
+            let window = trip.window.clone();
+            self.log(
+                LogEntry::new(
+                    LogLevel::Warn,
+                    LogCategory::Action,
+                    format!(
+                        "Circuit breaker still tripped ({}); observing only until reset",
+                        trip.reason
+                    ),
+                )
+                .with_window(&window),
+            );
+            if let Some(tx) = &self.sender {
+                let _ = tx.send(Event::BreakerTripped { window, trip });
+            }
+        }
 
         loop {
+            self.handle_controls();
+            self.update_schedule(Utc::now());
+
             // 1. Measure Scan
             let start = Instant::now();
-            let mut high_load = false;
-
-            match self.cycle() {
-                Ok(hl) => high_load = hl,
-                Err(e) => {
-                    error!("Cycle error: {}", e);
-                }
+            if let Err(e) = self.cycle() {
+                error!("Cycle error: {}", e);
             }
-
             let duration = start.elapsed();
 
             // 2. Report Timing
@@ -97,37 +455,145 @@ impl Automation {
                 let _ = tx.send(Event::Timing(duration.as_millis() as u64));
             }
 
-            // 3. Adaptive Sleep
-            let sleep_duration = if high_load {
-                let backoff = self.config.interval * 2.0;
-                let backoff = if backoff < 2.0 { 2.0 } else { backoff }; // Min 2s backoff
-                self.status(format!("Backoff (High Load) - Sleeping {:.1}s...", backoff));
-                Duration::from_secs_f64(backoff)
+            // 3. Sleep until the next window is due, but keep discovering
+            // windows at the base interval
+            let now = Instant::now();
+            let sleep_duration = self
+                .scheduler
+                .next_due(now)
+                .map_or(interval, |due| due.saturating_duration_since(now))
+                .min(interval)
+                .max(min_sleep);
+            self.status(format!(
+                "Sleeping ({:.1}s)...",
+                sleep_duration.as_secs_f64()
+            ));
+            if let Some(tx) = &self.sender {
+                let _ = tx.send(Event::Sleep(sleep_duration));
+            }
+
+            thread::sleep(sleep_duration);
+        }
+    }
+
+    /// Re-evaluates `active_hours` at `now`, reporting state changes.
+    pub fn update_schedule(&mut self, now: DateTime<Utc>) {
+        let state = self.config.active_hours.state_at(now);
+        if self.schedule.as_ref() == Some(&state) {
+            return;
+        }
+        if !self.config.active_hours.ranges.is_empty() {
+            let until = state
+                .next_change
+                .map(|at| format!(" until {}", at.format("%a %H:%M")))
+                .unwrap_or_default();
+            let message = if state.active {
+                format!("Within active hours{}: acting on prompts", until)
             } else {
-                self.status(format!("Sleeping ({:.1}s)...", self.config.interval));
-                interval
+                format!("Outside active hours{}: observing only", until)
             };
+            self.log(LogEntry::new(LogLevel::Info, LogCategory::System, message));
+        }
+        if let Some(tx) = &self.sender {
+            let _ = tx.send(Event::Schedule(state.clone()));
+        }
+        self.schedule = Some(state);
+    }
 
-            thread::sleep(sleep_duration);
+    fn outside_active_hours(&self) -> bool {
+        self.schedule.as_ref().is_some_and(|s| !s.active)
+    }
+
+    /// Why prompts in the window with breaker key `window` are only
+    /// observed right now, if they are.
+    fn observe_reason(&self, window: &str) -> Option<String> {
+        if self.outside_active_hours() {
+            return Some("outside active hours".to_string());
         }
+        self.breaker
+            .trip(window)
+            .map(|trip| format!("circuit breaker tripped: {}", trip.reason))
     }
 
-    fn cycle(&mut self) -> anyhow::Result<bool> {
-        let target_title = &self.config.target_window_title;
-        let target_lower = target_title.to_lowercase();
-        let focused_name = self
-            .window_service
-            .get_focused_window_name()
+    /// Trips the breaker of `window`, titled `title`, when accepting `prompt`
+    /// now would go over a limit, and raises the alert.
+    fn check_breaker(&mut self, window: &str, title: &str, prompt: u64) -> Option<String> {
+        let trip = self.breaker.check(window, title, prompt, Instant::now())?;
+        self.log(
+            LogEntry::new(
+                LogLevel::Error,
+                LogCategory::Action,
+                format!(
+                    "Circuit breaker tripped ({}); observing only until reset",
+                    trip.reason
+                ),
+            )
+            .with_window(title),
+        );
+        if let Some(tx) = &self.sender {
+            let _ = tx.send(Event::BreakerTripped {
+                window: title.to_string(),
+                trip: trip.clone(),
+            });
+        }
+        Some(format!("circuit breaker tripped: {}", trip.reason))
+    }
+
+    /// Clears the breaker of the window titled `window`, or of every window.
+    pub fn reset_breaker(&mut self, window: Option<&str>) {
+        let reset = self.breaker.reset(window);
+        self.breaker_reset(reset);
+    }
+
+    fn breaker_reset(&mut self, windows: Vec<String>) {
+        for window in windows {
+            self.log(
+                LogEntry::new(
+                    LogLevel::Info,
+                    LogCategory::Action,
+                    "Circuit breaker reset".to_string(),
+                )
+                .with_window(&window),
+            );
+            if let Some(tx) = &self.sender {
+                let _ = tx.send(Event::BreakerReset(window));
+            }
+        }
+    }
+
+    /// Applies requests from the TUI and resets made with `reset-breaker`.
+    fn handle_controls(&mut self) {
+        let controls: Vec<Control> = self
+            .controls
+            .as_ref()
+            .map(|rx| rx.try_iter().collect())
             .unwrap_or_default();
-        let focused_lower = focused_name.to_lowercase();
+        for control in controls {
+            match control {
+                Control::ResetBreaker(window) => self.reset_breaker(window.as_deref()),
+            }
+        }
+        let cleared = self.breaker.sync();
+        self.breaker_reset(cleared);
+    }
+
+    /// Runs one discovery/scan/act pass. Target windows get a full scan
+    /// only when the scheduler says they are due.
+    pub fn cycle(&mut self) -> anyhow::Result<()> {
+        let target_title = self.config.target_window_title.clone();
 
         // 1. SINGLE SOURCE OF TRUTH: Get all windows once
         let all_windows = self.window_service.get_all_windows().unwrap_or_default();
+        let focused_index = self
+            .window_service
+            .find_focused_window(&all_windows)
+            .unwrap_or_default();
         let mut titles = Vec::new();
         let mut target_windows = Vec::new();
+        self.foreground_title = None;
 
         // Define exclusions
-        let exclusions = vec![
+        let exclusions = [
             "ag-accept".to_string(),
             "cmd.exe".to_string(),
             "powershell".to_string(),
@@ -136,21 +602,32 @@ impl Automation {
 
         // 2. Classify Windows (Discovery vs Target)
         // We filter manually here to avoid re-fetching names
-        for window in all_windows {
+        for (index, window) in all_windows.into_iter().enumerate() {
             if let Ok(name) = window.get_name() {
                 if !name.trim().is_empty() {
                     titles.push(name.clone());
+                    if focused_index == Some(index) {
+                        self.foreground_title = Some(name.clone());
+                    }
 
-                    if name.to_lowercase().contains(&target_lower) {
+                    if text::title_matches(&name, &target_title) {
                         // Apply Exclusions HERE
                         if !exclusions.iter().any(|ex| name.contains(ex)) {
-                            target_windows.push((name, window));
+                            let is_focused = focused_index == Some(index);
+                            target_windows.push((name, window, is_focused));
                         }
                     }
                 }
             }
         }
 
+        self.scheduler.update_targets(
+            target_windows
+                .iter()
+                .map(|(name, _, is_focused)| (name.as_str(), *is_focused)),
+            Instant::now(),
+        );
+
         // 3. Broadcast Discovery List
         titles.sort();
         if let Some(tx) = &self.sender {
@@ -161,66 +638,195 @@ impl Automation {
         // Construct stable stats from memory before doing work
         let current_stats: Vec<WindowStat> = target_windows
             .iter()
-            .map(|(name, _)| {
+            .map(|(name, _, is_focused)| {
                 let dur = *self.last_durations.get(name).unwrap_or(&0);
                 WindowStat {
                     title: name.clone(),
                     duration_ms: dur,
                     is_target: false,
-                    is_focused: !focused_lower.is_empty() && name.to_lowercase() == focused_lower,
+                    is_focused: *is_focused,
+                    scan: self.last_scans.get(name).copied().unwrap_or_default(),
+                    hung_for: self.quarantine_remaining(name),
+                    schedule: self.scheduler.info(name, Instant::now()),
                 }
             })
             .collect();
         self.publish_snapshot(current_stats);
 
+        // Every prompt would only be deferred; the full scan reports each once
+        if self.outside_active_hours()
+            && (self.cached_button.is_some() || !self.cached_ancestry.is_empty())
+        {
+            self.log(LogEntry::new(
+                LogLevel::Debug,
+                LogCategory::Cache,
+                "Dropped cache: outside active hours".to_string(),
+            ));
+            self.cached_button = None;
+            self.cached_ancestry.clear();
+        }
+
         // --- TIER 1 Checking (Cached Button) ---
-        if let Some(button) = &self.cached_button {
-            if let Ok(name) = button.get_name() {
-                if !name.trim().is_empty() {
-                    self.log(format!("Using Cached Button: '{}' (Instant Scan)", name));
-                    self.perform_action(button.clone(), "Cached Window".to_string());
-                    return Ok(false);
+        if let Some(cached) = self.cached_button.take() {
+            match self.validate_cached_button(&cached) {
+                Ok(name) => {
+                    let fingerprint = PromptFingerprint::new(
+                        &cached.window,
+                        &cached.button,
+                        cached.context.as_deref(),
+                    );
+                    if self.suppress_duplicate(&fingerprint, &name) {
+                        return Ok(());
+                    }
+                    self.log(
+                        LogEntry::new(
+                            LogLevel::Info,
+                            LogCategory::Cache,
+                            format!("Using Cached Button: '{}' (Instant Scan)", name),
+                        )
+                        .with_window(&cached.window),
+                    );
+                    let outcome = self.perform_action(
+                        &cached.button,
+                        &cached.rule,
+                        &cached.root,
+                        &cached.window_element,
+                        &cached.window,
+                        cached.context.as_deref(),
+                    );
+                    self.restore_focus();
+                    self.record_action(fingerprint, &outcome);
+                    // A deferred prompt is dropped and does not end the
+                    // cycle, so other prompts and windows still get a turn
+                    if !matches!(outcome, ActionOutcome::Deferred { .. }) {
+                        if !outcome.is_handled() {
+                            // Keep the original timestamp so the TTL bounds total reuse
+                            self.cached_button = Some(cached);
+                        }
+                        return Ok(());
+                    }
+                }
+                Err(reason) => {
+                    self.log(LogEntry::new(
+                        LogLevel::Debug,
+                        LogCategory::Cache,
+                        format!("Dropped cached button: {}", reason),
+                    ));
                 }
             }
-            self.cached_button = None;
         }
 
         // --- TIER 2 Checking (Ancestry) ---
         // (Optimized: Checking cached ancestry before full scan)
-        
+        if !self.cached_ancestry.is_empty() && self.cached_ancestry_at.elapsed() > self.cache_ttl()
+        {
+            self.log(LogEntry::new(
+                LogLevel::Debug,
+                LogCategory::Cache,
+                "Dropped cached ancestry: expired".to_string(),
+            ));
+            self.cached_ancestry.clear();
+        }
+        if !self.cached_ancestry.is_empty()
+            && self
+                .quarantine_remaining(&self.cached_ancestry_window)
+                .is_some()
+        {
+            self.log(LogEntry::new(
+                LogLevel::Debug,
+                LogCategory::Cache,
+                "Dropped cached ancestry: window not responding".to_string(),
+            ));
+            self.cached_ancestry.clear();
+        }
+
         let ancestors = self.cached_ancestry.clone();
         for ancestor in ancestors.iter() {
-            if let Ok(_) = ancestor.get_name() {
-                if let Ok((ctx, btn_opt)) = self.query_service.scan_for_context_and_button(
-                    ancestor,
-                    &self.config.context_text_agent_manager,
-                    &self.config.search_texts_agent_manager,
-                ) {
-                    if ctx && btn_opt.is_some() {
-                        let button = btn_opt.unwrap();
-                        self.cached_button = Some(button.clone());
-                        if let Ok(new_ancestry) = self.query_service.get_ancestry(&button) {
-                            self.cached_ancestry = new_ancestry;
-                        }
-                        self.perform_action(button, "Cached Ancestor".to_string());
-                        // Snapshot is already valid (old times), returning early is fine.
-                        return Ok(false);
+            // Only scan ancestors that still exist
+            if ancestor.runtime_id().is_ok() {
+                // Selectors and proximity still see the whole window
+                let window = self
+                    .cached_ancestry_window_element
+                    .clone()
+                    .unwrap_or_else(|| ancestor.clone());
+                if let Ok(Some(found)) =
+                    self.query_service
+                        .scan_within(&window, ancestor, &self.rules)
+                {
+                    let button = found.button;
+                    let win_name = self.cached_ancestry_window.clone();
+                    let fingerprint =
+                        PromptFingerprint::new(&win_name, &button, found.context.as_deref());
+                    let btn_name = button.get_name().unwrap_or_default();
+                    if self.recently_handled(&fingerprint) {
+                        // The full scan counts it and looks for other prompts
+                        break;
+                    }
+                    self.log(
+                        LogEntry::new(
+                            LogLevel::Info,
+                            LogCategory::Cache,
+                            format!("Found button: '{}' via cached ancestor", btn_name),
+                        )
+                        .with_window(&win_name),
+                    );
+                    self.remember_ancestry(&button, &window, &win_name);
+                    let outcome = self.perform_action(
+                        &button,
+                        &found.rule,
+                        ancestor,
+                        &window,
+                        &win_name,
+                        found.context.as_deref(),
+                    );
+                    self.restore_focus();
+                    self.record_action(fingerprint, &outcome);
+                    if matches!(outcome, ActionOutcome::Deferred { .. }) {
+                        // Leave it to the full scan, like Tier 1
+                        self.cached_ancestry.clear();
+                        break;
                     }
+                    if !outcome.is_handled() {
+                        self.remember_button(
+                            button,
+                            found.rule,
+                            found.context,
+                            ancestor.clone(),
+                            window,
+                            &win_name,
+                        );
+                    }
+                    // Snapshot is already valid (old times), returning early is fine.
+                    return Ok(());
                 }
             }
         }
 
         // 5. Phase 3: Full Scan (Processing Targets)
         let mut window_stats = Vec::new();
-        let mut high_load_detected = false;
 
         if target_windows.is_empty() {
             self.status(format!("Target '{}' not found.", target_title));
         }
 
-        for (win_name, window) in target_windows {
+        for (win_name, window, is_focused) in target_windows {
             // Exclusions already applied in Step 2
 
+            // A hung application would only time out again
+            let hung_for = self.quarantine_remaining(&win_name);
+            if hung_for.is_some() || !self.scheduler.is_due(&win_name, Instant::now()) {
+                window_stats.push(WindowStat {
+                    title: win_name.clone(),
+                    duration_ms: *self.last_durations.get(&win_name).unwrap_or(&0),
+                    is_target: true,
+                    is_focused,
+                    scan: self.last_scans.get(&win_name).copied().unwrap_or_default(),
+                    hung_for,
+                    schedule: self.scheduler.info(&win_name, Instant::now()),
+                });
+                continue;
+            }
+
             // MEASUREMENT
             let win_start = Instant::now();
             if let Some(tx) = &self.sender {
@@ -229,108 +835,1481 @@ impl Automation {
 
             thread::sleep(Duration::from_millis(50));
 
-            let scan_result = self.query_service.scan_for_context_and_button(
-                &window,
-                &self.config.context_text_agent_manager,
-                &self.config.search_texts_agent_manager,
-            );
+            let scan_result = self.query_service.scan_all_with_stats(&window, &self.rules);
+            let scan = scan_result
+                .as_ref()
+                .map(|(_, stats)| *stats)
+                .unwrap_or_default();
 
             let win_duration = win_start.elapsed();
             let win_ms = win_duration.as_millis() as u64;
 
+            let hung_for = match &scan_result {
+                Err(e) if is_timeout(e) => Some(self.quarantine(&win_name, e)),
+                Ok(_) => {
+                    self.release(&win_name);
+                    None
+                }
+                Err(_) => None,
+            };
+
             // Update Memory
             self.last_durations.insert(win_name.clone(), win_ms);
+            self.last_scans.insert(win_name.clone(), scan);
+            // Cost excludes the fixed pause above; a failed scan costs its wall time
+            let cost = match &scan_result {
+                Ok(_) => Duration::from_millis(scan.fetch_ms + scan.match_ms),
+                Err(_) => win_duration,
+            };
+            let prompt_seen = matches!(&scan_result, Ok((found, _)) if !found.is_empty());
+            self.scheduler
+                .record_scan(&win_name, cost, prompt_seen, Instant::now());
 
             // Add to fresh stats list
             window_stats.push(WindowStat {
                 title: win_name.clone(),
                 duration_ms: win_ms,
                 is_target: true,
-                is_focused: !focused_lower.is_empty() && win_name.to_lowercase() == focused_lower,
+                is_focused,
+                scan,
+                hung_for,
+                schedule: self.scheduler.info(&win_name, Instant::now()),
             });
 
-            if win_ms > 1000 {
-                high_load_detected = true;
-                self.log(format!("High Load: '{}' took {}ms", win_name, win_ms));
+            if win_ms > 1000 && hung_for.is_none() {
+                self.log(
+                    LogEntry::new(
+                        LogLevel::Warn,
+                        LogCategory::Scan,
+                        format!("High Load: '{}' took {}ms", win_name, win_ms),
+                    )
+                    .with_window(&win_name),
+                );
             }
 
-            // Logic Handling (Context/Button) - Same as before
-            if let Ok((ctx_found, btn_found)) = scan_result {
-                if !self.config.context_text_agent_manager.is_empty() && !ctx_found {
-                    continue;
+            if let Ok((found, _)) = scan_result {
+                if found.is_empty() {
+                    self.forget_deferred(&win_name, &[]);
+                } else {
+                    self.process_queue(&window, &win_name, found);
                 }
-                if let Some(button) = btn_found {
-                    let btn_name = button.get_name().unwrap_or_default();
-                    self.log(format!("Found button: '{}' in '{}'", btn_name, win_name));
+            }
+        }
 
-                    self.cached_button = Some(button.clone());
-                    if let Ok(new_ancestry) = self.query_service.get_ancestry(&button) {
-                        self.cached_ancestry = new_ancestry;
-                    }
-                    if let Ok(siblings) = self.query_service.inspect_siblings(&button) {
-                        if let Some(tx) = &self.sender {
-                            let _ = tx.send(Event::ContextData {
-                                button: btn_name,
-                                neighbors: siblings,
-                            });
-                        }
-                    }
+        // 6. Broadcast Updated Snapshot
+        self.publish_snapshot(window_stats);
+
+        Ok(())
+    }
+
+    /// Handles the prompts of one window in queue order, publishing the
+    /// queue as each one completes. Focus taken by `focus_window` steps is
+    /// restored once all are done.
+    fn process_queue(
+        &mut self,
+        window: &B::Element,
+        win_name: &str,
+        found: Vec<ScanMatch<B::Element>>,
+    ) {
+        let mut prompts: Vec<QueuedPrompt> = found
+            .iter()
+            .map(|m| QueuedPrompt {
+                button: m.button.get_name().unwrap_or_default(),
+                rule: m.rule.name.clone(),
+                context: m.context.clone(),
+                state: QueueState::Pending,
+            })
+            .collect();
+        let fingerprints: Vec<PromptFingerprint> = found
+            .iter()
+            .map(|m| PromptFingerprint::new(win_name, &m.button, m.context.as_deref()))
+            .collect();
+        self.forget_deferred(win_name, &fingerprints);
+        for (prompt, fingerprint) in prompts.iter_mut().zip(&fingerprints) {
+            if self.suppress_duplicate(fingerprint, &prompt.button) {
+                prompt.state = QueueState::Suppressed;
+            }
+        }
+        self.publish_queue(win_name, &prompts);
+
+        let pending = prompts
+            .iter()
+            .filter(|p| p.state == QueueState::Pending)
+            .count();
+        if pending == 0 {
+            return;
+        }
+        if pending > 1 {
+            self.log(
+                LogEntry::new(
+                    LogLevel::Info,
+                    LogCategory::Scan,
+                    format!("Queued {} prompts", pending),
+                )
+                .with_window(win_name),
+            );
+        }
+
+        for (i, (found, fingerprint)) in found.into_iter().zip(fingerprints).enumerate() {
+            if prompts[i].state != QueueState::Pending {
+                continue;
+            }
+            let button = found.button;
+            let btn_name = prompts[i].button.clone();
+            // An earlier action in the queue may have closed this prompt
+            if button.runtime_id().is_err() {
+                prompts[i].state = QueueState::Gone;
+                self.publish_queue(win_name, &prompts);
+                continue;
+            }
+            let relation = found
+                .relation
+                .as_ref()
+                .map(|r| format!(", context {}", r))
+                .unwrap_or_default();
+            self.log(
+                LogEntry::new(
+                    LogLevel::Info,
+                    LogCategory::Scan,
+                    format!(
+                        "Found button: '{}' in '{}' (rule '{}'{})",
+                        btn_name, win_name, found.rule.name, relation
+                    ),
+                )
+                .with_window(win_name),
+            );
 
-                    let _ = self.window_service.focus_window(&window);
-                    self.perform_action(button, win_name);
-                    let _ = self.window_service.restore_previous_focus();
+            self.remember_ancestry(&button, window, win_name);
+            if let Ok(siblings) = self.query_service.inspect_siblings(&button) {
+                if let Some(tx) = &self.sender {
+                    let _ = tx.send(Event::ContextData {
+                        button: btn_name,
+                        neighbors: siblings,
+                        context: found.context.clone().zip(found.relation.clone()),
+                    });
                 }
             }
+
+            let outcome = self.perform_action(
+                &button,
+                &found.rule,
+                window,
+                window,
+                win_name,
+                found.context.as_deref(),
+            );
+            self.record_action(fingerprint, &outcome);
+            prompts[i].state = QueueState::Done(outcome.clone());
+            self.publish_queue(win_name, &prompts);
+            // Only a prompt left open by an action is worth caching; a later
+            // handled one must not evict it. A deferred one would be retried
+            // ahead of every other window.
+            if matches!(outcome, ActionOutcome::Deferred { .. }) {
+                self.cached_ancestry.clear();
+            } else if !outcome.is_handled() {
+                self.remember_button(
+                    button,
+                    found.rule,
+                    found.context,
+                    window.clone(),
+                    window.clone(),
+                    win_name,
+                );
+            }
         }
+        self.restore_focus();
+    }
 
-        // 6. Broadcast Updated Snapshot
-        self.publish_snapshot(window_stats);
+    /// Drops the deferred prompts of `win_name` that its latest scan did not
+    /// find, so they are reported again should they come back.
+    fn forget_deferred(&mut self, win_name: &str, found: &[PromptFingerprint]) {
+        self.deferred_prompts
+            .retain(|fp| fp.window != win_name || found.contains(fp));
+    }
 
-        Ok(high_load_detected)
+    fn publish_queue(&self, win_name: &str, prompts: &[QueuedPrompt]) {
+        if let Some(tx) = &self.sender {
+            let _ = tx.send(Event::PromptQueue {
+                window: win_name.to_string(),
+                prompts: prompts.to_vec(),
+            });
+        }
     }
 
-    fn perform_action(&self, button: PlatformElement, _win_name: String) {
-        let btn_name = button.get_name().unwrap_or_default();
-        let mut success = false;
+    /// Time left before a quarantined window may be scanned again.
+    fn quarantine_remaining(&self, win_name: &str) -> Option<Duration> {
+        self.quarantined
+            .get(win_name)
+            .and_then(|q| q.until.checked_duration_since(Instant::now()))
+            .filter(|d| !d.is_zero())
+    }
 
-        // Try Invoke
-        if let Ok(_) = button.invoke() {
-            self.log(format!("Clicked '{}' (Invoke)", btn_name));
-            success = true;
+    /// Quarantines a window whose scan timed out, doubling the period on
+    /// each consecutive timeout. Returns the period.
+    fn quarantine(&mut self, win_name: &str, error: &anyhow::Error) -> Duration {
+        let strikes = self.quarantined.get(win_name).map_or(0, |q| q.strikes) + 1;
+        let backoff = (self.config.hung_backoff_secs.max(0.0) * 2f64.powi(strikes as i32 - 1))
+            .min(self.config.hung_backoff_max_secs.max(0.0));
+        let period = Duration::from_secs_f64(backoff);
+        self.quarantined.insert(
+            win_name.to_string(),
+            Quarantine {
+                strikes,
+                until: Instant::now() + period,
+            },
+        );
+        self.log(
+            LogEntry::new(
+                LogLevel::Warn,
+                LogCategory::Scan,
+                format!(
+                    "Not responding ({}); skipping for {:.0}s",
+                    error,
+                    period.as_secs_f64()
+                ),
+            )
+            .with_window(win_name),
+        );
+        period
+    }
+
+    fn release(&mut self, win_name: &str) {
+        if self.quarantined.remove(win_name).is_some() {
+            self.log(
+                LogEntry::new(
+                    LogLevel::Info,
+                    LogCategory::Scan,
+                    "Responding again".to_string(),
+                )
+                .with_window(win_name),
+            );
         }
+    }
+
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs_f64(self.config.cache_ttl_secs.max(0.0))
+    }
 
-        // Fallback: Click
-        if !success {
-            // Validate coordinates first
-            if let Ok(pt) = button.get_clickable_point() {
+    /// Checks that a cached button is still the same live, clickable element.
+    /// Returns its name, or why it must be dropped.
+    fn validate_cached_button(&self, cached: &CachedButton<B::Element>) -> Result<String, String> {
+        if cached.cached_at.elapsed() > self.cache_ttl() {
+            return Err("expired".to_string());
+        }
+        if self.quarantine_remaining(&cached.window).is_some() {
+            return Err("window not responding".to_string());
+        }
+        match cached.button.runtime_id() {
+            Ok(id) if id == cached.runtime_id => {}
+            // UIA may still return the cached name of a dead element, so
+            // identity is the liveness check
+            _ => return Err("stale element".to_string()),
+        }
+        if !cached.button.is_enabled().unwrap_or(false) {
+            return Err("disabled".to_string());
+        }
+        if cached.button.is_offscreen().unwrap_or(true) {
+            return Err("offscreen".to_string());
+        }
+        match cached.button.get_name() {
+            Ok(name) if !name.trim().is_empty() => Ok(name),
+            _ => Err("no name".to_string()),
+        }
+    }
+
+    fn remember_ancestry(&mut self, button: &B::Element, window: &B::Element, win_name: &str) {
+        if let Ok(mut new_ancestry) = self.query_service.get_ancestry(button) {
+            // Above the window, a scan would reach prompts of other windows
+            if let Some(top) = new_ancestry.iter().position(|a| a.same_as(window)) {
+                new_ancestry.truncate(top + 1);
+            }
+            self.cached_ancestry = new_ancestry;
+            self.cached_ancestry_at = Instant::now();
+            self.cached_ancestry_window = win_name.to_string();
+            self.cached_ancestry_window_element = Some(window.clone());
+        }
+    }
+
+    /// Caches `button` for Tier 1. Only worth it while its prompt is still
+    /// open.
+    fn remember_button(
+        &mut self,
+        button: B::Element,
+        rule: Rule,
+        context: Option<String>,
+        root: B::Element,
+        window: B::Element,
+        win_name: &str,
+    ) {
+        self.cached_button = None;
+        if let Ok(runtime_id) = button.runtime_id() {
+            self.cached_button = Some(CachedButton {
+                button,
+                rule,
+                context,
+                root,
+                window_element: window,
+                window: win_name.to_string(),
+                runtime_id,
+                cached_at: Instant::now(),
+            });
+        }
+    }
+
+    fn publish_metrics(&self) {
+        if let Some(tx) = &self.sender {
+            let _ = tx.send(Event::Metrics(self.metrics.clone()));
+        }
+    }
+
+    /// True (and counted) when the prompt was already handled within
+    /// `dedup_window_secs`, so it must not be acted on again.
+    fn suppress_duplicate(&mut self, fingerprint: &PromptFingerprint, btn_name: &str) -> bool {
+        if !self.recently_handled(fingerprint) {
+            return false;
+        }
+        self.metrics.suppressed += 1;
+        self.log(
+            LogEntry::new(
+                LogLevel::Debug,
+                LogCategory::Action,
+                format!("Suppressed repeat action on '{}'", btn_name),
+            )
+            .with_window(&fingerprint.window),
+        );
+        self.publish_metrics();
+        true
+    }
+
+    fn recently_handled(&mut self, fingerprint: &PromptFingerprint) -> bool {
+        let window = Duration::from_secs_f64(self.config.dedup_window_secs.max(0.0));
+        self.handled_prompts.retain(|_, at| at.elapsed() < window);
+        self.handled_prompts.contains_key(fingerprint)
+    }
+
+    /// Returns focus to where it was before `focus_window` steps took it,
+    /// reporting when neither the element nor its window got it back.
+    fn restore_focus(&mut self) {
+        match self.window_service.restore_previous_focus() {
+            Ok(FocusRestore::Window) => self.log(LogEntry::new(
+                LogLevel::Debug,
+                LogCategory::Action,
+                "Previously focused element is gone; focused its window instead".to_string(),
+            )),
+            Ok(_) => {}
+            Err(e) => {
+                self.log(LogEntry::new(
+                    LogLevel::Warn,
+                    LogCategory::Action,
+                    format!("Failed to restore focus: {}", e),
+                ));
+                if let Some(tx) = &self.sender {
+                    let _ = tx.send(Event::FocusRestoreFailed(e.to_string()));
+                }
+            }
+        }
+    }
+
+    fn record_action(&mut self, fingerprint: PromptFingerprint, outcome: &ActionOutcome) {
+        if matches!(outcome, ActionOutcome::Deferred { .. }) {
+            return;
+        }
+        self.metrics.actions += 1;
+        if outcome.is_handled() {
+            self.handled_prompts.insert(fingerprint, Instant::now());
+        }
+        self.publish_metrics();
+    }
+
+    /// Executes one method against `button`. Fails when the method is
+    /// unsupported or the element refused it.
+    fn try_method(
+        &self,
+        button: &B::Element,
+        method: &ActionMethod,
+        btn_name: &str,
+        win_name: &str,
+    ) -> anyhow::Result<()> {
+        match method {
+            ActionMethod::Invoke => {
+                button.invoke()?;
+                self.log(
+                    LogEntry::new(
+                        LogLevel::Info,
+                        LogCategory::Action,
+                        format!("Clicked '{}' (Invoke)", btn_name),
+                    )
+                    .with_window(win_name),
+                );
+            }
+            ActionMethod::Click => {
+                // Validate coordinates first
+                let pt = match button.get_clickable_point() {
+                    Ok(pt) => pt,
+                    Err(e) => {
+                        self.log(
+                            LogEntry::new(
+                                LogLevel::Warn,
+                                LogCategory::Action,
+                                format!("Skipping click: No clickable point for '{}'", btn_name),
+                            )
+                            .with_window(win_name),
+                        );
+                        return Err(e);
+                    }
+                };
                 // Check for (0,0) - simplified check
                 if pt.0 == 0 && pt.1 == 0 {
-                    self.log(format!(
-                        "Skipping click: Invalid coordinates (0,0) for '{}'",
-                        btn_name
+                    self.log(
+                        LogEntry::new(
+                            LogLevel::Warn,
+                            LogCategory::Action,
+                            format!(
+                                "Skipping click: Invalid coordinates (0,0) for '{}'",
+                                btn_name
+                            ),
+                        )
+                        .with_window(win_name),
+                    );
+                    return Err(anyhow::anyhow!("invalid coordinates (0,0)"));
+                }
+                button.click()?;
+                self.log(
+                    LogEntry::new(
+                        LogLevel::Info,
+                        LogCategory::Action,
+                        format!("Clicked '{}' (Click) at {:?}", btn_name, pt),
+                    )
+                    .with_window(win_name),
+                );
+            }
+            ActionMethod::Keys(keys) => {
+                // Keys go to whatever has focus, so aim it at the button
+                if let Err(e) = button.set_focus() {
+                    debug!("Could not focus '{}' before typing: {}", btn_name, e);
+                }
+                if let Err(e) = self.window_service.send_keys(keys) {
+                    self.log(
+                        LogEntry::new(
+                            LogLevel::Warn,
+                            LogCategory::Action,
+                            format!("Failed to send {} to '{}': {}", keys, btn_name, e),
+                        )
+                        .with_window(win_name),
+                    );
+                    return Err(e);
+                }
+                self.log(
+                    LogEntry::new(
+                        LogLevel::Info,
+                        LogCategory::Action,
+                        format!("Sent {} to '{}'", keys, btn_name),
+                    )
+                    .with_window(win_name),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `step` with its timeout, if any, applied to backend calls.
+    fn with_step_timeout<T>(
+        &mut self,
+        timeout: Option<Duration>,
+        step: impl FnOnce(&mut Self) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let Some(timeout) = timeout else {
+            return step(self);
+        };
+        self.window_service.set_call_timeout(timeout);
+        self.query_service.set_call_timeout(timeout);
+        let result = step(self);
+        let default = Duration::from_millis(self.config.call_timeout_ms);
+        self.window_service.set_call_timeout(default);
+        self.query_service.set_call_timeout(default);
+        result
+    }
+
+    /// True when `rule` no longer matches `button` under `root` of
+    /// `window`, so other prompts of the same rule do not keep it open. A
+    /// root that can no longer be scanned (window closed) also counts as
+    /// gone.
+    fn prompt_gone(
+        &self,
+        root: &B::Element,
+        window: &B::Element,
+        rule: &Rule,
+        button: &B::Element,
+    ) -> bool {
+        match self
+            .query_service
+            .scan_all_within(window, root, std::slice::from_ref(rule))
+        {
+            Ok((found, _)) => !found.iter().any(|m| m.button.same_as(button)),
+            Err(_) => true,
+        }
+    }
+
+    /// Decides from the user's activity and the foreground window whether
+    /// the next action may run and take focus.
+    fn focus_gate(&mut self) -> FocusGate {
+        let policy = self.config.focus_policy.clone();
+        let idle = match self.window_service.idle_time() {
+            Ok(idle) => Some(idle),
+            Err(e) => {
+                if !self.idle_unknown_logged {
+                    self.idle_unknown_logged = true;
+                    self.log(LogEntry::new(
+                        LogLevel::Warn,
+                        LogCategory::System,
+                        format!("Idle time unavailable: {}", e),
                     ));
-                } else {
-                    if let Ok(_) = button.click() {
-                        self.log(format!("Clicked '{}' (Click) at {:?}", btn_name, pt));
-                        success = true;
-                    }
                 }
-            } else {
-                self.log(format!(
-                    "Skipping click: No clickable point for '{}'",
-                    btn_name
-                ));
+                None
             }
+        };
+
+        match idle {
+            // Required idle time cannot be confirmed
+            None if policy.min_idle_secs > 0.0 => {
+                return FocusGate::Defer("idle time unknown".to_string());
+            }
+            Some(idle) if idle.as_secs_f64() < policy.min_idle_secs => {
+                return FocusGate::Defer(format!("user active {:.1}s ago", idle.as_secs_f64()));
+            }
+            _ => {}
+        }
+        if let Some(app) = self.foreground_title.as_deref().and_then(|title| {
+            policy
+                .protected_apps
+                .iter()
+                .find(|app| !app.trim().is_empty() && text::title_matches(title, app))
+        }) {
+            return FocusGate::NoFocus(format!("'{}' is in the foreground", app));
+        }
+        if policy.avoid_focus_when_active
+            && idle.is_some_and(|i| i.as_secs_f64() < policy.active_within_secs)
+        {
+            return FocusGate::NoFocus("user active".to_string());
+        }
+        FocusGate::Free
+    }
+
+    /// Runs the rule's action pipeline on `button` unless the focus policy
+    /// defers it, then reports the outcome.
+    fn perform_action(
+        &mut self,
+        button: &B::Element,
+        rule: &Rule,
+        root: &B::Element,
+        window: &B::Element,
+        win_name: &str,
+        context: Option<&str>,
+    ) -> ActionOutcome {
+        let btn_name = button.get_name().unwrap_or_default();
+        // Keyed on what the prompt shows, e.g. its command, so repeats of one
+        // command are told apart from different commands
+        let content = self
+            .query_service
+            .prompt_text(button)
+            .ok()
+            .filter(|text| !text.is_empty())
+            .or_else(|| context.map(str::to_string))
+            .unwrap_or_default();
+        let prompt = breaker::prompt_key(&btn_name, &content);
+        // Titles change with the open file or conversation
+        let window_key = window.runtime_id().unwrap_or_else(|_| win_name.to_string());
+        let gate = match self
+            .observe_reason(&window_key)
+            .or_else(|| self.check_breaker(&window_key, win_name, prompt))
+        {
+            Some(reason) => FocusGate::Defer(reason),
+            None => self.focus_gate(),
+        };
+        // A prompt stays deferred for many cycles; it is reported once
+        let fingerprint = PromptFingerprint::new(win_name, button, context);
+        if let FocusGate::Defer(reason) = &gate {
+            if !self.deferred_prompts.insert(fingerprint) {
+                return ActionOutcome::Deferred {
+                    reason: reason.clone(),
+                };
+            }
+        } else {
+            self.deferred_prompts.remove(&fingerprint);
         }
+        let (outcome, results) = match gate {
+            FocusGate::Defer(reason) => (ActionOutcome::Deferred { reason }, Vec::new()),
+            FocusGate::NoFocus(reason) => {
+                self.run_pipeline(button, rule, root, window, win_name, Some(&reason))
+            }
+            FocusGate::Free => self.run_pipeline(button, rule, root, window, win_name, None),
+        };
+        // An unverified action may still have accepted the prompt
+        if outcome.is_handled() || matches!(outcome, ActionOutcome::Unverified { .. }) {
+            self.breaker.record(&window_key, prompt, Instant::now());
+        }
+
+        let level = match outcome {
+            ActionOutcome::Verified { .. } | ActionOutcome::Performed { .. } => LogLevel::Info,
+            ActionOutcome::Unverified { .. } => LogLevel::Warn,
+            ActionOutcome::Failed { .. } => LogLevel::Error,
+            ActionOutcome::Deferred { .. } => LogLevel::Info,
+        };
+        self.log(
+            LogEntry::new(
+                level,
+                LogCategory::Action,
+                format!("Action on '{}': {}", btn_name, outcome.label()),
+            )
+            .with_window(win_name),
+        );
+        let summary = pipeline::summarize(&results);
+        self.log(
+            LogEntry::new(
+                LogLevel::Debug,
+                LogCategory::Action,
+                format!("Steps: {}", summary),
+            )
+            .with_window(win_name),
+        );
+        info!(
+            target: "audit",
+            window = win_name,
+            button = btn_name.as_str(),
+            rule = rule.name.as_str(),
+            outcome = outcome.label().as_str(),
+            steps = summary.as_str(),
+            "action"
+        );
+
+        self.status(
+            match outcome {
+                ActionOutcome::Verified { .. } | ActionOutcome::Performed { .. } => "Success!",
+                ActionOutcome::Unverified { .. } => "Unverified",
+                ActionOutcome::Failed { .. } => "Action Failed",
+                ActionOutcome::Deferred { .. } => "Deferred",
+            }
+            .to_string(),
+        );
+        if let Some(tx) = &self.sender {
+            let _ = tx.send(Event::ActionResult {
+                window: win_name.to_string(),
+                button: btn_name,
+                outcome: outcome.clone(),
+                steps: results,
+            });
+        }
+        outcome
+    }
 
-        if !success {
-            let err = "Failed to click button via Invoke or Click".to_string();
-            error!("{}", err);
-            self.log(format!("ERROR: {}", err));
-            self.status("Action Failed".to_string());
+    /// Runs the rule's action pipeline on `button`. A `verify` step re-scans
+    /// `root`; while the prompt stays open the pipeline is retried up to
+    /// `verify_retries` times, each fallback group starting after the method
+    /// that failed to close it. With `no_focus` set, steps that take focus
+    /// are blocked for that reason.
+    fn run_pipeline(
+        &mut self,
+        button: &B::Element,
+        rule: &Rule,
+        root: &B::Element,
+        window: &B::Element,
+        win_name: &str,
+        no_focus: Option<&str>,
+    ) -> (ActionOutcome, Vec<StepResult>) {
+        let btn_name = button.get_name().unwrap_or_default();
+        let verify_delay = Duration::from_millis(self.config.verify_delay_ms);
+        let steps = if rule.pipeline.is_empty() {
+            pipeline::default_pipeline()
+        } else {
+            rule.pipeline.clone()
+        };
+        let groups = pipeline::method_groups(&steps);
+        let verifies =
+            self.config.verify_actions && steps.iter().any(|s| s.kind == StepKind::Verify);
+        let max_attempts = if verifies {
+            1 + self.config.verify_retries
         } else {
-            self.status("Success!".to_string());
-            thread::sleep(Duration::from_millis(500));
+            1
+        };
+
+        // Offset of the method each fallback group tries first
+        let mut starts = vec![0; groups.len()];
+        let mut results: Vec<StepResult> = Vec::new();
+        let mut last_method = None;
+        let mut blocked_by = None;
+        let mut outcome = None;
+
+        'attempts: for attempt in 1..=max_attempts {
+            let mut i = 0;
+            while i < steps.len() {
+                let step = &steps[i];
+                if let Some(g) = groups.iter().position(|r| r.start == i) {
+                    let group = groups[g].clone();
+                    let mut used = None;
+                    let mut blocked = 0;
+                    for offset in 0..group.len() {
+                        let index = group.start + (starts[g] + offset) % group.len();
+                        let step = &steps[index];
+                        let Some(method) = step.method() else {
+                            continue;
+                        };
+                        if used.is_some() {
+                            results.push(StepResult::skipped(attempt, step));
+                            continue;
+                        }
+                        if let Some(reason) = no_focus.filter(|_| step.kind.takes_focus()) {
+                            results.push(StepResult::blocked(attempt, step, reason));
+                            blocked_by = Some(reason.to_string());
+                            blocked += 1;
+                            continue;
+                        }
+                        let started = Instant::now();
+                        let result = self.with_step_timeout(step.timeout, |this| {
+                            this.try_method(button, method, &btn_name, win_name)
+                        });
+                        if result.is_ok() {
+                            used = Some(offset);
+                            last_method = Some(method.clone());
+                        }
+                        results.push(StepResult::new(attempt, step, &result, started.elapsed()));
+                    }
+                    match used {
+                        Some(offset) => starts[g] = (starts[g] + offset + 1) % group.len(),
+                        // Retrying cannot help while the policy blocks them all
+                        None if blocked == group.len() => break 'attempts,
+                        // Nothing was done, so the rest of the attempt is moot
+                        None => continue 'attempts,
+                    }
+                    i = group.end;
+                    continue;
+                }
+
+                let started = Instant::now();
+                match &step.kind {
+                    StepKind::FocusWindow if no_focus.is_some() => {
+                        let reason = no_focus.unwrap_or_default();
+                        results.push(StepResult::blocked(attempt, step, reason));
+                    }
+                    StepKind::FocusWindow => {
+                        let result = self.with_step_timeout(step.timeout, |this| {
+                            this.window_service.focus_window(window)
+                        });
+                        results.push(StepResult::new(attempt, step, &result, started.elapsed()));
+                    }
+                    StepKind::Wait(duration) => {
+                        thread::sleep(*duration);
+                        results.push(StepResult::new(attempt, step, &Ok(()), started.elapsed()));
+                    }
+                    StepKind::Verify if !self.config.verify_actions => {
+                        results.push(StepResult::skipped(attempt, step));
+                    }
+                    StepKind::Verify => {
+                        let deadline = started + step.timeout.unwrap_or_default();
+                        let gone = loop {
+                            thread::sleep(verify_delay);
+                            if self.prompt_gone(root, window, rule, button) {
+                                break true;
+                            }
+                            if Instant::now() >= deadline {
+                                break false;
+                            }
+                        };
+                        let method = last_method.clone().unwrap_or(ActionMethod::Invoke);
+                        if gone {
+                            results.push(StepResult::new(
+                                attempt,
+                                step,
+                                &Ok(()),
+                                started.elapsed(),
+                            ));
+                            outcome = Some(ActionOutcome::Verified {
+                                method,
+                                attempts: attempt,
+                            });
+                            break 'attempts;
+                        }
+                        let still_open = Err(anyhow::anyhow!("prompt still open"));
+                        results.push(StepResult::new(
+                            attempt,
+                            step,
+                            &still_open,
+                            started.elapsed(),
+                        ));
+                        self.log(
+                            LogEntry::new(
+                                LogLevel::Warn,
+                                LogCategory::Action,
+                                format!(
+                                    "Prompt still open after {} on '{}' (attempt {}/{})",
+                                    method, btn_name, attempt, max_attempts
+                                ),
+                            )
+                            .with_window(win_name),
+                        );
+                        continue 'attempts;
+                    }
+                    StepKind::Method(_) => {}
+                }
+                i += 1;
+            }
+
+            // Ran to the end without a verify that counted
+            if let Some(method) = last_method.clone() {
+                if !verifies {
+                    outcome = Some(ActionOutcome::Performed { method });
+                    break;
+                }
+            }
         }
+
+        let outcome = outcome.unwrap_or(match (last_method, blocked_by) {
+            (Some(method), _) => ActionOutcome::Unverified {
+                method,
+                attempts: max_attempts,
+            },
+            // Nothing ran only because of the policy; try again later
+            (None, Some(reason)) => ActionOutcome::Deferred { reason },
+            (None, None) => ActionOutcome::Failed {
+                attempts: max_attempts,
+            },
+        });
+        (outcome, results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{ActionStep, StepStatus};
+    use crate::platform::fixture::{load_fixture, load_test_fixture, FixtureBackend};
+    use std::sync::mpsc;
+
+    fn automation(
+        backend: &FixtureBackend,
+        sender: Option<Sender<Event>>,
+    ) -> Automation<FixtureBackend> {
+        // Every window is due on every cycle
+        let config = AppConfig {
+            verify_delay_ms: 0,
+            interval: 0.0,
+            min_interval_secs: 0.0,
+            ..AppConfig::default()
+        };
+        Automation::with_services(
+            config,
+            sender,
+            WindowService::with_backend(backend.clone()),
+            QueryService::with_backend(backend.clone()),
+        )
+    }
+
+    fn clicks(backend: &FixtureBackend) -> Vec<String> {
+        backend
+            .actions()
+            .into_iter()
+            .filter(|a| a.kind == "invoke" || a.kind == "click")
+            .map(|a| format!("{} {}", a.kind, a.path))
+            .collect()
+    }
+
+    fn outcomes(rx: &mpsc::Receiver<Event>) -> Vec<ActionOutcome> {
+        rx.try_iter()
+            .filter_map(|e| match e {
+                Event::ActionResult { outcome, .. } => Some(outcome),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cycle_accepts_recorded_prompt() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let (tx, rx) = mpsc::channel();
+        automation(&backend, Some(tx)).cycle().unwrap();
+        assert_eq!(
+            clicks(&backend),
+            vec!["invoke Window/Pane[2]/Group[1]/Button[3]"]
+        );
+        assert_eq!(
+            outcomes(&rx),
+            vec![ActionOutcome::Verified {
+                method: ActionMethod::Invoke,
+                attempts: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn cycle_ignores_idle_and_non_target_windows() {
+        let backend = load_test_fixture("agent_manager_idle.json");
+        automation(&backend, None).cycle().unwrap();
+        assert!(clicks(&backend).is_empty());
+    }
+
+    #[test]
+    fn handled_prompt_is_not_clicked_again() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let mut automation = automation(&backend, None);
+        for _ in 0..3 {
+            automation.cycle().unwrap();
+        }
+        assert_eq!(
+            clicks(&backend),
+            vec!["invoke Window/Pane[2]/Group[1]/Button[3]"]
+        );
+    }
+
+    #[test]
+    fn repeat_detections_of_handled_prompt_are_suppressed() {
+        // With verification off a slow UI can still show the prompt afterwards
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        fixture.dismiss_on_action = false;
+        let backend = FixtureBackend::from_fixture(&fixture);
+
+        let (tx, rx) = mpsc::channel();
+        let mut automation = automation(&backend, Some(tx));
+        automation.config.verify_actions = false;
+        for _ in 0..3 {
+            automation.cycle().unwrap();
+        }
+        assert_eq!(
+            clicks(&backend),
+            vec!["invoke Window/Pane[2]/Group[1]/Button[3]"]
+        );
+        let last = rx
+            .try_iter()
+            .filter_map(|e| match e {
+                Event::Metrics(m) => Some(m),
+                _ => None,
+            })
+            .last();
+        assert_eq!(
+            last,
+            Some(Metrics {
+                actions: 1,
+                suppressed: 2
+            })
+        );
+    }
+
+    #[test]
+    fn open_prompt_reuses_cached_button_until_expired() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        fixture.dismiss_on_action = false;
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let cache_hits = |rx: &mpsc::Receiver<Event>| {
+            rx.try_iter()
+                .filter(
+                    |e| matches!(e, Event::Log(l) if l.message.starts_with("Using Cached Button")),
+                )
+                .count()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let mut cached = automation(&backend, Some(tx));
+        cached.cycle().unwrap();
+        cached.cycle().unwrap();
+        assert_eq!(cache_hits(&rx), 1);
+
+        let (tx, rx) = mpsc::channel();
+        let mut expired = automation(&backend, Some(tx));
+        expired.config.cache_ttl_secs = 0.0;
+        expired.cycle().unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        expired.cycle().unwrap();
+        assert_eq!(cache_hits(&rx), 0);
+    }
+
+    #[test]
+    fn hung_window_is_quarantined_without_blocking_others() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        let mut editor = fixture.windows[0].clone();
+        editor.window = "Editor - Antigravity".to_string();
+        editor.root.name = editor.window.clone();
+        fixture.windows.push(editor);
+        fixture.hung_windows = vec!["Agent Manager - Antigravity".to_string()];
+        let backend = FixtureBackend::from_fixture(&fixture);
+
+        let (tx, rx) = mpsc::channel();
+        let mut automation = automation(&backend, Some(tx));
+        automation.cycle().unwrap();
+        automation.cycle().unwrap();
+        assert_eq!(
+            clicks(&backend),
+            vec!["invoke Window/Pane[2]/Group[1]/Button[3]"]
+        );
+
+        let events: Vec<Event> = rx.try_iter().collect();
+        let timeouts = events
+            .iter()
+            .filter(|e| matches!(e, Event::Log(l) if l.message.starts_with("Not responding")))
+            .count();
+        assert_eq!(timeouts, 1, "the second cycle must skip the hung window");
+        let Some(Event::VisibleWindows(stats)) = events
+            .iter()
+            .rev()
+            .find(|e| matches!(e, Event::VisibleWindows(_)))
+        else {
+            panic!("no window snapshot published");
+        };
+        let hung: Vec<(&str, bool)> = stats
+            .iter()
+            .map(|w| (w.title.as_str(), w.hung_for.is_some()))
+            .collect();
+        assert_eq!(
+            hung,
+            vec![
+                ("Agent Manager - Antigravity", true),
+                ("Editor - Antigravity", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn deferred_prompt_does_not_hold_up_other_windows() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        let mut editor = fixture.windows[0].clone();
+        editor.window = "Editor - Antigravity".to_string();
+        editor.root.name = editor.window.clone();
+        fixture.windows.push(editor);
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let (tx, rx) = mpsc::channel();
+        let mut automation = automation(&backend, Some(tx));
+
+        // Agent Manager's prompt was left open by an earlier action and is
+        // cached; its next accept goes over the limit
+        let manager = backend.get_all_windows().unwrap().remove(0);
+        let manager_name = manager.get_name().unwrap();
+        let (found, _) = automation
+            .query_service
+            .scan_all_with_stats(&manager, &automation.rules)
+            .unwrap();
+        let found = found.into_iter().next().unwrap();
+        automation.remember_ancestry(&found.button, &manager, &manager_name);
+        automation.remember_button(
+            found.button,
+            found.rule,
+            found.context,
+            manager.clone(),
+            manager.clone(),
+            &manager_name,
+        );
+        automation.breaker = CircuitBreaker::new(crate::breaker::BreakerLimits {
+            max_per_minute: 1,
+            max_per_hour: 0,
+            max_repeats: 0,
+        });
+        let manager_key = manager.runtime_id().unwrap();
+        automation.breaker.record(&manager_key, 0, Instant::now());
+
+        // Reported once, though the full scan finds it again
+        automation.cycle().unwrap();
+        let results = |rx: &mpsc::Receiver<Event>| -> Vec<(String, bool)> {
+            rx.try_iter()
+                .filter_map(|e| match e {
+                    Event::ActionResult {
+                        window, outcome, ..
+                    } => Some((window, matches!(outcome, ActionOutcome::Deferred { .. }))),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(
+            results(&rx),
+            vec![
+                (manager_name.clone(), true),
+                ("Editor - Antigravity".to_string(), false)
+            ]
+        );
+        assert_eq!(clicks(&backend).len(), 1);
+        assert!(automation.cached_button.is_none());
+
+        automation.cycle().unwrap();
+        assert!(results(&rx).is_empty());
+    }
+
+    #[test]
+    fn every_prompt_in_a_window_is_queued_top_to_bottom() {
+        let backend = load_test_fixture("agent_manager_two_prompts.json");
+        let (tx, rx) = mpsc::channel();
+        automation(&backend, Some(tx)).cycle().unwrap();
+        assert_eq!(
+            clicks(&backend),
+            vec![
+                "invoke Window/Pane[3]/Group[0]/Button[3]",
+                "invoke Window/Pane[2]/Group[1]/Button[3]"
+            ]
+        );
+
+        let queue = rx
+            .try_iter()
+            .filter_map(|e| match e {
+                Event::PromptQueue { prompts, .. } => Some(prompts),
+                _ => None,
+            })
+            .last()
+            .unwrap();
+        let verified = QueueState::Done(ActionOutcome::Verified {
+            method: ActionMethod::Invoke,
+            attempts: 1,
+        });
+        assert_eq!(queue.len(), 2);
+        assert!(queue.iter().all(|p| p.state == verified));
+    }
+
+    #[test]
+    fn full_scan_finds_prompts_outside_the_anchor() {
+        let mut fixture = load_fixture("agent_manager_two_prompts.json");
+        fixture.dismiss_on_action = false;
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let (tx, rx) = mpsc::channel();
+        let mut automation = automation(&backend, Some(tx));
+        automation.config.verify_actions = false;
+        for rule in &mut automation.rules {
+            rule.traversal.anchor = true;
+        }
+        let mut queued = Vec::new();
+        for _ in 0..2 {
+            automation.cycle().unwrap();
+            let queue = rx
+                .try_iter()
+                .filter_map(|e| match e {
+                    Event::PromptQueue { prompts, .. } => Some(prompts),
+                    _ => None,
+                })
+                .last()
+                .unwrap();
+            queued.push(queue.len());
+        }
+
+        // The prompts stay open, and the anchor holds only one of them
+        assert_eq!(queued, vec![2, 2]);
+    }
+
+    #[test]
+    fn rule_can_accept_with_keyboard() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let (tx, rx) = mpsc::channel();
+        let mut automation = automation(&backend, Some(tx));
+        let pipeline: Vec<ActionStep> =
+            serde_json::from_str(r#"["keys:Enter", "verify"]"#).unwrap();
+        for rule in &mut automation.rules {
+            rule.pipeline = pipeline.clone();
+        }
+        automation.cycle().unwrap();
+
+        let button = "Window/Pane[2]/Group[1]/Button[3]";
+        let typed: Vec<String> = backend
+            .actions()
+            .into_iter()
+            .filter(|a| a.kind == "keys")
+            .map(|a| format!("{} {}", a.name, a.path))
+            .collect();
+        assert_eq!(typed, vec![format!("Enter {}", button)]);
+        assert!(clicks(&backend).is_empty());
+        assert_eq!(
+            outcomes(&rx),
+            vec![ActionOutcome::Verified {
+                method: ActionMethod::Keys("Enter".parse().unwrap()),
+                attempts: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn rule_pipeline_runs_in_order_and_records_steps() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let (tx, rx) = mpsc::channel();
+        let mut automation = automation(&backend, Some(tx));
+        let pipeline: Vec<ActionStep> = serde_json::from_str(
+            r#"["focus_window", "click", "invoke", "wait:1ms", {"step": "verify", "timeout_ms": 50}]"#,
+        )
+        .unwrap();
+        for rule in &mut automation.rules {
+            rule.pipeline = pipeline.clone();
+        }
+        automation.cycle().unwrap();
+
+        assert_eq!(
+            clicks(&backend),
+            vec!["click Window/Pane[2]/Group[1]/Button[3]"]
+        );
+        let steps: Vec<(String, StepStatus)> = rx
+            .try_iter()
+            .find_map(|e| match e {
+                Event::ActionResult { steps, .. } => Some(steps),
+                _ => None,
+            })
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.step, r.status))
+            .collect();
+        let expected = [
+            ("focus_window", StepStatus::Ok),
+            ("click", StepStatus::Ok),
+            ("invoke", StepStatus::Skipped),
+            ("wait:1ms", StepStatus::Ok),
+            ("verify", StepStatus::Ok),
+        ];
+        let expected: Vec<(String, StepStatus)> = expected
+            .into_iter()
+            .map(|(step, status)| (step.to_string(), status))
+            .collect();
+        assert_eq!(steps, expected);
+    }
+
+    #[test]
+    fn active_user_keeps_focus_and_can_defer_actions() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        fixture.idle_secs = Some(1.0);
+
+        // Typing a second ago: no focus change, Invoke only
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let (tx, rx) = mpsc::channel();
+        automation(&backend, Some(tx)).cycle().unwrap();
+        assert!(backend.actions().iter().all(|a| a.kind != "focus"));
+        assert_eq!(
+            clicks(&backend),
+            vec!["invoke Window/Pane[2]/Group[1]/Button[3]"]
+        );
+        assert_eq!(
+            outcomes(&rx),
+            vec![ActionOutcome::Verified {
+                method: ActionMethod::Invoke,
+                attempts: 1
+            }]
+        );
+
+        // Required idle time not reached: the prompt is left alone
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let (tx, rx) = mpsc::channel();
+        let mut deferred = automation(&backend, Some(tx));
+        deferred.config.focus_policy.min_idle_secs = 30.0;
+        deferred.cycle().unwrap();
+        assert!(backend.actions().is_empty());
+        assert_eq!(
+            outcomes(&rx),
+            vec![ActionOutcome::Deferred {
+                reason: "user active 1.0s ago".to_string()
+            }]
+        );
+
+        // Unknown idle time never counts as idle
+        fixture.idle_secs = None;
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let (tx, rx) = mpsc::channel();
+        let mut unknown = automation(&backend, Some(tx));
+        unknown.config.focus_policy.min_idle_secs = 30.0;
+        unknown.cycle().unwrap();
+        assert!(backend.actions().is_empty());
+        assert_eq!(
+            outcomes(&rx),
+            vec![ActionOutcome::Deferred {
+                reason: "idle time unknown".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn focus_returns_to_previous_window_after_action() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        let idle = load_fixture("agent_manager_idle.json");
+        let notes = idle.windows[1].clone();
+        fixture.focused_window = Some(notes.window.clone());
+        fixture.minimized_windows = vec![fixture.windows[0].window.clone()];
+        fixture.windows.push(notes.clone());
+
+        // Agent Manager is restored to act on, then minimized again
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let (tx, rx) = mpsc::channel();
+        automation(&backend, Some(tx)).cycle().unwrap();
+        let kinds: Vec<String> = backend
+            .actions()
+            .iter()
+            .map(|a| format!("{} {}", a.kind, a.name))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "restore Agent Manager - Antigravity",
+                "focus Agent Manager - Antigravity",
+                "invoke Accept",
+                "minimize Agent Manager - Antigravity",
+                "focus Run command? - Notes",
+            ]
+        );
+        assert!(rx
+            .try_iter()
+            .all(|e| !matches!(e, Event::FocusRestoreFailed(_))));
+
+        // Notes refuses focus: every retry fails and the failure is reported
+        fixture.unfocusable_windows = vec![notes.window.clone()];
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let (tx, rx) = mpsc::channel();
+        automation(&backend, Some(tx)).cycle().unwrap();
+        let failures: Vec<String> = rx
+            .try_iter()
+            .filter_map(|e| match e {
+                Event::FocusRestoreFailed(reason) => Some(reason),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            failures,
+            vec!["Focus did not return to 'Run command? - Notes' after 3 attempts"]
+        );
+    }
+
+    #[test]
+    fn prompts_are_only_observed_outside_active_hours() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let (tx, rx) = mpsc::channel();
+        let mut automation = automation(&backend, Some(tx));
+        automation.config.active_hours =
+            serde_json::from_str(r#"{"timezone": "UTC", "ranges": ["Daily 22:00-06:00"]}"#)
+                .unwrap();
+        let noon = DateTime::parse_from_rfc3339("2026-10-19T12:00:00Z")
+            .unwrap()
+            .to_utc();
+
+        automation.update_schedule(noon);
+        automation.cycle().unwrap();
+        automation.cycle().unwrap();
+        assert!(backend.actions().is_empty());
+        let events: Vec<Event> = rx.try_iter().collect();
+        assert!(events.iter().any(|e| matches!(
+            e,
+            Event::Schedule(ScheduleState { active: false, next_change: Some(at) })
+                if at.to_rfc3339() == "2026-10-19T22:00:00+00:00"
+        )));
+        // Once per prompt, not per cycle
+        let deferred = events
+            .iter()
+            .filter(|e| {
+                matches!(
+                    e,
+                    Event::ActionResult { outcome: ActionOutcome::Deferred { reason }, .. }
+                        if reason == "outside active hours"
+                )
+            })
+            .count();
+        assert_eq!(deferred, 1);
+
+        // The prompt is still open when active hours begin
+        automation.update_schedule(noon + chrono::Duration::hours(10));
+        automation.cycle().unwrap();
+        assert_eq!(
+            clicks(&backend),
+            vec!["invoke Window/Pane[2]/Group[1]/Button[3]"]
+        );
+    }
+
+    #[test]
+    fn breaker_trips_window_into_observe_only_until_reset() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        fixture.dismiss_on_action = false;
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let (tx, rx) = mpsc::channel();
+        let mut automation = automation(&backend, Some(tx));
+        automation.config.verify_retries = 0;
+        automation.breaker = CircuitBreaker::new(crate::breaker::BreakerLimits {
+            max_per_minute: 0,
+            max_per_hour: 0,
+            max_repeats: 2,
+        });
+
+        // The prompt stays open; a third accept would repeat it once more
+        for _ in 0..3 {
+            automation.cycle().unwrap();
+        }
+        assert_eq!(clicks(&backend).len(), 2);
+        let events: Vec<Event> = rx.try_iter().collect();
+        assert!(events.iter().any(|e| matches!(
+            e,
+            Event::BreakerTripped { window, trip }
+                if window == "Agent Manager - Antigravity"
+                    && trip.reason == "same prompt accepted 2 times in a row"
+        )));
+
+        // Still tripped on later cycles
+        automation.cycle().unwrap();
+        assert_eq!(clicks(&backend).len(), 2);
+
+        automation.reset_breaker(None);
+        automation.cycle().unwrap();
+        assert_eq!(clicks(&backend).len(), 3);
+        assert!(rx
+            .try_iter()
+            .any(|e| matches!(e, Event::BreakerReset(w) if w == "Agent Manager - Antigravity")));
+    }
+
+    #[test]
+    fn breaker_counts_repeats_per_command() {
+        let mut fixture = load_fixture("agent_manager_two_prompts.json");
+        fixture.dismiss_on_action = false;
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let mut automation = automation(&backend, None);
+        automation.config.verify_actions = false;
+        automation.breaker = CircuitBreaker::new(crate::breaker::BreakerLimits {
+            max_per_minute: 0,
+            max_per_hour: 0,
+            max_repeats: 2,
+        });
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let win_name = window.get_name().unwrap();
+        let (prompts, _) = automation
+            .query_service
+            .scan_all_with_stats(&window, &automation.rules)
+            .unwrap();
+        // npm install, then cargo test --workspace
+        assert_eq!(prompts.len(), 2);
+        let mut accept = |i: usize| {
+            let found = &prompts[i];
+            automation.perform_action(
+                &found.button,
+                &found.rule,
+                &window,
+                &window,
+                &win_name,
+                found.context.as_deref(),
+            )
+        };
+
+        // Same button and context, different commands: never a repeat
+        for i in [0, 1, 0, 1, 0] {
+            assert!(accept(i).is_handled());
+        }
+        assert!(accept(0).is_handled());
+        assert!(matches!(
+            accept(0),
+            ActionOutcome::Deferred { reason }
+                if reason == "circuit breaker tripped: same prompt accepted 2 times in a row"
+        ));
+    }
+
+    #[test]
+    fn stuck_prompt_alternates_methods_and_reports_unverified() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        fixture.dismiss_on_action = false;
+        let backend = FixtureBackend::from_fixture(&fixture);
+
+        let (tx, rx) = mpsc::channel();
+        automation(&backend, Some(tx)).cycle().unwrap();
+        let button = "Window/Pane[2]/Group[1]/Button[3]";
+        assert_eq!(
+            clicks(&backend),
+            vec![
+                format!("invoke {}", button),
+                format!("click {}", button),
+                format!("invoke {}", button),
+            ]
+        );
+        assert_eq!(
+            outcomes(&rx),
+            vec![ActionOutcome::Unverified {
+                method: ActionMethod::Invoke,
+                attempts: 3
+            }]
+        );
     }
 }
@@ -0,0 +1,275 @@
+use crate::config::AppConfig;
+use crate::text;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Accept limits per target window; 0 turns a limit off. Going over one
+/// trips the window into observe-only mode until it is reset.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct BreakerLimits {
+    pub max_per_minute: u32,
+    pub max_per_hour: u32,
+    /// Accepts of the same prompt text in a row, within the last hour.
+    pub max_repeats: u32,
+}
+
+impl Default for BreakerLimits {
+    fn default() -> Self {
+        Self {
+            max_per_minute: 10,
+            max_per_hour: 120,
+            max_repeats: 5,
+        }
+    }
+}
+
+/// Why and when a window's breaker tripped.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trip {
+    /// Title of the window when it tripped, for display and `reset-breaker`.
+    #[serde(default)]
+    pub window: String,
+    pub reason: String,
+    pub at: DateTime<Local>,
+}
+
+/// Where trips are kept so they survive restarts and `reset-breaker` can
+/// clear them: `breaker.json` next to the config file.
+pub fn state_path() -> PathBuf {
+    AppConfig::get_config_path().with_file_name("breaker.json")
+}
+
+/// Tripped windows by identity. A missing file means none.
+pub fn load_trips(path: &Path) -> Result<BTreeMap<String, Trip>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+pub fn save_trips(path: &Path, trips: &BTreeMap<String, Trip>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(trips)?)?;
+    Ok(())
+}
+
+/// Identity of a prompt's text, so repeats of one command are recognized
+/// across prompts. `content` is what the prompt shows with the button,
+/// such as the command it asks to run.
+pub fn prompt_key(button: &str, content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text::normalize(button).hash(&mut hasher);
+    text::normalize(content).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Counts accepts per window and trips windows that go over `BreakerLimits`.
+/// Windows are keyed by their identity (UIA runtime ID, AT-SPI bus and
+/// path) rather than their title, which changes with the open file or
+/// conversation; the identity lasts as long as the window, across restarts
+/// of ag-accept.
+pub struct CircuitBreaker {
+    limits: BreakerLimits,
+    /// Accepts of the last hour per window, oldest first, with their
+    /// `prompt_key`.
+    history: HashMap<String, VecDeque<(Instant, u64)>>,
+    trips: BTreeMap<String, Trip>,
+    /// Trips are persisted here; `None` keeps them in memory.
+    path: Option<PathBuf>,
+}
+
+impl CircuitBreaker {
+    pub fn new(limits: BreakerLimits) -> Self {
+        Self {
+            limits,
+            history: HashMap::new(),
+            trips: BTreeMap::new(),
+            path: None,
+        }
+    }
+
+    /// Keeps trips in `path`, starting with the ones already there.
+    pub fn persist_to(&mut self, path: PathBuf) {
+        match load_trips(&path) {
+            Ok(trips) => self.trips = trips,
+            Err(e) => warn!("Ignoring unreadable {}: {}", path.display(), e),
+        }
+        self.path = Some(path);
+    }
+
+    pub fn trip(&self, window: &str) -> Option<&Trip> {
+        self.trips.get(window)
+    }
+
+    pub fn trips(&self) -> &BTreeMap<String, Trip> {
+        &self.trips
+    }
+
+    /// Trips `window`, titled `title`, when accepting `prompt` at `now` would
+    /// go over a limit, returning the new trip.
+    pub fn check(&mut self, window: &str, title: &str, prompt: u64, now: Instant) -> Option<Trip> {
+        if self.trips.contains_key(window) {
+            return None;
+        }
+        let history = self.history.entry(window.to_string()).or_default();
+        while history
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) >= HOUR)
+        {
+            history.pop_front();
+        }
+
+        let last_minute = history
+            .iter()
+            .filter(|(at, _)| now.saturating_duration_since(*at) < MINUTE)
+            .count();
+        let repeats = history
+            .iter()
+            .rev()
+            .take_while(|(_, key)| *key == prompt)
+            .count();
+        let limits = &self.limits;
+        let reason = if over(last_minute, limits.max_per_minute) {
+            format!("{} accepts in the last minute", last_minute)
+        } else if over(history.len(), limits.max_per_hour) {
+            format!("{} accepts in the last hour", history.len())
+        } else if over(repeats, limits.max_repeats) {
+            format!("same prompt accepted {} times in a row", repeats)
+        } else {
+            return None;
+        };
+
+        let trip = Trip {
+            window: title.to_string(),
+            reason,
+            at: Local::now(),
+        };
+        self.trips.insert(window.to_string(), trip.clone());
+        self.save();
+        Some(trip)
+    }
+
+    /// Counts an accept of `prompt` in `window`.
+    pub fn record(&mut self, window: &str, prompt: u64, now: Instant) {
+        self.history
+            .entry(window.to_string())
+            .or_default()
+            .push_back((now, prompt));
+    }
+
+    /// Clears the trip of the window titled `title`, or of every window, and
+    /// returns the titles of the windows that were tripped.
+    pub fn reset(&mut self, title: Option<&str>) -> Vec<String> {
+        let reset: Vec<String> = self
+            .trips
+            .iter()
+            .filter(|(_, trip)| title.is_none_or(|title| trip.window == title))
+            .map(|(window, _)| window.clone())
+            .collect();
+        let titles = self.forget(&reset);
+        if !reset.is_empty() {
+            self.save();
+        }
+        titles
+    }
+
+    /// Picks up trips cleared in the state file by `reset-breaker`,
+    /// returning the windows that are no longer tripped.
+    pub fn sync(&mut self) -> Vec<String> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        if self.trips.is_empty() {
+            return Vec::new();
+        }
+        let Ok(stored) = load_trips(path) else {
+            return Vec::new();
+        };
+        let cleared: Vec<String> = self
+            .trips
+            .keys()
+            .filter(|w| !stored.contains_key(*w))
+            .cloned()
+            .collect();
+        self.forget(&cleared)
+    }
+
+    /// Drops the trips and counts of `windows`, returning their titles.
+    fn forget(&mut self, windows: &[String]) -> Vec<String> {
+        windows
+            .iter()
+            .filter_map(|window| {
+                self.history.remove(window);
+                self.trips.remove(window).map(|trip| trip.window)
+            })
+            .collect()
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            if let Err(e) = save_trips(path, &self.trips) {
+                warn!("Failed to save {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+fn over(count: usize, limit: u32) -> bool {
+    limit > 0 && count >= limit as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trips_on_rate_and_repeats_until_reset() {
+        let limits = BreakerLimits {
+            max_per_minute: 3,
+            max_per_hour: 0,
+            max_repeats: 2,
+        };
+        let mut breaker = CircuitBreaker::new(limits);
+        let start = Instant::now();
+        let (a, b) = (
+            prompt_key("Accept", "Run command?\nnpm test"),
+            prompt_key("Accept", "Run command?\ncargo test"),
+        );
+
+        // Alternating prompts stay under the repeat limit
+        for (i, key) in [a, b, a].into_iter().enumerate() {
+            let now = start + Duration::from_secs(i as u64);
+            assert_eq!(breaker.check("w", "a.rs - Antigravity", key, now), None);
+            breaker.record("w", key, now);
+        }
+        // Counted by identity, so a new title does not start over
+        let trip = breaker
+            .check("w", "b.rs - Antigravity", b, start + Duration::from_secs(3))
+            .unwrap();
+        assert_eq!(trip.reason, "3 accepts in the last minute");
+        assert_eq!(trip.window, "b.rs - Antigravity");
+        assert_eq!(breaker.check("other", "b.rs - Antigravity", b, start), None);
+
+        // After a reset the minute has passed, but `a` twice in a row trips
+        assert_eq!(breaker.reset(None), vec!["b.rs - Antigravity"]);
+        let later = start + Duration::from_secs(120);
+        breaker.record("w", a, later);
+        breaker.record("w", a, later);
+        let trip = breaker.check("w", "a.rs - Antigravity", a, later).unwrap();
+        assert_eq!(trip.reason, "same prompt accepted 2 times in a row");
+    }
+}
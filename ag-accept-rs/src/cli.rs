@@ -0,0 +1,280 @@
+use crate::automation::{Automation, Event};
+use crate::breaker;
+use crate::config::AppConfig;
+use crate::platform::fixture::{Fixture, FixtureBackend};
+use crate::platform::{Backend, Element, PlatformBackend};
+use crate::selector::Selector;
+use crate::services::query::QueryService;
+use crate::services::snapshot::{self, DiffKind, SnapshotFormat, SnapshotService, TreeSnapshot};
+use crate::services::window::WindowService;
+use crate::text;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use crossterm::style::Stylize;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Auto-accepts Antigravity prompts. Runs the TUI when no command is given."
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Save a window's accessibility tree to a file
+    DumpTree {
+        /// Window title (substring, normalized like rule texts). Defaults to target_window_title.
+        #[arg(short, long)]
+        window: Option<String>,
+        /// Output file. Defaults to tree_<window>_<timestamp>.<ext> in the current directory.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(short, long, value_enum, default_value_t = SnapshotFormat::Json)]
+        format: SnapshotFormat,
+        #[arg(long, default_value_t = 64)]
+        max_depth: usize,
+    },
+    /// Show what changed between two JSON tree dumps
+    DiffTree { before: PathBuf, after: PathBuf },
+    /// Record all target windows into a replayable fixture file
+    RecordFixture {
+        output: PathBuf,
+        /// Window title (substring, normalized like rule texts). Defaults to target_window_title.
+        #[arg(short, long)]
+        window: Option<String>,
+        #[arg(long, default_value_t = 64)]
+        max_depth: usize,
+    },
+    /// Run one automation cycle against a fixture and print what it would do
+    Replay { fixture: PathBuf },
+    /// Print the elements a selector matches, e.g. 'Group >> Button[name="Accept"]'
+    TestSelector {
+        selector: String,
+        /// Window title (substring, normalized like rule texts). Defaults to target_window_title
+        /// for live windows and to every window of a dump.
+        #[arg(short, long)]
+        window: Option<String>,
+        /// JSON tree dump or fixture to search instead of the live desktop.
+        #[arg(short, long)]
+        dump: Option<PathBuf>,
+    },
+    /// Clear tripped circuit breakers so their windows are acted on again. A running
+    /// instance picks this up on its next cycle.
+    ResetBreaker {
+        /// Exact title the window had when it tripped. Defaults to every tripped window.
+        #[arg(short, long)]
+        window: Option<String>,
+    },
+}
+
+pub fn run(command: Command, config: &AppConfig) -> Result<()> {
+    match command {
+        Command::DumpTree {
+            window,
+            output,
+            format,
+            max_depth,
+        } => {
+            let service = SnapshotService::new()?;
+            let title = window.unwrap_or_else(|| config.target_window_title.clone());
+            let (name, element) = service.find_window(&title)?;
+            let tree = service.capture(&element, &name, max_depth)?;
+            let path = output.unwrap_or_else(|| TreeSnapshot::default_path(&name, format));
+            tree.save(&path, format)?;
+            println!(
+                "Saved {} nodes of '{}' to {}",
+                tree.node_count(),
+                name,
+                path.display()
+            );
+        }
+        Command::DiffTree { before, after } => {
+            let before = TreeSnapshot::load(&before)?;
+            let after = TreeSnapshot::load(&after)?;
+            let changes = snapshot::diff(&before, &after);
+            if changes.is_empty() {
+                println!("No differences.");
+            }
+            for change in &changes {
+                let line = format!("{}  [{}]", change.description, change.path);
+                match change.kind {
+                    DiffKind::Added => println!("{}", format!("+ {}", line).green()),
+                    DiffKind::Removed => println!("{}", format!("- {}", line).red()),
+                    DiffKind::Changed => println!("{}", format!("~ {}", line).yellow()),
+                }
+            }
+        }
+        Command::RecordFixture {
+            output,
+            window,
+            max_depth,
+        } => {
+            let service = SnapshotService::new()?;
+            let title = window.unwrap_or_else(|| config.target_window_title.clone());
+            let windows = service.find_windows(&title)?;
+            if windows.is_empty() {
+                return Err(anyhow!("No window matching '{}'", title));
+            }
+            let mut snapshots = Vec::new();
+            for (name, element) in &windows {
+                snapshots.push(service.capture(element, name, max_depth)?);
+            }
+            let fixture = Fixture {
+                focused_window: None,
+                windows: snapshots,
+                dismiss_on_action: true,
+                hung_windows: Vec::new(),
+                idle_secs: None,
+                minimized_windows: Vec::new(),
+                maximized_windows: Vec::new(),
+                unfocusable_windows: Vec::new(),
+            };
+            fixture.save(&output)?;
+            println!(
+                "Recorded {} window(s) to {}",
+                windows.len(),
+                output.display()
+            );
+        }
+        Command::Replay { fixture } => {
+            let backend = FixtureBackend::load(&fixture)?;
+            let (tx, rx) = mpsc::channel();
+            let mut automation = Automation::with_services(
+                config.clone(),
+                Some(tx),
+                WindowService::with_backend(backend.clone()),
+                QueryService::with_backend(backend.clone()),
+            );
+            automation.cycle()?;
+            drop(automation);
+
+            for event in rx.try_iter() {
+                if let Event::Log(entry) = event {
+                    println!(
+                        "{:<5} [{}] {}",
+                        entry.level.label(),
+                        entry.category.label(),
+                        entry.message
+                    );
+                }
+            }
+            let actions = backend.actions();
+            if actions.is_empty() {
+                println!("{}", "No action taken.".yellow());
+            }
+            for action in actions {
+                println!(
+                    "{}",
+                    format!("{} \"{}\"  [{}]", action.kind, action.name, action.path).green()
+                );
+            }
+        }
+        Command::TestSelector {
+            selector,
+            window,
+            dump,
+        } => {
+            let selector: Selector = selector.parse()?;
+            let total = match dump {
+                Some(dump) => {
+                    let backend = FixtureBackend::load(&dump)?;
+                    print_selector_matches(
+                        &WindowService::with_backend(backend.clone()),
+                        &QueryService::with_backend(backend),
+                        window.as_deref(),
+                        &selector,
+                    )?
+                }
+                None => {
+                    let title = window.unwrap_or_else(|| config.target_window_title.clone());
+                    print_selector_matches(
+                        &WindowService::<PlatformBackend>::new()?,
+                        &QueryService::<PlatformBackend>::new()?,
+                        Some(&title),
+                        &selector,
+                    )?
+                }
+            };
+            if total == 0 {
+                println!("{}", "No matches.".yellow());
+            }
+        }
+        Command::ResetBreaker { window } => {
+            let path = breaker::state_path();
+            let mut trips = breaker::load_trips(&path)?;
+            let reset: Vec<String> = trips
+                .iter()
+                .filter(|(_, trip)| window.as_ref().is_none_or(|window| trip.window == *window))
+                .map(|(key, _)| key.clone())
+                .collect();
+            if reset.is_empty() {
+                println!("{}", "No tripped circuit breaker.".yellow());
+                return Ok(());
+            }
+            for key in &reset {
+                if let Some(trip) = trips.remove(key) {
+                    println!(
+                        "{}",
+                        format!(
+                            "Reset '{}' (tripped {}: {})",
+                            trip.window,
+                            trip.at.format("%Y-%m-%d %H:%M"),
+                            trip.reason
+                        )
+                        .green()
+                    );
+                }
+            }
+            breaker::save_trips(&path, &trips)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the matches of `selector` in every window whose title contains
+/// `title`. Returns the number of matches.
+fn print_selector_matches<B: Backend>(
+    windows: &WindowService<B>,
+    query: &QueryService<B>,
+    title: Option<&str>,
+    selector: &Selector,
+) -> Result<usize> {
+    let mut total = 0;
+    for window in windows.get_all_windows()? {
+        let name = window.get_name().unwrap_or_default();
+        if title.is_some_and(|t| !text::title_matches(&name, t)) {
+            continue;
+        }
+        let matches = query.select(&window, selector)?;
+        println!(
+            "{}",
+            format!("# {} ({} match(es))", name, matches.len()).bold()
+        );
+        for element in &matches {
+            let info = element.get_info().unwrap_or_default();
+            let id = if info.automation_id.is_empty() {
+                String::new()
+            } else {
+                format!(" #{}", info.automation_id)
+            };
+            println!(
+                "{}",
+                format!(
+                    "  {}{} \"{}\" {}",
+                    info.control_type,
+                    id,
+                    info.name,
+                    snapshot::format_bounds(info.bounds)
+                )
+                .green()
+            );
+        }
+        total += matches.len();
+    }
+    Ok(total)
+}
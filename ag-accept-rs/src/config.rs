@@ -1,11 +1,221 @@
+use crate::active_hours::ActiveHours;
+use crate::breaker::BreakerLimits;
+use crate::notify::NotifyConfig;
+use crate::pipeline::{self, ActionStep};
+use crate::platform::ElementInfo;
+use crate::selector::Selector;
+use crate::text::{self, Needles};
 use anyhow::Result;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+/// A button-matching rule: click a button whose name contains one of
+/// `button_texts` when one of `context_texts` is visible close to it.
+/// Selectors locate the button or context by tree structure instead of (or
+/// in addition to) their text.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Rule {
+    pub name: String,
+    pub button_texts: Vec<String>,
+    /// Empty means the button alone is enough.
+    pub context_texts: Vec<String>,
+    /// Exact control types to accept. Empty means Button or Hyperlink.
+    pub control_types: Vec<String>,
+    /// Structural path the button must match. With no `button_texts`, any
+    /// name is accepted and the selector's types replace `control_types`.
+    pub button_selector: Option<Selector>,
+    /// Any named element matching this satisfies the context.
+    pub context_selector: Option<Selector>,
+    /// How close the context must be to the button.
+    pub proximity: Proximity,
+    /// Extra checks on the button's properties.
+    pub properties: PropertyFilter,
+    /// How much of the window to walk when looking for this rule.
+    pub traversal: Traversal,
+    /// When a window shows several prompts, those of higher-priority rules
+    /// are handled first.
+    pub priority: i32,
+    /// Steps run on a matched button, e.g.
+    /// `["focus_window", "invoke", "keys:Alt+Enter", "verify"]`. Empty means
+    /// focus, invoke (click as fallback), verify.
+    pub pipeline: Vec<ActionStep>,
+    /// Action methods of configs written before `pipeline`, e.g.
+    /// `["invoke", "keys:Alt+Enter"]`. Loading moves them into `pipeline`.
+    #[serde(skip_serializing)]
+    pub methods: Vec<ActionStep>,
+}
+
+/// Limits on the tree walk for a rule. The default fetches every
+/// descendant in one query.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Traversal {
+    /// Levels below the window to visit, breadth-first.
+    pub max_depth: Option<usize>,
+    /// Control types whose subtrees are not entered, e.g. editors and
+    /// terminals holding thousands of nodes. The nodes themselves still match.
+    pub prune_control_types: Vec<String>,
+    /// First scan the container the button was last found in (its parent),
+    /// falling back to the whole window.
+    pub anchor: bool,
+}
+
+impl Traversal {
+    /// True when the whole subtree can be fetched in one query.
+    pub fn is_unbounded(&self) -> bool {
+        self.max_depth.is_none() && self.prune_control_types.is_empty()
+    }
+
+    /// True when both walk the same nodes.
+    pub fn same_walk(&self, other: &Traversal) -> bool {
+        self.max_depth == other.max_depth && self.prune_control_types == other.prune_control_types
+    }
+}
+
+/// Maximum separation between a rule's context and its button. Either limit
+/// being met is enough; with both unset the context may be anywhere in the
+/// window.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Proximity {
+    /// Tree hops from the button up to the closest common ancestor and down
+    /// to the context. Siblings are 2 hops apart.
+    pub max_hops: Option<usize>,
+    /// Gap between the two bounding boxes, in pixels.
+    pub max_distance: Option<f64>,
+}
+
+impl Default for Proximity {
+    fn default() -> Self {
+        Self {
+            max_hops: Some(4),
+            max_distance: Some(200.0),
+        }
+    }
+}
+
+impl Proximity {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_hops.is_none() && self.max_distance.is_none()
+    }
+
+    pub fn allows(&self, hops: Option<usize>, distance: Option<f64>) -> bool {
+        self.is_unlimited()
+            || self.max_hops.zip(hops).is_some_and(|(max, h)| h <= max)
+            || self
+                .max_distance
+                .zip(distance)
+                .is_some_and(|(max, d)| d <= max)
+    }
+}
+
+/// Conditions on a button's `ElementInfo`. Unset fields match anything.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PropertyFilter {
+    /// Exact automation ID (UIA AutomationId, AT-SPI accessible ID).
+    pub automation_id: Option<String>,
+    pub help_text_contains: Option<String>,
+    pub value_contains: Option<String>,
+    pub enabled: Option<bool>,
+    pub offscreen: Option<bool>,
+    /// Patterns (UIA) or interfaces (AT-SPI) that must all be supported.
+    pub patterns: Vec<String>,
+}
+
+impl PropertyFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, info: &ElementInfo) -> bool {
+        self.automation_id
+            .as_ref()
+            .is_none_or(|id| *id == info.automation_id)
+            && self
+                .help_text_contains
+                .as_ref()
+                .is_none_or(|t| info.help_text.contains(t.as_str()))
+            && self.value_contains.as_ref().is_none_or(|t| {
+                info.value
+                    .as_deref()
+                    .is_some_and(|v| v.contains(t.as_str()))
+            })
+            && self.enabled.is_none_or(|e| e == info.enabled)
+            && self.offscreen.is_none_or(|o| o == info.offscreen)
+            && self.patterns.iter().all(|p| info.patterns.contains(p))
+    }
+}
+
+impl Rule {
+    /// True when a context must be present before the button is clicked.
+    pub fn needs_context(&self) -> bool {
+        !self.context_texts.is_empty() || self.context_selector.is_some()
+    }
+
+    /// `name` is normalized; `texts` are this rule's `button_texts`.
+    pub fn matches_button_name(&self, name: &str, texts: &Needles) -> bool {
+        if self.button_texts.is_empty() {
+            return self.button_selector.is_some();
+        }
+        texts.found_in(name)
+    }
+
+    pub fn matches_control_type(&self, control_type: &str) -> bool {
+        if self.control_types.is_empty() {
+            self.button_selector.is_some()
+                || control_type.contains("Button")
+                || control_type.contains("Hyperlink")
+        } else {
+            self.control_types.iter().any(|t| t == control_type)
+        }
+    }
+
+    fn sanitize(&mut self) {
+        self.button_texts.retain(|s| !s.trim().is_empty());
+        self.context_texts.retain(|s| !s.trim().is_empty());
+        let methods = std::mem::take(&mut self.methods);
+        if self.pipeline.is_empty() && !methods.is_empty() {
+            self.pipeline = pipeline::from_methods(methods);
+        }
+    }
+}
+
+/// When actions may run and take focus, given what the user is doing.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct FocusPolicy {
+    /// Act only after this long without keyboard or mouse input; 0 acts
+    /// right away.
+    pub min_idle_secs: f64,
+    /// Input more recent than this means the user is active.
+    pub active_within_secs: f64,
+    /// While the user is active, skip steps that take focus or move the
+    /// pointer (`focus_window`, `click`, `keys:`) and rely on `invoke`.
+    pub avoid_focus_when_active: bool,
+    /// Never take focus while the foreground window's title contains one of
+    /// these.
+    pub protected_apps: Vec<String>,
+}
+
+impl Default for FocusPolicy {
+    fn default() -> Self {
+        Self {
+            min_idle_secs: 0.0,
+            active_within_secs: 5.0,
+            avoid_focus_when_active: true,
+            protected_apps: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct AppConfig {
+    /// Base scan interval per target window, see `Scheduler`.
     pub interval: f64,
     pub target_window_title: String,
     pub search_texts_ide: Vec<String>,
@@ -15,6 +225,41 @@ pub struct AppConfig {
     pub debug_enabled: bool,
     pub window_width: u32,
     pub window_height: u32,
+    /// Number of log entries kept in the TUI log pane.
+    pub log_retention: usize,
+    /// Extra rules, checked after the Agent Manager texts above.
+    pub rules: Vec<Rule>,
+    /// Re-scan after each action to confirm the prompt closed.
+    pub verify_actions: bool,
+    /// Delay before re-scanning, giving the UI time to close the prompt.
+    pub verify_delay_ms: u64,
+    /// Extra runs of the action pipeline when the prompt stays open.
+    pub verify_retries: u32,
+    /// Maximum age of cached buttons and ancestry before a full re-scan.
+    pub cache_ttl_secs: f64,
+    /// The same prompt is acted on at most once within this many seconds.
+    pub dedup_window_secs: f64,
+    /// Filtered FindAll with prefetched properties. Turn off to compare with
+    /// the per-element property reads used before.
+    pub batched_scan: bool,
+    /// Upper bound for each UIA / D-Bus call into a target application.
+    pub call_timeout_ms: u64,
+    /// First quarantine of a window that timed out; doubles on each
+    /// consecutive timeout up to `hung_backoff_max_secs`.
+    pub hung_backoff_secs: f64,
+    pub hung_backoff_max_secs: f64,
+    /// Bounds for per-window scan intervals.
+    pub min_interval_secs: f64,
+    pub max_interval_secs: f64,
+    /// Bundled translations added to every rule's texts, e.g. `["ja"]`.
+    pub locales: Vec<String>,
+    pub focus_policy: FocusPolicy,
+    /// When prompts are acted on; outside it ag-accept only observes.
+    pub active_hours: ActiveHours,
+    /// Accept limits per window, see `CircuitBreaker`.
+    pub circuit_breaker: BreakerLimits,
+    /// Desktop notifications for automation events.
+    pub notifications: NotifyConfig,
 }
 
 impl Default for AppConfig {
@@ -39,6 +284,24 @@ impl Default for AppConfig {
             debug_enabled: false,
             window_width: 600,
             window_height: 700,
+            log_retention: 1000,
+            rules: Vec::new(),
+            verify_actions: true,
+            verify_delay_ms: 500,
+            verify_retries: 2,
+            cache_ttl_secs: 30.0,
+            dedup_window_secs: 10.0,
+            batched_scan: true,
+            call_timeout_ms: 2000,
+            hung_backoff_secs: 5.0,
+            hung_backoff_max_secs: 300.0,
+            min_interval_secs: 0.25,
+            max_interval_secs: 10.0,
+            locales: Vec::new(),
+            focus_policy: FocusPolicy::default(),
+            active_hours: ActiveHours::default(),
+            circuit_breaker: BreakerLimits::default(),
+            notifications: NotifyConfig::default(),
         }
     }
 }
@@ -58,6 +321,17 @@ impl AppConfig {
             config
                 .context_text_agent_manager
                 .retain(|s| !s.trim().is_empty());
+            for rule in &mut config.rules {
+                rule.sanitize();
+            }
+            config
+                .rules
+                .retain(|r| !r.button_texts.is_empty() || r.button_selector.is_some());
+            text::check_locales(&config.locales)?;
+            config.active_hours.check()?;
+            for rule in &config.rules {
+                pipeline::check_pipeline(&rule.name, &rule.pipeline)?;
+            }
 
             // In a real app we might want to merge with defaults to handle new keys,
             // but for now strict loading is fine, or we can fallback.
@@ -65,15 +339,49 @@ impl AppConfig {
         } else {
             // Create default
             let config = Self::default();
-            if let Some(parent) = config_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            let content = serde_json::to_string_pretty(&config)?;
-            fs::write(&config_path, content)?;
+            config.save()?;
             Ok(config)
         }
     }
 
+    pub fn save(&self) -> Result<()> {
+        let config_path = Self::get_config_path();
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&config_path, content)?;
+        Ok(())
+    }
+
+    /// All rules in evaluation order: the legacy Agent Manager texts first,
+    /// then the configured `rules`, with texts extended by `locales`.
+    pub fn effective_rules(&self) -> Vec<Rule> {
+        let mut rules = Vec::new();
+        if !self.search_texts_agent_manager.is_empty() {
+            rules.push(Rule {
+                name: "Agent Manager".to_string(),
+                button_texts: self.search_texts_agent_manager.clone(),
+                context_texts: self.context_text_agent_manager.clone(),
+                control_types: Vec::new(),
+                button_selector: None,
+                context_selector: None,
+                proximity: Proximity::default(),
+                properties: PropertyFilter::default(),
+                traversal: Traversal::default(),
+                priority: 0,
+                pipeline: Vec::new(),
+                methods: Vec::new(),
+            });
+        }
+        rules.extend(self.rules.iter().cloned());
+        for rule in &mut rules {
+            rule.button_texts = text::with_translations(&rule.button_texts, &self.locales);
+            rule.context_texts = text::with_translations(&rule.context_texts, &self.locales);
+        }
+        rules
+    }
+
     pub fn get_config_path() -> PathBuf {
         if let Some(proj_dirs) = ProjectDirs::from("", "RyosukeMondo", "ag-accept") {
             proj_dirs.config_dir().join("config.json")
@@ -82,3 +390,33 @@ impl AppConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn methods_of_older_configs_become_a_pipeline() {
+        let mut rule: Rule = serde_json::from_str(
+            r#"{"name": "Keys", "button_texts": ["Accept"], "methods": ["invoke", "keys:Alt+Enter"]}"#,
+        )
+        .unwrap();
+        rule.sanitize();
+        let steps: Vec<String> = rule.pipeline.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            steps,
+            vec!["focus_window", "invoke", "keys:Alt+Enter", "verify"]
+        );
+        assert!(rule.methods.is_empty());
+        let saved = serde_json::to_value(&rule).unwrap();
+        assert!(saved.get("methods").is_none());
+
+        // An explicit pipeline wins
+        let mut rule: Rule = serde_json::from_str(
+            r#"{"name": "Both", "methods": ["click"], "pipeline": ["invoke"]}"#,
+        )
+        .unwrap();
+        rule.sanitize();
+        assert_eq!(rule.pipeline.len(), 1);
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A key without modifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Enter,
+    Tab,
+    Escape,
+    Space,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    /// F1 to F24.
+    F(u8),
+    /// A printable character, lowercase for letters.
+    Char(char),
+}
+
+impl Key {
+    fn parse(name: &str) -> Option<Self> {
+        let lower = name.to_lowercase();
+        let key = match lower.as_str() {
+            "enter" | "return" => Key::Enter,
+            "tab" => Key::Tab,
+            "esc" | "escape" => Key::Escape,
+            "space" => Key::Space,
+            "backspace" => Key::Backspace,
+            "delete" | "del" => Key::Delete,
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "home" => Key::Home,
+            "end" => Key::End,
+            "pageup" | "pgup" => Key::PageUp,
+            "pagedown" | "pgdn" => Key::PageDown,
+            _ => {
+                let mut chars = lower.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if !c.is_whitespace() && c != '+' && c != ',' => Key::Char(c),
+                    (Some('f'), Some(_)) => match lower[1..].parse::<u8>() {
+                        Ok(n @ 1..=24) => Key::F(n),
+                        _ => return None,
+                    },
+                    _ => return None,
+                }
+            }
+        };
+        Some(key)
+    }
+
+    /// True for keys that activate a focused button.
+    pub fn activates(&self) -> bool {
+        matches!(self, Key::Enter | Key::Space)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::F(n) => write!(f, "F{}", n),
+            Key::Char(c) => write!(f, "{}", c.to_uppercase()),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// Windows / Super key.
+    pub meta: bool,
+}
+
+impl Modifiers {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One key pressed with modifiers held, e.g. `Alt+Enter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChord {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl KeyChord {
+    fn parse(source: &str, chord: &str) -> Result<Self> {
+        let error = |what: &str| anyhow!("Invalid key sequence '{}': {}", source, what);
+        let mut modifiers = Modifiers::default();
+        let mut parts: Vec<&str> = chord.split('+').collect();
+        let key = parts.pop().unwrap_or_default();
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "win" | "meta" | "super" | "cmd" => modifiers.meta = true,
+                _ => {
+                    return Err(error(&format!(
+                        "unknown modifier '{}' in '{}'",
+                        part, chord
+                    )))
+                }
+            }
+        }
+        let key = Key::parse(key).ok_or_else(|| error(&format!("unknown key in '{}'", chord)))?;
+        Ok(Self { modifiers, key })
+    }
+}
+
+/// Keys typed one after another, written like `Alt+Enter` or
+/// `Tab, Tab, Enter` (chords separated by spaces or commas).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeySequence {
+    source: String,
+    pub chords: Vec<KeyChord>,
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl From<KeySequence> for String {
+    fn from(keys: KeySequence) -> Self {
+        keys.source
+    }
+}
+
+impl TryFrom<String> for KeySequence {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Self> {
+        source.parse()
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let chords = source
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|chord| !chord.is_empty())
+            .map(|chord| KeyChord::parse(source, chord))
+            .collect::<Result<Vec<_>>>()?;
+        if chords.is_empty() {
+            return Err(anyhow!("Invalid key sequence '{}': no keys", source));
+        }
+        Ok(Self {
+            source: source.trim().to_string(),
+            chords,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chords_and_sequences() {
+        let keys: KeySequence = "Alt+Enter".parse().unwrap();
+        assert_eq!(
+            keys.chords,
+            vec![KeyChord {
+                modifiers: Modifiers {
+                    alt: true,
+                    ..Modifiers::default()
+                },
+                key: Key::Enter,
+            }]
+        );
+
+        let keys: KeySequence = "Tab, tab  ctrl+shift+a F5".parse().unwrap();
+        let keys: Vec<String> = keys.chords.iter().map(|c| c.key.to_string()).collect();
+        assert_eq!(keys, vec!["Tab", "Tab", "A", "F5"]);
+
+        let error = "Hyper+Enter".parse::<KeySequence>().unwrap_err();
+        assert!(error.to_string().contains("unknown modifier 'Hyper'"));
+        assert!("Ctrl+".parse::<KeySequence>().is_err());
+        assert!(" , ".parse::<KeySequence>().is_err());
+    }
+}
@@ -1,13 +1,25 @@
+mod active_hours;
 mod automation;
+mod breaker;
+mod cli;
 mod config;
+mod keys;
+mod notify;
+mod pipeline;
+mod platform;
+mod scheduler;
+mod selector;
 mod services;
+mod text;
 mod ui;
-mod platform;
 
 use anyhow::Result;
-use automation::{Automation, Event};
+use automation::{Automation, Event, LogCategory, LogEntry, LogLevel};
+use clap::Parser;
+use cli::Cli;
 use config::AppConfig;
-use crossterm::event::{self, Event as CEvent, KeyCode};
+use crossterm::event::{self, Event as CEvent, KeyEventKind};
+use notify::{NotificationSink, Notifier, PlatformSink};
 
 use std::sync::mpsc;
 
@@ -16,6 +28,8 @@ use std::time::Duration;
 use ui::app::App;
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+
     // Setup logging to file since stdout is taken by TUI
     // Setup logging to file
     let file_appender = tracing_appender::rolling::daily(".", "ag-accept.log");
@@ -27,10 +41,16 @@ fn main() -> Result<()> {
         .init();
 
     let config = AppConfig::load()?;
+
+    if let Some(command) = cli.command {
+        return cli::run(command, &config);
+    }
+
     let config_clone = config.clone();
 
     // Channel for communication
     let (tx, rx) = mpsc::channel();
+    let (control_tx, control_rx) = mpsc::channel();
 
     // Automation Thread
     thread::spawn(move || {
@@ -38,6 +58,7 @@ fn main() -> Result<()> {
             Ok(a) => a,
             Err(e) => panic!("Failed to init automation: {}", e),
         };
+        automation.set_controls(control_rx);
         if let Err(e) = automation.run() {
             eprintln!("Automation error: {}", e);
         }
@@ -45,7 +66,25 @@ fn main() -> Result<()> {
 
     // TUI (Main Thread)
     let mut terminal = ui::tui::init()?;
+    let notifications = config.notifications.clone();
     let mut app = App::new(config);
+    app.set_controls(control_tx);
+
+    // Desktop notifications, fed the same events as the TUI
+    let mut notifier = None;
+    if notifications.enabled {
+        match PlatformSink::new() {
+            Ok(sink) => {
+                let sink: Box<dyn NotificationSink> = Box::new(sink);
+                notifier = Some(Notifier::new(notifications, notify::spawn(sink)));
+            }
+            Err(e) => app.on_log(LogEntry::new(
+                LogLevel::Warn,
+                LogCategory::System,
+                format!("Desktop notifications unavailable: {}", e),
+            )),
+        }
+    }
 
     loop {
         terminal.draw(|f| ui::ui::render(&mut app, f))?;
@@ -53,23 +92,42 @@ fn main() -> Result<()> {
         // Handle inputs
         if event::poll(Duration::from_millis(100))? {
             if let CEvent::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => app.quit(),
-                    _ => {}
+                if key.kind == KeyEventKind::Press {
+                    app.on_key(key);
                 }
             }
         }
 
         // Handle events
         while let Ok(event) = rx.try_recv() {
+            if let Some(notifier) = notifier.as_mut() {
+                notifier.on_event(&event);
+            }
             match event {
-                Event::Log(msg) => app.on_log(msg),
+                Event::Log(entry) => app.on_log(entry),
                 Event::Status(msg) => app.on_status(msg),
                 Event::VisibleWindows(wins) => app.on_visible_windows(wins),
                 Event::AllWindows(wins) => app.on_all_windows(wins),
-                Event::ContextData { button, neighbors } => app.on_context(button, neighbors),
+                Event::ContextData {
+                    button,
+                    neighbors,
+                    context,
+                } => app.on_context(button, neighbors, context),
+                Event::ActionResult {
+                    window,
+                    button,
+                    outcome,
+                    steps,
+                } => app.on_action_result(window, button, outcome, steps),
+                Event::Metrics(metrics) => app.on_metrics(metrics),
+                Event::PromptQueue { window, prompts } => app.on_prompt_queue(window, prompts),
                 Event::ProcessingWindow(win) => app.on_processing(win),
                 Event::Timing(ms) => app.on_timing(ms),
+                Event::Sleep(duration) => app.on_sleep(duration),
+                Event::FocusRestoreFailed(reason) => app.on_focus_restore_failed(reason),
+                Event::Schedule(state) => app.on_schedule(state),
+                Event::BreakerTripped { window, trip } => app.on_breaker_tripped(window, trip),
+                Event::BreakerReset(window) => app.on_breaker_reset(window),
             }
         }
 
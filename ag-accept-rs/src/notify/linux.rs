@@ -0,0 +1,140 @@
+use super::{Notification, NotificationSink};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use zbus::blocking::Connection;
+use zbus::zvariant::Value;
+
+/// Sends notifications to the freedesktop notification service
+/// (org.freedesktop.Notifications) on the session bus.
+pub struct DbusSink {
+    connection: Connection,
+}
+
+impl DbusSink {
+    pub fn new() -> Result<Self> {
+        let connection =
+            Connection::session().map_err(|e| anyhow!("Failed to open session bus: {:?}", e))?;
+        Ok(Self::with_connection(connection))
+    }
+
+    /// Uses `connection` instead of the session bus, e.g. a peer-to-peer
+    /// connection to a stand-in service.
+    pub fn with_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+}
+
+impl NotificationSink for DbusSink {
+    fn show(&self, notification: &Notification) -> Result<()> {
+        let mut hints: HashMap<&str, Value> = HashMap::new();
+        hints.insert("urgency", Value::U8(notification.urgency as u8));
+        let actions: Vec<&str> = Vec::new();
+        self.connection
+            .call_method(
+                Some("org.freedesktop.Notifications"),
+                "/org/freedesktop/Notifications",
+                Some("org.freedesktop.Notifications"),
+                "Notify",
+                &(
+                    "ag-accept",
+                    0u32, // replaces_id
+                    "",   // app_icon
+                    notification.summary.as_str(),
+                    notification.body.as_str(),
+                    actions,
+                    hints,
+                    -1i32, // expire_timeout: server default
+                ),
+            )
+            .map_err(|e| anyhow!("Notify call failed: {:?}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::{NotifyKind, Urgency};
+    use std::os::unix::net::UnixStream;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use zbus::blocking::ConnectionBuilder;
+    use zbus::zvariant::OwnedValue;
+    use zbus::Guid;
+
+    /// App name, summary, body and urgency of one notification.
+    type Shown = (String, String, String, u8);
+
+    /// Records what a notification daemon would show.
+    struct StandIn {
+        shown: Arc<Mutex<Vec<Shown>>>,
+    }
+
+    #[zbus::dbus_interface(name = "org.freedesktop.Notifications")]
+    impl StandIn {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: &str,
+            _replaces_id: u32,
+            _app_icon: &str,
+            summary: &str,
+            body: &str,
+            _actions: Vec<&str>,
+            hints: HashMap<&str, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let urgency = hints
+                .get("urgency")
+                .and_then(|v| u8::try_from(v.clone()).ok())
+                .unwrap_or(u8::MAX);
+            let mut shown = self.shown.lock().unwrap();
+            shown.push((app_name.into(), summary.into(), body.into(), urgency));
+            shown.len() as u32
+        }
+    }
+
+    #[test]
+    fn notifies_a_stand_in_daemon_over_dbus() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let stand_in = StandIn {
+            shown: shown.clone(),
+        };
+        // Both ends authenticate at once, so the service starts on its own
+        // thread
+        let service = thread::spawn(move || {
+            let guid = Guid::generate();
+            ConnectionBuilder::unix_stream(server)
+                .server(&guid)
+                .p2p()
+                .serve_at("/org/freedesktop/Notifications", stand_in)
+                .unwrap()
+                .build()
+                .unwrap()
+        });
+        let connection = ConnectionBuilder::unix_stream(client)
+            .p2p()
+            .build()
+            .unwrap();
+        let _service = service.join().unwrap();
+
+        let sink = DbusSink::with_connection(connection);
+        sink.show(&Notification {
+            kind: NotifyKind::BreakerTripped,
+            summary: "Circuit breaker tripped".to_string(),
+            body: "Agent Manager: 11 accepts in the last minute".to_string(),
+            urgency: Urgency::Critical,
+        })
+        .unwrap();
+        assert_eq!(
+            *shown.lock().unwrap(),
+            vec![(
+                "ag-accept".to_string(),
+                "Circuit breaker tripped".to_string(),
+                "Agent Manager: 11 accepts in the last minute".to_string(),
+                2
+            )]
+        );
+    }
+}
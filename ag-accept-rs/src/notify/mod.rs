@@ -0,0 +1,306 @@
+use crate::automation::{ActionOutcome, Event, LogCategory, LogLevel};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Events a desktop notification can be sent for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyKind {
+    /// A prompt was accepted and closed.
+    Accepted,
+    /// An action ran but the prompt stayed open.
+    Unverified,
+    /// Every action method failed.
+    Failed,
+    /// The focus policy, active hours or the circuit breaker held a prompt
+    /// back.
+    Deferred,
+    BreakerTripped,
+    FocusRestoreFailed,
+    /// Errors logged outside actions, e.g. failed scans.
+    Error,
+}
+
+/// Which events raise desktop notifications and how often. Off until
+/// `enabled` is set.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub enabled: bool,
+    /// Opted-in events once enabled.
+    pub events: Vec<NotifyKind>,
+    /// At most one notification per event and window within this many
+    /// seconds; the rest are counted into the next one.
+    pub throttle_secs: f64,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            events: vec![
+                NotifyKind::Failed,
+                NotifyKind::BreakerTripped,
+                NotifyKind::FocusRestoreFailed,
+            ],
+            throttle_secs: 60.0,
+        }
+    }
+}
+
+/// Freedesktop urgency levels; critical toasts stay up longer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Urgency {
+    Low = 0,
+    Normal = 1,
+    Critical = 2,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub kind: NotifyKind,
+    pub summary: String,
+    pub body: String,
+    pub urgency: Urgency,
+}
+
+/// Shows notifications on the desktop.
+pub trait NotificationSink: Send {
+    fn show(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Turns automation events into notifications, dropping events that are
+/// not opted in and throttling repeats.
+pub struct Notifier {
+    config: NotifyConfig,
+    outbox: Sender<Notification>,
+    /// Last notification per event and window, and how many were
+    /// throttled since.
+    last_sent: HashMap<(NotifyKind, String), (Instant, u32)>,
+}
+
+impl Notifier {
+    /// Notifications go to `outbox`, usually the sender of `spawn`.
+    pub fn new(config: NotifyConfig, outbox: Sender<Notification>) -> Self {
+        Self {
+            config,
+            outbox,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    pub fn on_event(&mut self, event: &Event) {
+        self.on_event_at(event, Instant::now());
+    }
+
+    fn on_event_at(&mut self, event: &Event, now: Instant) {
+        if !self.config.enabled {
+            return;
+        }
+        let Some((window, mut notification)) = describe(event) else {
+            return;
+        };
+        if !self.config.events.contains(&notification.kind) {
+            return;
+        }
+
+        let throttle = Duration::from_secs_f64(self.config.throttle_secs.max(0.0));
+        let key = (notification.kind, window);
+        if let Some((sent, throttled)) = self.last_sent.get_mut(&key) {
+            if now.saturating_duration_since(*sent) < throttle {
+                *throttled += 1;
+                return;
+            }
+            if *throttled > 0 {
+                notification.body = format!(
+                    "{}\n({} similar since the last notification)",
+                    notification.body, throttled
+                );
+            }
+        }
+        self.last_sent.insert(key, (now, 0));
+        let _ = self.outbox.send(notification);
+    }
+}
+
+/// The notification for `event` and the window it concerns, if any.
+fn describe(event: &Event) -> Option<(String, Notification)> {
+    let notification = |kind, summary: String, body: String, urgency| Notification {
+        kind,
+        summary,
+        body,
+        urgency,
+    };
+    let described = match event {
+        Event::ActionResult {
+            window,
+            button,
+            outcome,
+            ..
+        } => {
+            let (kind, summary, urgency) = match outcome {
+                ActionOutcome::Verified { .. } | ActionOutcome::Performed { .. } => (
+                    NotifyKind::Accepted,
+                    format!("Accepted '{}'", button),
+                    Urgency::Low,
+                ),
+                ActionOutcome::Unverified { .. } => (
+                    NotifyKind::Unverified,
+                    format!("'{}' may still be open", button),
+                    Urgency::Normal,
+                ),
+                ActionOutcome::Failed { .. } => (
+                    NotifyKind::Failed,
+                    format!("Failed to accept '{}'", button),
+                    Urgency::Critical,
+                ),
+                ActionOutcome::Deferred { .. } => (
+                    NotifyKind::Deferred,
+                    format!("Held back '{}'", button),
+                    Urgency::Normal,
+                ),
+            };
+            let body = format!("{}: {}", window, outcome.label());
+            (window.clone(), notification(kind, summary, body, urgency))
+        }
+        Event::BreakerTripped { window, trip } => (
+            window.clone(),
+            notification(
+                NotifyKind::BreakerTripped,
+                "Circuit breaker tripped".to_string(),
+                format!("{}: {}. Observing only until reset.", window, trip.reason),
+                Urgency::Critical,
+            ),
+        ),
+        Event::FocusRestoreFailed(reason) => (
+            String::new(),
+            notification(
+                NotifyKind::FocusRestoreFailed,
+                "Focus not restored".to_string(),
+                reason.clone(),
+                Urgency::Normal,
+            ),
+        ),
+        // Action errors are covered by the action result
+        Event::Log(entry)
+            if entry.level == LogLevel::Error && entry.category != LogCategory::Action =>
+        {
+            (
+                entry.window.clone().unwrap_or_default(),
+                notification(
+                    NotifyKind::Error,
+                    "ag-accept error".to_string(),
+                    entry.message.clone(),
+                    Urgency::Critical,
+                ),
+            )
+        }
+        _ => return None,
+    };
+    Some(described)
+}
+
+/// Shows notifications sent to the returned channel on a background
+/// thread, so a slow notification service never stalls the TUI.
+pub fn spawn(sink: Box<dyn NotificationSink>) -> Sender<Notification> {
+    let (tx, rx) = mpsc::channel::<Notification>();
+    thread::spawn(move || {
+        for notification in rx {
+            if let Err(e) = sink.show(&notification) {
+                warn!("Failed to show notification: {}", e);
+            }
+        }
+    });
+    tx
+}
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+pub use windows::ToastSink as PlatformSink;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::DbusSink as PlatformSink;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breaker::Trip;
+
+    #[test]
+    fn sends_opted_in_events_and_throttles_repeats() {
+        let (tx, rx) = mpsc::channel();
+        let config = NotifyConfig {
+            enabled: true,
+            ..NotifyConfig::default()
+        };
+        let mut notifier = Notifier::new(config, tx);
+        let start = Instant::now();
+        let failed = |window: &str| Event::ActionResult {
+            window: window.to_string(),
+            button: "Accept".to_string(),
+            outcome: ActionOutcome::Failed { attempts: 3 },
+            steps: Vec::new(),
+        };
+
+        notifier.on_event_at(&failed("a"), start);
+        notifier.on_event_at(&failed("a"), start + Duration::from_secs(1));
+        notifier.on_event_at(&failed("a"), start + Duration::from_secs(2));
+        notifier.on_event_at(&failed("b"), start + Duration::from_secs(3));
+        // Not opted in by default
+        notifier.on_event_at(&Event::Status("Scanning".to_string()), start);
+        notifier.on_event_at(
+            &Event::ActionResult {
+                window: "a".to_string(),
+                button: "Accept".to_string(),
+                outcome: ActionOutcome::Deferred {
+                    reason: "outside active hours".to_string(),
+                },
+                steps: Vec::new(),
+            },
+            start,
+        );
+        notifier.on_event_at(&failed("a"), start + Duration::from_secs(61));
+        notifier.on_event_at(
+            &Event::BreakerTripped {
+                window: "a".to_string(),
+                trip: Trip {
+                    window: "a".to_string(),
+                    reason: "11 accepts in the last minute".to_string(),
+                    at: chrono::Local::now(),
+                },
+            },
+            start,
+        );
+
+        let sent: Vec<Notification> = rx.try_iter().collect();
+        let bodies: Vec<&str> = sent.iter().map(|n| n.body.as_str()).collect();
+        assert_eq!(
+            bodies,
+            vec![
+                "a: FAILED (3 attempt(s))",
+                "b: FAILED (3 attempt(s))",
+                "a: FAILED (3 attempt(s))\n(2 similar since the last notification)",
+                "a: 11 accepts in the last minute. Observing only until reset.",
+            ]
+        );
+        assert_eq!(sent[3].urgency, Urgency::Critical);
+    }
+
+    #[test]
+    fn notifications_are_off_by_default() {
+        let (tx, rx) = mpsc::channel();
+        let mut notifier = Notifier::new(NotifyConfig::default(), tx);
+        notifier.on_event(&Event::FocusRestoreFailed(
+            "Notes refused focus".to_string(),
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use super::{Notification, NotificationSink, Urgency};
+use anyhow::{anyhow, Result};
+use windows::core::HSTRING;
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::UI::Notifications::{ToastNotification, ToastNotificationManager, ToastNotifier};
+
+/// AppUserModelID the toasts are shown under. An unpackaged app needs a
+/// Start menu shortcut to register its own, so PowerShell's is borrowed.
+const APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+/// Shows notifications as Windows toasts.
+pub struct ToastSink;
+
+impl ToastSink {
+    pub fn new() -> Result<Self> {
+        // Fails early when toasts are unavailable, e.g. on Server Core
+        notifier()?;
+        Ok(Self)
+    }
+}
+
+// Created per toast: WinRT objects stay on the thread that made them
+fn notifier() -> Result<ToastNotifier> {
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))
+        .map_err(|e| anyhow!("Toast notifications unavailable: {}", e))
+}
+
+impl NotificationSink for ToastSink {
+    fn show(&self, notification: &Notification) -> Result<()> {
+        let duration = match notification.urgency {
+            Urgency::Critical => "long",
+            Urgency::Low | Urgency::Normal => "short",
+        };
+        let xml = format!(
+            r#"<toast duration="{}"><visual><binding template="ToastGeneric"><text>{}</text><text>{}</text></binding></visual></toast>"#,
+            duration,
+            escape(&notification.summary),
+            escape(&notification.body)
+        );
+        let document = XmlDocument::new()?;
+        document.LoadXml(&HSTRING::from(xml))?;
+        let toast = ToastNotification::CreateToastNotification(&document)?;
+        notifier()?.Show(&toast)?;
+        Ok(())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,361 @@
+use crate::keys::KeySequence;
+use crate::platform::is_timeout;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+/// How a button is acted on. Written in pipelines as `invoke`, `click` or
+/// `keys:<sequence>`, e.g. `keys:Alt+Enter`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ActionMethod {
+    Invoke,
+    Click,
+    /// Focuses the button, then types the keys.
+    Keys(KeySequence),
+}
+
+impl fmt::Display for ActionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionMethod::Invoke => f.write_str("Invoke"),
+            ActionMethod::Click => f.write_str("Click"),
+            ActionMethod::Keys(keys) => write!(f, "Keys {}", keys),
+        }
+    }
+}
+
+/// What one pipeline step does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepKind {
+    /// Brings the prompt's window to the foreground. The previous focus is
+    /// restored once the window's prompts are handled.
+    FocusWindow,
+    /// Acts on the button. Adjacent methods are fallbacks for each other:
+    /// the first that succeeds is used and the rest are skipped.
+    Method(ActionMethod),
+    Wait(Duration),
+    /// Re-scans the window; a prompt that is still open ends the attempt.
+    Verify,
+}
+
+impl StepKind {
+    /// True for steps that move focus or the pointer.
+    pub fn takes_focus(&self) -> bool {
+        matches!(
+            self,
+            StepKind::FocusWindow
+                | StepKind::Method(ActionMethod::Click)
+                | StepKind::Method(ActionMethod::Keys(_))
+        )
+    }
+
+    fn parse(source: &str) -> Result<Self> {
+        let source = source.trim();
+        let (name, arg) = match source.split_once(':') {
+            Some((name, arg)) => (name.trim().to_lowercase(), Some(arg.trim())),
+            None => (source.to_lowercase(), None),
+        };
+        let kind = match (name.as_str(), arg) {
+            ("focus_window", None) => StepKind::FocusWindow,
+            ("invoke", None) => StepKind::Method(ActionMethod::Invoke),
+            ("click", None) => StepKind::Method(ActionMethod::Click),
+            ("keys", Some(keys)) => StepKind::Method(ActionMethod::Keys(keys.parse()?)),
+            ("wait", Some(duration)) => {
+                StepKind::Wait(parse_duration(duration).ok_or_else(|| {
+                    anyhow!("Invalid wait '{}': expected e.g. 200ms or 1.5s", source)
+                })?)
+            }
+            ("verify", None) => StepKind::Verify,
+            _ => {
+                return Err(anyhow!(
+                    "Unknown action step '{}' (expected focus_window, invoke, click, \
+                     keys:<sequence>, wait:<duration> or verify)",
+                    source
+                ))
+            }
+        };
+        Ok(kind)
+    }
+}
+
+impl fmt::Display for StepKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepKind::FocusWindow => f.write_str("focus_window"),
+            StepKind::Method(ActionMethod::Invoke) => f.write_str("invoke"),
+            StepKind::Method(ActionMethod::Click) => f.write_str("click"),
+            StepKind::Method(ActionMethod::Keys(keys)) => write!(f, "keys:{}", keys),
+            StepKind::Wait(duration) => write!(f, "wait:{}", format_duration(*duration)),
+            StepKind::Verify => f.write_str("verify"),
+        }
+    }
+}
+
+/// One step of a rule's action pipeline, written as `"invoke"`,
+/// `"keys:Enter"`, `"wait:200ms"` and so on, or as
+/// `{"step": "verify", "timeout_ms": 3000}` to give it a timeout.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawStep", into = "RawStep")]
+pub struct ActionStep {
+    pub kind: StepKind,
+    /// Call timeout of focus and action steps; for `verify`, how long to
+    /// keep re-checking a prompt that is still open. `None` uses
+    /// `call_timeout_ms` and a single check.
+    pub timeout: Option<Duration>,
+}
+
+impl ActionStep {
+    pub fn new(kind: StepKind) -> Self {
+        Self {
+            kind,
+            timeout: None,
+        }
+    }
+
+    pub fn method(&self) -> Option<&ActionMethod> {
+        match &self.kind {
+            StepKind::Method(method) => Some(method),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ActionStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawStep {
+    Short(String),
+    Full {
+        step: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_ms: Option<u64>,
+    },
+}
+
+impl TryFrom<RawStep> for ActionStep {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawStep) -> Result<Self> {
+        let (step, timeout_ms) = match raw {
+            RawStep::Short(step) => (step, None),
+            RawStep::Full { step, timeout_ms } => (step, timeout_ms),
+        };
+        Ok(Self {
+            kind: StepKind::parse(&step)?,
+            timeout: timeout_ms.map(Duration::from_millis),
+        })
+    }
+}
+
+impl From<ActionStep> for RawStep {
+    fn from(step: ActionStep) -> Self {
+        match step.timeout {
+            None => RawStep::Short(step.kind.to_string()),
+            Some(timeout) => RawStep::Full {
+                step: step.kind.to_string(),
+                timeout_ms: Some(timeout.as_millis() as u64),
+            },
+        }
+    }
+}
+
+/// Pipeline of rules that do not set one: focus, invoke with click as the
+/// fallback, verify.
+pub fn default_pipeline() -> Vec<ActionStep> {
+    [
+        StepKind::FocusWindow,
+        StepKind::Method(ActionMethod::Invoke),
+        StepKind::Method(ActionMethod::Click),
+        StepKind::Verify,
+    ]
+    .into_iter()
+    .map(ActionStep::new)
+    .collect()
+}
+
+/// Pipeline for a rule's former `methods` list: focus, the methods as
+/// fallbacks for each other, verify.
+pub fn from_methods(methods: Vec<ActionStep>) -> Vec<ActionStep> {
+    let mut steps = vec![ActionStep::new(StepKind::FocusWindow)];
+    steps.extend(methods);
+    steps.push(ActionStep::new(StepKind::Verify));
+    steps
+}
+
+/// Index ranges of adjacent action methods, each a group of fallbacks.
+pub fn method_groups(steps: &[ActionStep]) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        if step.method().is_none() {
+            continue;
+        }
+        match groups.last_mut() {
+            Some(group) if group.end == i => group.end = i + 1,
+            _ => groups.push(i..i + 1),
+        }
+    }
+    groups
+}
+
+/// Fails on pipelines that never act on the button or verify before acting.
+pub fn check_pipeline(rule: &str, steps: &[ActionStep]) -> Result<()> {
+    if steps.is_empty() {
+        return Ok(());
+    }
+    let first_method = steps.iter().position(|s| s.method().is_some());
+    let first_verify = steps.iter().position(|s| s.kind == StepKind::Verify);
+    match (first_method, first_verify) {
+        (None, _) => Err(anyhow!(
+            "Rule '{}': pipeline has no invoke, click or keys step",
+            rule
+        )),
+        (Some(method), Some(verify)) if verify < method => Err(anyhow!(
+            "Rule '{}': verify must come after an invoke, click or keys step",
+            rule
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parses `200ms`, `2s` or `1.5s`; a bare number is milliseconds.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, scale) = if let Some(ms) = text.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(secs) = text.strip_suffix('s') {
+        (secs, 1.0)
+    } else {
+        (text, 0.001)
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n >= 0.0)
+        .map(|n| Duration::from_secs_f64(n * scale))
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.subsec_millis() == 0 && !duration.is_zero() {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepStatus {
+    Ok,
+    Failed(String),
+    TimedOut,
+    /// Not run because an earlier fallback succeeded or verification is
+    /// disabled.
+    Skipped,
+    /// Not run because the focus policy forbids taking focus.
+    Blocked(String),
+}
+
+/// How one pipeline step went.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepResult {
+    pub attempt: u32,
+    pub step: String,
+    pub status: StepStatus,
+    pub elapsed: Duration,
+}
+
+impl StepResult {
+    pub fn new(attempt: u32, step: &ActionStep, result: &Result<()>, elapsed: Duration) -> Self {
+        let status = match result {
+            Ok(()) => StepStatus::Ok,
+            Err(e) if is_timeout(e) => StepStatus::TimedOut,
+            Err(e) => StepStatus::Failed(e.to_string()),
+        };
+        Self {
+            attempt,
+            step: step.to_string(),
+            status,
+            elapsed,
+        }
+    }
+
+    pub fn blocked(attempt: u32, step: &ActionStep, reason: &str) -> Self {
+        Self {
+            attempt,
+            step: step.to_string(),
+            status: StepStatus::Blocked(reason.to_string()),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn skipped(attempt: u32, step: &ActionStep) -> Self {
+        Self {
+            attempt,
+            step: step.to_string(),
+            status: StepStatus::Skipped,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+impl fmt::Display for StepResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            StepStatus::Ok => write!(f, "{} ok", self.step)?,
+            StepStatus::Failed(reason) => write!(f, "{} failed ({})", self.step, reason)?,
+            StepStatus::TimedOut => write!(f, "{} timed out", self.step)?,
+            StepStatus::Skipped => return write!(f, "{} skipped", self.step),
+            StepStatus::Blocked(reason) => return write!(f, "{} blocked ({})", self.step, reason),
+        }
+        write!(f, " {}ms", self.elapsed.as_millis())
+    }
+}
+
+/// `results` on one line, prefixed with the attempt when there were several.
+pub fn summarize(results: &[StepResult]) -> String {
+    let retried = results.iter().any(|r| r.attempt > 1);
+    results
+        .iter()
+        .map(|r| {
+            if retried {
+                format!("#{} {}", r.attempt, r)
+            } else {
+                r.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_steps_and_groups_fallbacks() {
+        let steps: Vec<ActionStep> = serde_json::from_str(
+            r#"["focus_window", "invoke", "keys:Alt+Enter", "wait:1.5s",
+                {"step": "verify", "timeout_ms": 3000}, "click"]"#,
+        )
+        .unwrap();
+        assert_eq!(steps[3].kind, StepKind::Wait(Duration::from_millis(1500)));
+        assert_eq!(steps[4].timeout, Some(Duration::from_secs(3)));
+        assert_eq!(method_groups(&steps), vec![1..3, 5..6]);
+        assert_eq!(
+            serde_json::to_string(&steps[1..5]).unwrap(),
+            r#"["invoke","keys:Alt+Enter","wait:1500ms",{"step":"verify","timeout_ms":3000}]"#
+        );
+
+        let error = serde_json::from_str::<Vec<ActionStep>>(r#"["tap"]"#).unwrap_err();
+        assert!(error.to_string().contains("Unknown action step 'tap'"));
+        assert!(check_pipeline("r", &steps).is_ok());
+        assert!(check_pipeline("r", &steps[3..5]).is_err());
+        assert!(check_pipeline("r", &steps[4..]).is_err());
+    }
+}
@@ -0,0 +1,602 @@
+use super::{
+    Backend, Bounds, CallTimeout, Element, ElementFilter, ElementInfo, Scope, Siblings, WindowState,
+};
+use crate::keys::KeySequence;
+use crate::services::snapshot::{SnapshotNode, TreeSnapshot};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Recorded accessibility trees of one or more top-level windows.
+///
+/// A single `dump-tree` JSON file is also accepted as a one-window fixture.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    /// Title of the window that had keyboard focus when recorded.
+    #[serde(default)]
+    pub focused_window: Option<String>,
+    pub windows: Vec<TreeSnapshot>,
+    /// Simulate the prompt closing: an invoked/clicked element's container
+    /// disappears from the tree.
+    #[serde(default = "default_dismiss_on_action")]
+    pub dismiss_on_action: bool,
+    /// Titles of windows whose tree queries time out, as if the application
+    /// had hung.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hung_windows: Vec<String>,
+    /// Seconds since the user's last input; unknown when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_secs: Option<f64>,
+    /// Titles of windows that start minimized.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub minimized_windows: Vec<String>,
+    /// Titles of windows that start maximized, or that return to maximized
+    /// when restored from minimized.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maximized_windows: Vec<String>,
+    /// Titles of windows whose elements refuse focus, as when the system
+    /// blocks a foreground change.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unfocusable_windows: Vec<String>,
+}
+
+fn default_dismiss_on_action() -> bool {
+    true
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        if let Ok(fixture) = serde_json::from_str::<Fixture>(&content) {
+            return Ok(fixture);
+        }
+        let snapshot: TreeSnapshot = serde_json::from_str(&content).map_err(|e| {
+            anyhow!(
+                "{} is not a fixture or tree snapshot: {}",
+                path.display(),
+                e
+            )
+        })?;
+        Ok(Fixture {
+            focused_window: None,
+            windows: vec![snapshot],
+            dismiss_on_action: true,
+            hung_windows: Vec::new(),
+            idle_secs: None,
+            minimized_windows: Vec::new(),
+            maximized_windows: Vec::new(),
+            unfocusable_windows: Vec::new(),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// An action the automation performed against a fixture element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixtureAction {
+    pub kind: &'static str,
+    pub name: String,
+    pub path: String,
+}
+
+struct FixtureNode {
+    info: ElementInfo,
+    path: String,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+struct FixtureTree {
+    /// Index 0 is a synthetic desktop root whose children are the windows.
+    nodes: Vec<FixtureNode>,
+    focused: Option<usize>,
+    /// Element last given keyboard focus through `set_focus`.
+    keyboard_focus: Mutex<Option<usize>>,
+    actions: Mutex<Vec<FixtureAction>>,
+    dismiss_on_action: bool,
+    /// Roots of subtrees removed by dismissed prompts.
+    hidden: Mutex<HashSet<usize>>,
+    /// Windows whose tree queries time out.
+    hung: HashSet<usize>,
+    idle: Option<Duration>,
+    minimized: Mutex<HashSet<usize>>,
+    /// Maximized windows, including minimized ones that restore to
+    /// maximized.
+    maximized: Mutex<HashSet<usize>>,
+    unfocusable: HashSet<usize>,
+}
+
+impl FixtureTree {
+    fn add(&mut self, node: &SnapshotNode, parent: usize) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(FixtureNode {
+            info: node.info(),
+            path: node.path.clone(),
+            parent: Some(parent),
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(index);
+        for child in &node.children {
+            self.add(child, index);
+        }
+        index
+    }
+
+    fn record(&self, kind: &'static str, index: usize) {
+        let node = &self.nodes[index];
+        if let Ok(mut actions) = self.actions.lock() {
+            actions.push(FixtureAction {
+                kind,
+                name: node.info.name.clone(),
+                path: node.path.clone(),
+            });
+        }
+    }
+
+    /// Hides the prompt container of an acted-on element (its parent, unless
+    /// that is the top-level window itself).
+    fn dismiss(&self, index: usize) {
+        if !self.dismiss_on_action {
+            return;
+        }
+        let target = match self.nodes[index].parent {
+            Some(parent) if self.nodes[parent].parent != Some(0) => parent,
+            _ => index,
+        };
+        if let Ok(mut hidden) = self.hidden.lock() {
+            hidden.insert(target);
+        }
+    }
+
+    /// Index of the top-level window containing `index`.
+    fn window_of(&self, index: usize) -> usize {
+        let mut current = index;
+        while let Some(parent) = self.nodes[current].parent.filter(|&p| p != 0) {
+            current = parent;
+        }
+        current
+    }
+
+    fn is_hung(&self, index: usize) -> bool {
+        let mut current = Some(index);
+        while let Some(i) = current {
+            if self.hung.contains(&i) {
+                return true;
+            }
+            current = self.nodes[i].parent;
+        }
+        false
+    }
+
+    fn is_hidden(&self, index: usize) -> bool {
+        let Ok(hidden) = self.hidden.lock() else {
+            return false;
+        };
+        let mut current = Some(index);
+        while let Some(i) = current {
+            if hidden.contains(&i) {
+                return true;
+            }
+            current = self.nodes[i].parent;
+        }
+        false
+    }
+}
+
+#[derive(Clone)]
+pub struct FixtureElement {
+    tree: Arc<FixtureTree>,
+    index: usize,
+}
+
+impl FixtureElement {
+    fn node(&self) -> &FixtureNode {
+        &self.tree.nodes[self.index]
+    }
+
+    fn ensure_present(&self) -> Result<()> {
+        if self.tree.is_hidden(self.index) {
+            return Err(anyhow!(
+                "Element {} is no longer available",
+                self.node().path
+            ));
+        }
+        Ok(())
+    }
+
+    fn at(&self, index: usize) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            index,
+        }
+    }
+
+    fn in_set(&self, set: &Mutex<HashSet<usize>>) -> bool {
+        set.lock().map(|s| s.contains(&self.index)).unwrap_or(false)
+    }
+}
+
+impl fmt::Debug for FixtureElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixtureElement")
+            .field("path", &self.node().path)
+            .finish()
+    }
+}
+
+impl Element for FixtureElement {
+    // Name and type keep resolving after dismissal, like UIA cached
+    // properties of a dead element; identity and actions do not.
+    fn get_name(&self) -> Result<String> {
+        Ok(self.node().info.name.clone())
+    }
+
+    fn get_control_type(&self) -> Result<String> {
+        Ok(self.node().info.control_type.clone())
+    }
+
+    // Attempts are recorded even when they fail, so tests can assert that a
+    // stale element was never acted on.
+    fn click(&self) -> Result<()> {
+        self.tree.record("click", self.index);
+        self.ensure_present()?;
+        self.tree.dismiss(self.index);
+        Ok(())
+    }
+
+    fn invoke(&self) -> Result<()> {
+        self.tree.record("invoke", self.index);
+        self.ensure_present()?;
+        self.tree.dismiss(self.index);
+        Ok(())
+    }
+
+    fn set_focus(&self) -> Result<()> {
+        self.ensure_present()?;
+        self.tree.record("focus", self.index);
+        let window = self.tree.window_of(self.index);
+        if self.tree.unfocusable.contains(&window) {
+            return Err(anyhow!("{} refused focus", self.node().path));
+        }
+        if let Ok(mut focus) = self.tree.keyboard_focus.lock() {
+            *focus = Some(self.index);
+        }
+        // Focusing a minimized window restores it
+        if let Ok(mut minimized) = self.tree.minimized.lock() {
+            minimized.remove(&window);
+        }
+        Ok(())
+    }
+
+    fn window_state(&self) -> Result<WindowState> {
+        if self.in_set(&self.tree.minimized) {
+            return Ok(WindowState::Minimized);
+        }
+        self.restored_state()
+    }
+
+    fn set_window_state(&self, state: WindowState) -> Result<()> {
+        let kind = match state {
+            WindowState::Minimized => "minimize",
+            WindowState::Normal => "restore",
+            WindowState::Maximized => "maximize",
+        };
+        self.tree.record(kind, self.index);
+        if let Ok(mut minimized) = self.tree.minimized.lock() {
+            if state == WindowState::Minimized {
+                minimized.insert(self.index);
+            } else {
+                minimized.remove(&self.index);
+            }
+        }
+        // Minimizing keeps the state to restore to
+        if let Ok(mut maximized) = self.tree.maximized.lock() {
+            match state {
+                WindowState::Maximized => maximized.insert(self.index),
+                WindowState::Normal => maximized.remove(&self.index),
+                WindowState::Minimized => false,
+            };
+        }
+        Ok(())
+    }
+
+    fn restored_state(&self) -> Result<WindowState> {
+        Ok(if self.in_set(&self.tree.maximized) {
+            WindowState::Maximized
+        } else {
+            WindowState::Normal
+        })
+    }
+
+    fn get_clickable_point(&self) -> Result<(i32, i32)> {
+        let b = self.get_bounds()?;
+        Ok((b.x + b.width / 2, b.y + b.height / 2))
+    }
+
+    fn get_bounds(&self) -> Result<Bounds> {
+        self.node()
+            .info
+            .bounds
+            .ok_or_else(|| anyhow!("No bounds recorded for {}", self.node().path))
+    }
+
+    fn runtime_id(&self) -> Result<String> {
+        self.ensure_present()?;
+        // Windows recorded from the same app share paths
+        Ok(format!("{}:{}", self.index, self.node().path))
+    }
+
+    fn same_as(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.tree, &other.tree) && self.index == other.index
+    }
+
+    fn is_enabled(&self) -> Result<bool> {
+        Ok(self.node().info.enabled)
+    }
+
+    fn is_offscreen(&self) -> Result<bool> {
+        Ok(self.node().info.offscreen || self.tree.is_hidden(self.index))
+    }
+
+    fn get_info(&self) -> Result<ElementInfo> {
+        let mut info = self.node().info.clone();
+        info.offscreen |= self.tree.is_hidden(self.index);
+        Ok(info)
+    }
+
+    fn find_elements(&self, scope: Scope) -> Result<Vec<Self>> {
+        self.ensure_present()?;
+        if self.tree.is_hung(self.index) {
+            return Err(CallTimeout {
+                call: format!("find elements under {}", self.node().path),
+                after: Duration::ZERO,
+            }
+            .into());
+        }
+        let hidden = self
+            .tree
+            .hidden
+            .lock()
+            .map(|h| h.clone())
+            .unwrap_or_default();
+        let mut result = Vec::new();
+        match scope {
+            Scope::Children => {
+                result.extend(
+                    self.node()
+                        .children
+                        .iter()
+                        .filter(|i| !hidden.contains(i))
+                        .map(|&i| self.at(i)),
+                );
+            }
+            Scope::Descendants => {
+                // Depth-first, document order (same as UIA FindAll)
+                let mut stack: Vec<usize> = self.node().children.iter().rev().copied().collect();
+                while let Some(i) = stack.pop() {
+                    if hidden.contains(&i) {
+                        continue;
+                    }
+                    result.push(self.at(i));
+                    stack.extend(self.tree.nodes[i].children.iter().rev().copied());
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    // Applied here like a UIA condition would be on the provider side
+    fn find_elements_filtered(&self, scope: Scope, filter: &ElementFilter) -> Result<Vec<Self>> {
+        Ok(self
+            .find_elements(scope)?
+            .into_iter()
+            .filter(|e| {
+                let info = &e.node().info;
+                (!filter.named_only || !info.name.is_empty())
+                    && (!filter.clickable_only
+                        || info.control_type.contains("Button")
+                        || info.control_type.contains("Hyperlink"))
+            })
+            .collect())
+    }
+}
+
+/// Backend that replays recorded trees instead of talking to a live desktop.
+///
+/// Clones share state, so actions performed through any service are visible
+/// via [`FixtureBackend::actions`].
+#[derive(Clone)]
+pub struct FixtureBackend {
+    tree: Arc<FixtureTree>,
+}
+
+impl FixtureBackend {
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::from_fixture(&Fixture::load(path)?))
+    }
+
+    pub fn from_fixture(fixture: &Fixture) -> Self {
+        let mut tree = FixtureTree {
+            nodes: vec![FixtureNode {
+                info: ElementInfo {
+                    name: "Desktop".to_string(),
+                    control_type: "Pane".to_string(),
+                    enabled: true,
+                    ..ElementInfo::default()
+                },
+                path: String::new(),
+                parent: None,
+                children: Vec::new(),
+            }],
+            focused: None,
+            keyboard_focus: Mutex::new(None),
+            actions: Mutex::new(Vec::new()),
+            dismiss_on_action: fixture.dismiss_on_action,
+            hidden: Mutex::new(HashSet::new()),
+            hung: HashSet::new(),
+            idle: fixture.idle_secs.map(Duration::from_secs_f64),
+            minimized: Mutex::new(HashSet::new()),
+            maximized: Mutex::new(HashSet::new()),
+            unfocusable: HashSet::new(),
+        };
+        for window in &fixture.windows {
+            let index = tree.add(&window.root, 0);
+            if fixture.hung_windows.contains(&window.window) {
+                tree.hung.insert(index);
+            }
+            if fixture.focused_window.as_deref() == Some(window.window.as_str()) {
+                tree.focused = Some(index);
+            }
+            if fixture.minimized_windows.contains(&window.window) {
+                if let Ok(mut minimized) = tree.minimized.lock() {
+                    minimized.insert(index);
+                }
+            }
+            if fixture.maximized_windows.contains(&window.window) {
+                if let Ok(mut maximized) = tree.maximized.lock() {
+                    maximized.insert(index);
+                }
+            }
+            if fixture.unfocusable_windows.contains(&window.window) {
+                tree.unfocusable.insert(index);
+            }
+        }
+        Self {
+            tree: Arc::new(tree),
+        }
+    }
+
+    /// Actions performed so far, oldest first.
+    pub fn actions(&self) -> Vec<FixtureAction> {
+        self.tree
+            .actions
+            .lock()
+            .map(|a| a.clone())
+            .unwrap_or_default()
+    }
+
+    fn element(&self, index: usize) -> FixtureElement {
+        FixtureElement {
+            tree: self.tree.clone(),
+            index,
+        }
+    }
+}
+
+impl Backend for FixtureBackend {
+    type Element = FixtureElement;
+
+    fn new() -> Result<Self> {
+        Err(anyhow!(
+            "FixtureBackend must be created with FixtureBackend::load"
+        ))
+    }
+
+    fn get_root_element(&self) -> Result<Self::Element> {
+        Ok(self.element(0))
+    }
+
+    /// The element last focused through `set_focus`, else the recorded
+    /// focused window.
+    fn get_focused_element(&self) -> Result<Self::Element> {
+        let focus = self.tree.keyboard_focus.lock().ok().and_then(|f| *f);
+        focus
+            .or(self.tree.focused)
+            .map(|i| self.element(i))
+            .ok_or_else(|| anyhow!("No focused window recorded"))
+    }
+
+    fn idle_time(&self) -> Result<Duration> {
+        self.tree
+            .idle
+            .ok_or_else(|| anyhow!("No idle time recorded"))
+    }
+
+    /// Recorded as a `keys` action on the element last focused; Enter or
+    /// Space dismiss its prompt like a click would.
+    fn send_keys(&self, keys: &KeySequence) -> Result<()> {
+        let focus = self.tree.keyboard_focus.lock().ok().and_then(|f| *f);
+        if let Ok(mut actions) = self.tree.actions.lock() {
+            actions.push(FixtureAction {
+                kind: "keys",
+                name: keys.to_string(),
+                path: focus
+                    .map(|i| self.tree.nodes[i].path.clone())
+                    .unwrap_or_default(),
+            });
+        }
+        if let Some(index) = focus {
+            if keys.chords.iter().any(|c| c.key.activates()) && !self.tree.is_hidden(index) {
+                self.tree.dismiss(index);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_all_windows(&self) -> Result<Vec<Self::Element>> {
+        Ok(self.tree.nodes[0]
+            .children
+            .iter()
+            .map(|&i| self.element(i))
+            .collect())
+    }
+
+    fn get_parent(&self, element: &Self::Element) -> Result<Self::Element> {
+        element
+            .node()
+            .parent
+            .map(|i| self.element(i))
+            .ok_or_else(|| anyhow!("Root has no parent"))
+    }
+
+    fn get_children(&self, element: &Self::Element) -> Result<Vec<Self::Element>> {
+        element.find_elements(Scope::Children)
+    }
+
+    fn get_siblings(&self, element: &Self::Element) -> Result<Siblings<Self::Element>> {
+        let Some(parent) = element.node().parent else {
+            return Ok((Vec::new(), Vec::new()));
+        };
+        let siblings = &self.tree.nodes[parent].children;
+        let pos = siblings
+            .iter()
+            .position(|&i| i == element.index)
+            .ok_or_else(|| anyhow!("Element missing from its parent"))?;
+
+        let prev = siblings[pos.saturating_sub(2)..pos]
+            .iter()
+            .map(|&i| self.element(i))
+            .collect();
+        let next = siblings[pos + 1..(pos + 3).min(siblings.len())]
+            .iter()
+            .map(|&i| self.element(i))
+            .collect();
+        Ok((prev, next))
+    }
+}
+
+/// Reads `tests/fixtures/<name>` from the crate directory, for tests that
+/// adjust the fixture before building a backend from it.
+#[cfg(test)]
+pub fn load_fixture(name: &str) -> Fixture {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+    Fixture::load(&path).unwrap_or_else(|e| panic!("Failed to load fixture {}: {}", name, e))
+}
+
+/// Loads `tests/fixtures/<name>` from the crate directory.
+#[cfg(test)]
+pub fn load_test_fixture(name: &str) -> FixtureBackend {
+    FixtureBackend::from_fixture(&load_fixture(name))
+}
@@ -1,18 +1,72 @@
-use super::{Backend, Element};
+use super::{
+    is_timeout, Backend, Bounds, CallTimeout, Element, ElementFilter, ElementInfo, Scope, Siblings,
+    DEFAULT_CALL_TIMEOUT,
+};
 use anyhow::{anyhow, Result};
-use std::sync::Arc;
 use std::fmt;
-use tracing::{info, error, debug};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OnceCell, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{debug, error, info};
+
+use crate::keys::{Key, KeySequence, Modifiers};
+use atspi::proxy::accessible::AccessibleProxy;
+use atspi::proxy::device_event_controller::{DeviceEventControllerProxy, KeySynthType};
+use atspi::{State, StateSet};
+
+/// Maximum D-Bus calls in flight at once.
+const MAX_CONCURRENT_CALLS: usize = 32;
+/// Depth guard for tree walks, in case a provider reports a cyclic tree.
+const MAX_WALK_DEPTH: usize = 64;
+
+/// D-Bus connection and runtime shared by a backend and all its elements.
+struct Bus {
+    connection: zbus::Connection,
+    runtime: tokio::runtime::Runtime,
+    permits: Semaphore,
+    /// Per-call timeout in milliseconds; a hung application must not stall a scan.
+    timeout_ms: AtomicU64,
+    /// Session bus, opened on first use for idle time queries.
+    session: OnceCell<zbus::Connection>,
+}
 
-// Try atspi::proxy::accessible::AccessibleProxy
-use atspi::proxy::accessible::AccessibleProxy; 
+impl Bus {
+    /// Runs one call under the concurrency limit and timeout.
+    async fn call<T, E: fmt::Debug>(
+        &self,
+        what: &str,
+        call: impl Future<Output = std::result::Result<T, E>>,
+    ) -> Result<T> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| anyhow!("Failed to {}: {:?}", what, e))?;
+        let timeout = Duration::from_millis(self.timeout_ms.load(Ordering::Relaxed));
+        match tokio::time::timeout(timeout, call).await {
+            Ok(result) => result.map_err(|e| anyhow!("Failed to {}: {:?}", what, e)),
+            Err(_) => Err(CallTimeout {
+                call: what.to_string(),
+                after: timeout,
+            }
+            .into()),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct LinuxElement {
-    connection: Arc<atspi::connection::AccessibilityConnection>,
-    runtime: Arc<tokio::runtime::Runtime>,
+    bus: Arc<Bus>,
     bus_name: String,
     path: String,
+    /// Built on first use and shared by clones.
+    proxy: Arc<OnceCell<AccessibleProxy<'static>>>,
+    /// Filled by `find_elements_filtered`, like a UIA cache request.
+    name: Option<String>,
+    role: Option<String>,
 }
 
 impl fmt::Debug for LinuxElement {
@@ -24,38 +78,232 @@ impl fmt::Debug for LinuxElement {
     }
 }
 
-impl Element for LinuxElement {
-    fn get_name(&self) -> Result<String> {
-        let connection = self.connection.clone();
-        let bus_name = self.bus_name.clone();
-        let path = self.path.clone();
-
-        let name = self.runtime.block_on(async move {
-                let proxy = AccessibleProxy::builder((**connection).connection())
-                    .destination(bus_name)?
-                    .path(path)?
+/// X11 modifier mask: Shift, Control, Mod1 (Alt), Mod4 (Super).
+fn modifier_mask(modifiers: Modifiers) -> i32 {
+    [
+        (modifiers.shift, 1),
+        (modifiers.ctrl, 1 << 2),
+        (modifiers.alt, 1 << 3),
+        (modifiers.meta, 1 << 6),
+    ]
+    .into_iter()
+    .filter(|(held, _)| *held)
+    .fold(0, |mask, (_, bit)| mask | bit)
+}
+
+/// X keysym of `key`.
+fn keysym(key: Key) -> i32 {
+    match key {
+        Key::Enter => 0xff0d,
+        Key::Tab => 0xff09,
+        Key::Escape => 0xff1b,
+        Key::Space => 0x20,
+        Key::Backspace => 0xff08,
+        Key::Delete => 0xffff,
+        Key::Up => 0xff52,
+        Key::Down => 0xff54,
+        Key::Left => 0xff51,
+        Key::Right => 0xff53,
+        Key::Home => 0xff50,
+        Key::End => 0xff57,
+        Key::PageUp => 0xff55,
+        Key::PageDown => 0xff56,
+        Key::F(n) => 0xffbe + i32::from(n) - 1,
+        // Latin-1 keysyms equal the code point, the rest are offset
+        Key::Char(c) if (c as u32) < 0x100 => c as i32,
+        Key::Char(c) => 0x0100_0000 + c as i32,
+    }
+}
+
+/// Per-node results of a tree walk task.
+struct Visit {
+    index: usize,
+    children: Result<Vec<LinuxElement>>,
+    name: Option<String>,
+    role: Option<String>,
+}
+
+impl LinuxElement {
+    fn new(bus: &Arc<Bus>, bus_name: String, path: String) -> Self {
+        Self {
+            bus: bus.clone(),
+            bus_name,
+            path,
+            proxy: Arc::new(OnceCell::new()),
+            name: None,
+            role: None,
+        }
+    }
+
+    async fn proxy(&self) -> zbus::Result<&AccessibleProxy<'static>> {
+        self.proxy
+            .get_or_try_init(|| async {
+                AccessibleProxy::builder(&self.bus.connection)
+                    .destination(self.bus_name.clone())?
+                    .path(self.path.clone())?
+                    .cache_properties(zbus::CacheProperties::No)
                     .build()
-                    .await?;
-                proxy.name().await
+                    .await
             })
-            .map_err(|e| anyhow!("Failed to get name: {:?}", e))?;
-        Ok(name)
+            .await
     }
 
-    fn get_control_type(&self) -> Result<String> {
-        let connection = self.connection.clone();
-        let bus_name = self.bus_name.clone();
-        let path = self.path.clone();
-
-        let role = self.runtime.block_on(async move {
-                let proxy = AccessibleProxy::builder((**connection).connection())
-                    .destination(bus_name)?
-                    .path(path)?
-                    .build()
-                    .await?;
-                proxy.get_role().await
+    /// Runs one call for this element from synchronous code.
+    fn block_on<T, E: fmt::Debug>(
+        &self,
+        what: &str,
+        call: impl Future<Output = std::result::Result<T, E>>,
+    ) -> Result<T> {
+        self.bus.runtime.block_on(self.bus.call(what, call))
+    }
+
+    fn state(&self) -> Result<StateSet> {
+        self.block_on("get state", async { self.proxy().await?.get_state().await })
+    }
+
+    async fn name_async(&self) -> Result<String> {
+        self.bus
+            .call("get name", async { self.proxy().await?.name().await })
+            .await
+    }
+
+    async fn role_async(&self) -> Result<String> {
+        let role = self
+            .bus
+            .call("get role", async { self.proxy().await?.get_role().await })
+            .await?;
+        Ok(format!("{:?}", role))
+    }
+
+    /// All children in one `GetChildren` call.
+    async fn children_async(&self) -> Result<Vec<LinuxElement>> {
+        let children = self
+            .bus
+            .call("get children", async {
+                self.proxy().await?.get_children().await
             })
-            .map_err(|e| anyhow!("Failed to get role: {:?}", e))?;
+            .await?;
+        Ok(children
+            .into_iter()
+            .map(|c| LinuxElement::new(&self.bus, c.name, c.path.to_string()))
+            .collect())
+    }
+
+    /// Walks below this element breadth-first, one concurrent task per node
+    /// of a level, optionally prefetching names and roles in the same task.
+    /// Returns the elements in document (depth-first) order, like UIA FindAll.
+    /// Fails only when this element itself does not answer.
+    async fn walk(
+        &self,
+        scope: Scope,
+        prefetch_name: bool,
+        prefetch_role: bool,
+    ) -> Result<Vec<Self>> {
+        let mut nodes = vec![self.clone()];
+        let mut children: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut level = vec![0];
+        let max_depth = match scope {
+            Scope::Children => 1,
+            Scope::Descendants => MAX_WALK_DEPTH,
+        };
+
+        for depth in 0..=max_depth {
+            if level.is_empty() {
+                break;
+            }
+            // The last level only needs its own properties
+            let expand = depth < max_depth;
+            let fetch_props = depth > 0;
+            let mut tasks = JoinSet::new();
+            for &index in &level {
+                let element: LinuxElement = nodes[index].clone();
+                tasks.spawn(async move {
+                    let children = async {
+                        if expand {
+                            element.children_async().await
+                        } else {
+                            Ok(Vec::new())
+                        }
+                    };
+                    let name = async {
+                        if fetch_props && prefetch_name {
+                            element.name_async().await.ok()
+                        } else {
+                            None
+                        }
+                    };
+                    let role = async {
+                        if fetch_props && prefetch_role {
+                            element.role_async().await.ok()
+                        } else {
+                            None
+                        }
+                    };
+                    let (children, name, role) = tokio::join!(children, name, role);
+                    Visit {
+                        index,
+                        children,
+                        name,
+                        role,
+                    }
+                });
+            }
+
+            let mut visits = Vec::new();
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok(visit) => visits.push(visit),
+                    Err(e) => debug!("Tree walk task failed: {:?}", e),
+                }
+            }
+            visits.sort_by_key(|v| v.index);
+
+            let mut next = Vec::new();
+            for visit in visits {
+                nodes[visit.index].name = visit.name;
+                nodes[visit.index].role = visit.role;
+                match visit.children {
+                    Ok(kids) => {
+                        for kid in kids {
+                            let kid_index = nodes.len();
+                            nodes.push(kid);
+                            children.push(Vec::new());
+                            children[visit.index].push(kid_index);
+                            next.push(kid_index);
+                        }
+                    }
+                    // The whole application is not answering
+                    Err(e) if visit.index == 0 && is_timeout(&e) => return Err(e),
+                    // An unresponsive subtree just ends that branch
+                    Err(e) => debug!("Skipping children of {}: {:?}", nodes[visit.index].path, e),
+                }
+            }
+            level = next;
+        }
+
+        let mut result = Vec::new();
+        let mut stack: Vec<usize> = children[0].iter().rev().copied().collect();
+        while let Some(index) = stack.pop() {
+            result.push(nodes[index].clone());
+            stack.extend(children[index].iter().rev().copied());
+        }
+        Ok(result)
+    }
+}
+
+impl Element for LinuxElement {
+    fn get_name(&self) -> Result<String> {
+        if let Some(name) = &self.name {
+            return Ok(name.clone());
+        }
+        self.block_on("get name", async { self.proxy().await?.name().await })
+    }
+
+    fn get_control_type(&self) -> Result<String> {
+        if let Some(role) = &self.role {
+            return Ok(role.clone());
+        }
+        let role = self.block_on("get role", async { self.proxy().await?.get_role().await })?;
         Ok(format!("{:?}", role))
     }
 
@@ -75,50 +323,273 @@ impl Element for LinuxElement {
         Err(anyhow!("GetClickablePoint not implemented"))
     }
 
-    fn find_elements(&self, _scope: super::Scope) -> Result<Vec<Self>> {
-        Err(anyhow!("FindElements not implemented"))
+    fn get_bounds(&self) -> Result<Bounds> {
+        self.block_on("get extents", self.get_extents())
+    }
+
+    fn runtime_id(&self) -> Result<String> {
+        // Stale objects stop answering on the bus, so probe before handing out the ID
+        self.state()?;
+        Ok(format!("{}:{}", self.bus_name, self.path))
+    }
+
+    fn same_as(&self, other: &Self) -> bool {
+        self.bus_name == other.bus_name && self.path == other.path
+    }
+
+    fn is_enabled(&self) -> Result<bool> {
+        let state = self.state()?;
+        Ok(state.contains(State::Enabled) && state.contains(State::Sensitive))
+    }
+
+    fn is_offscreen(&self) -> Result<bool> {
+        let state = self.state()?;
+        Ok(!state.contains(State::Showing) || state.contains(State::Defunct))
+    }
+
+    fn get_info(&self) -> Result<ElementInfo> {
+        self.block_on("get info", async {
+            let proxy = self.proxy().await?;
+            // Independent calls go out concurrently
+            let (state, name, role, help_text, accessible_id, interfaces) = tokio::join!(
+                proxy.get_state(),
+                proxy.name(),
+                proxy.get_role(),
+                proxy.description(),
+                proxy.accessible_id(),
+                proxy
+                    .inner()
+                    .call::<_, _, Vec<String>>("GetInterfaces", &()),
+            );
+            let state = state?;
+            let patterns: Vec<String> = interfaces?
+                .iter()
+                .map(|i| i.trim_start_matches("org.a11y.atspi.").to_string())
+                .collect();
+
+            // AccessibleId needs AT-SPI 2.34+; toolkits also expose an "id" attribute
+            let automation_id = match accessible_id {
+                Ok(id) if !id.is_empty() => id,
+                _ => proxy
+                    .get_attributes()
+                    .await
+                    .ok()
+                    .and_then(|attrs| attrs.get("id").cloned())
+                    .unwrap_or_default(),
+            };
+
+            let bounds = if patterns.iter().any(|p| p == "Component") {
+                self.get_extents().await.ok()
+            } else {
+                None
+            };
+
+            let value = if patterns.iter().any(|p| p == "Value") {
+                let value_proxy: zbus::Proxy = zbus::ProxyBuilder::new_bare(&self.bus.connection)
+                    .destination(self.bus_name.clone())?
+                    .path(self.path.clone())?
+                    .interface("org.a11y.atspi.Value")?
+                    .cache_properties(zbus::CacheProperties::No)
+                    .build()
+                    .await?;
+                value_proxy
+                    .get_property::<f64>("CurrentValue")
+                    .await
+                    .ok()
+                    .map(|v| v.to_string())
+            } else {
+                None
+            };
+
+            Ok::<_, anyhow::Error>(ElementInfo {
+                name: name?,
+                control_type: format!("{:?}", role?),
+                bounds,
+                enabled: state.contains(State::Enabled) && state.contains(State::Sensitive),
+                offscreen: !state.contains(State::Showing) || state.contains(State::Defunct),
+                automation_id,
+                help_text: help_text.unwrap_or_default(),
+                value,
+                patterns,
+            })
+        })
+    }
+
+    fn find_elements(&self, scope: Scope) -> Result<Vec<Self>> {
+        self.bus.runtime.block_on(self.walk(scope, false, false))
+    }
+
+    fn find_elements_filtered(&self, scope: Scope, filter: &ElementFilter) -> Result<Vec<Self>> {
+        let elements = self.bus.runtime.block_on(self.walk(
+            scope,
+            filter.named_only,
+            filter.clickable_only,
+        ))?;
+        Ok(elements
+            .into_iter()
+            .filter(|e| {
+                (!filter.named_only || e.name.as_deref().is_some_and(|n| !n.is_empty()))
+                    && (!filter.clickable_only
+                        || e.role
+                            .as_deref()
+                            .is_some_and(|r| r.contains("Button") || r.contains("Hyperlink")))
+            })
+            .collect())
+    }
+}
+
+impl LinuxElement {
+    async fn get_extents(&self) -> zbus::Result<Bounds> {
+        let proxy: zbus::Proxy = zbus::ProxyBuilder::new_bare(&self.bus.connection)
+            .destination(self.bus_name.clone())?
+            .path(self.path.clone())?
+            .interface("org.a11y.atspi.Component")?
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await?;
+        // Coordinate type 0 = screen coordinates
+        let (x, y, width, height) = proxy
+            .call::<_, _, (i32, i32, i32, i32)>("GetExtents", &(0u32))
+            .await?;
+        Ok(Bounds {
+            x,
+            y,
+            width,
+            height,
+        })
     }
 }
 
 pub struct LinuxBackend {
-    connection: Arc<atspi::connection::AccessibilityConnection>,
-    runtime: Arc<tokio::runtime::Runtime>,
+    bus: Arc<Bus>,
 }
 
 impl Backend for LinuxBackend {
     type Element = LinuxElement;
 
     fn new() -> Result<Self> {
-        let runtime = tokio::runtime::Runtime::new().unwrap();
-        let connection = runtime.block_on(atspi::connection::AccessibilityConnection::open())
+        let runtime = tokio::runtime::Runtime::new()?;
+        let connection = runtime
+            .block_on(atspi::connection::AccessibilityConnection::open())
             .map_err(|e| anyhow!("Failed to open connection: {:?}", e))?;
-        
-        Ok(Self { 
-            connection: Arc::new(connection),
-            runtime: Arc::new(runtime),
+
+        Ok(Self {
+            bus: Arc::new(Bus {
+                connection: connection.connection().clone(),
+                runtime,
+                permits: Semaphore::new(MAX_CONCURRENT_CALLS),
+                timeout_ms: AtomicU64::new(DEFAULT_CALL_TIMEOUT.as_millis() as u64),
+                session: OnceCell::new(),
+            }),
         })
     }
 
     fn get_root_element(&self) -> Result<Self::Element> {
         // Root is at /org/a11y/atspi/accessible/root on org.a11y.atspi.Registry
-        Ok(LinuxElement {
-            connection: self.connection.clone(),
-            runtime: self.runtime.clone(),
-            bus_name: "org.a11y.atspi.Registry".to_string(),
-            path: "/org/a11y/atspi/accessible/root".to_string(),
-        })
+        Ok(LinuxElement::new(
+            &self.bus,
+            "org.a11y.atspi.Registry".to_string(),
+            "/org/a11y/atspi/accessible/root".to_string(),
+        ))
     }
 
     fn get_focused_element(&self) -> Result<Self::Element> {
         Err(anyhow!("GetFocused not implemented"))
     }
 
+    fn send_keys(&self, keys: &KeySequence) -> Result<()> {
+        let bus = &self.bus;
+        bus.runtime.block_on(async {
+            let controller = bus
+                .call(
+                    "open device event controller",
+                    DeviceEventControllerProxy::new(&bus.connection),
+                )
+                .await?;
+            for chord in &keys.chords {
+                // Modifiers are held by locking them around the key
+                let mask = modifier_mask(chord.modifiers);
+                if mask != 0 {
+                    bus.call(
+                        "lock modifiers",
+                        controller.generate_keyboard_event(mask, "", KeySynthType::Lockmodifiers),
+                    )
+                    .await?;
+                }
+                let sent = bus
+                    .call(
+                        "send key",
+                        controller.generate_keyboard_event(
+                            keysym(chord.key),
+                            "",
+                            KeySynthType::Sym,
+                        ),
+                    )
+                    .await;
+                if mask != 0 {
+                    bus.call(
+                        "unlock modifiers",
+                        controller.generate_keyboard_event(mask, "", KeySynthType::Unlockmodifiers),
+                    )
+                    .await?;
+                }
+                sent?;
+            }
+            Ok(())
+        })
+    }
+
+    // Mutter's idle monitor on GNOME, the ScreenSaver service elsewhere
+    // (KDE and most other desktops)
+    fn idle_time(&self) -> Result<Duration> {
+        let bus = &self.bus;
+        bus.runtime.block_on(async {
+            let session = bus
+                .session
+                .get_or_try_init(zbus::Connection::session)
+                .await
+                .map_err(|e| anyhow!("Failed to open session bus: {:?}", e))?;
+            let mutter = bus
+                .call(
+                    "get Mutter idle time",
+                    session.call_method(
+                        Some("org.gnome.Mutter.IdleMonitor"),
+                        "/org/gnome/Mutter/IdleMonitor/Core",
+                        Some("org.gnome.Mutter.IdleMonitor"),
+                        "GetIdletime",
+                        &(),
+                    ),
+                )
+                .await
+                .and_then(|reply| Ok(reply.body::<u64>()?));
+            if let Ok(ms) = mutter {
+                return Ok(Duration::from_millis(ms));
+            }
+            let reply = bus
+                .call(
+                    "get session idle time",
+                    session.call_method(
+                        Some("org.freedesktop.ScreenSaver"),
+                        "/org/freedesktop/ScreenSaver",
+                        Some("org.freedesktop.ScreenSaver"),
+                        "GetSessionIdleTime",
+                        &(),
+                    ),
+                )
+                .await?;
+            Ok(Duration::from_millis(u64::from(reply.body::<u32>()?)))
+        })
+    }
+
+    fn set_call_timeout(&mut self, timeout: Duration) {
+        self.bus
+            .timeout_ms
+            .store(timeout.as_millis() as u64, Ordering::Relaxed);
+    }
+
     fn get_all_windows(&self) -> Result<Vec<Self::Element>> {
-        // To get all windows, we usually iterate children of the root (applications)
-        // and then children of applications (windows).
-        debug!("Getting root element...");
+        // Windows are the children of the applications under the root
         let root = self.get_root_element()?;
-        debug!("Getting children of root (apps)...");
         let apps = match self.get_children(&root) {
             Ok(a) => a,
             Err(e) => {
@@ -126,76 +597,272 @@ impl Backend for LinuxBackend {
                 return Err(e);
             }
         };
-        info!("Found {} apps/children under root", apps.len());
-        
-        let mut windows = Vec::new();
-        for app in apps {
-            // Some apps might fail to return children, ignore them
-            // debug!("Getting children for app {:?}", app); // app debug might be verbose
-            match self.get_children(&app) {
-                Ok(app_windows) => {
-                    // debug!("App has {} windows", app_windows.len());
-                    windows.extend(app_windows);
-                },
-                Err(e) => {
-                    // This is common for some registry entries that aren't real apps
-                    debug!("Failed to get children for app: {:?}", e);
+        debug!("Found {} apps/children under root", apps.len());
+
+        // Applications are queried concurrently; one that fails (common for
+        // registry entries that aren't real apps) is skipped
+        let windows = self.bus.runtime.block_on(async {
+            let mut tasks = JoinSet::new();
+            for (index, app) in apps.into_iter().enumerate() {
+                tasks.spawn(async move { (index, app.children_async().await) });
+            }
+            let mut results = Vec::new();
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok((index, Ok(app_windows))) => results.push((index, app_windows)),
+                    Ok((_, Err(e))) => debug!("Failed to get children for app: {:?}", e),
+                    Err(e) => debug!("App query task failed: {:?}", e),
                 }
             }
-        }
+            results.sort_by_key(|(index, _)| *index);
+            results.into_iter().flat_map(|(_, w)| w).collect::<Vec<_>>()
+        });
         info!("Found total {} windows across all apps", windows.len());
         Ok(windows)
     }
 
-    fn get_parent(&self, _element: &Self::Element) -> Result<Self::Element> {
-        Err(anyhow!("GetParent not implemented"))
+    fn get_parent(&self, element: &Self::Element) -> Result<Self::Element> {
+        let parent = element.block_on("get parent", async {
+            element.proxy().await?.parent().await
+        })?;
+
+        // The desktop root reports the null object as its parent
+        let parent_path = parent.path.to_string();
+        if parent.name.is_empty() || parent_path == "/org/a11y/atspi/null" {
+            return Err(anyhow!("{} has no parent", element.path));
+        }
+        Ok(LinuxElement::new(&self.bus, parent.name, parent_path))
     }
 
     fn get_children(&self, element: &Self::Element) -> Result<Vec<Self::Element>> {
-        let connection = self.connection.clone();
-        let bus_name = element.bus_name.clone();
-        let path = element.path.clone();
-
-        let children_data = self.runtime.block_on(async move {
-                let conn = (**connection).connection();
-                let proxy: zbus::Proxy = zbus::ProxyBuilder::new_bare(conn)
-                    .destination(bus_name.clone())?
-                    .path(path.clone())?
-                    .interface("org.a11y.atspi.Accessible")?
-                    .cache_properties(zbus::CacheProperties::No)
-                    .build()
-                    .await.map_err(|e| anyhow!("Failed to build proxy: {:?}", e))?;
-
-                let count: i32 = proxy.get_property("ChildCount").await
-                    .map_err(|e| anyhow!("Failed to get ChildCount: {:?}", e))?;
-                
-                let mut results = Vec::new();
-                for i in 0..count {
-                    let (child_bus, child_path): (String, zbus::zvariant::OwnedObjectPath) = proxy.call(
-                        "GetChildAtIndex",
-                        &(i)
-                    ).await.map_err(|e| anyhow!("Failed to GetChildAtIndex({}): {:?}", i, e))?;
-                    
-                    results.push((child_bus, child_path.to_string()));
-                }
-                Ok::<_, anyhow::Error>(results)
+        self.bus
+            .runtime
+            .block_on(element.children_async())
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to get children for {} {}: {:?}",
+                    element.bus_name,
+                    element.path,
+                    e
+                )
             })
-            .map_err(|e| anyhow!("Failed to get children for {} {}: {:?}", element.bus_name, element.path, e))?;
+    }
 
-        let mut result = Vec::new();
-        for (c_bus, c_path) in children_data {
-             result.push(LinuxElement {
-                 connection: self.connection.clone(),
-                 runtime: self.runtime.clone(),
-                 bus_name: c_bus,
-                 path: c_path,
-             });
+    fn get_siblings(&self, _element: &Self::Element) -> Result<Siblings<Self::Element>> {
+        Err(anyhow!("GetSiblings not implemented"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::os::unix::net::UnixStream;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+    use std::time::Instant;
+    use zbus::zvariant::OwnedObjectPath;
+    use zbus::{ConnectionBuilder, Guid};
+
+    const STUB_NAME: &str = ":1.1";
+
+    /// One accessible of a stand-in application; `hang` makes GetChildren
+    /// never answer, like a hung application.
+    struct StubNode {
+        name: String,
+        children: Vec<String>,
+        hang: bool,
+    }
+
+    #[zbus::dbus_interface(name = "org.a11y.atspi.Accessible")]
+    impl StubNode {
+        async fn get_children(&self) -> Vec<(String, OwnedObjectPath)> {
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+            self.children
+                .iter()
+                .map(|path| (STUB_NAME.to_string(), path.as_str().try_into().unwrap()))
+                .collect()
+        }
+
+        #[dbus_interface(property)]
+        fn name(&self) -> String {
+            self.name.clone()
         }
-        
-        Ok(result)
     }
 
-    fn get_siblings(&self, _element: &Self::Element) -> Result<(Vec<Self::Element>, Vec<Self::Element>)> {
-        Err(anyhow!("GetSiblings not implemented"))
+    /// Records the keyboard events a device event controller was asked to
+    /// synthesize.
+    struct StubController {
+        events: Arc<Mutex<Vec<(i32, KeySynthType)>>>,
+    }
+
+    #[zbus::dbus_interface(name = "org.a11y.atspi.DeviceEventController")]
+    impl StubController {
+        fn generate_keyboard_event(&self, keycode: i32, _keystring: &str, kind: KeySynthType) {
+            self.events.lock().unwrap().push((keycode, kind));
+        }
+    }
+
+    /// A bus connected peer-to-peer to a stand-in application serving `tree`
+    /// (path -> (children, hangs)), and the stand-in's end of it.
+    fn stub_bus(
+        tree: HashMap<&str, (Vec<&str>, bool)>,
+        permits: usize,
+        timeout: Duration,
+    ) -> (Arc<Bus>, zbus::Connection) {
+        stub_service(
+            |mut service| {
+                for (path, (children, hang)) in tree {
+                    let node = StubNode {
+                        name: path.trim_start_matches('/').to_string(),
+                        children: children.into_iter().map(String::from).collect(),
+                        hang,
+                    };
+                    service = service.serve_at(path.to_string(), node).unwrap();
+                }
+                service
+            },
+            permits,
+            timeout,
+        )
+    }
+
+    /// A bus connected peer-to-peer to a stand-in serving the objects
+    /// `serve` adds, and the stand-in's end of it.
+    fn stub_service(
+        serve: impl for<'a> FnOnce(ConnectionBuilder<'a>) -> ConnectionBuilder<'a>,
+        permits: usize,
+        timeout: Duration,
+    ) -> (Arc<Bus>, zbus::Connection) {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (client, server) = UnixStream::pair().unwrap();
+        let guid = Guid::generate();
+        let service = serve(ConnectionBuilder::unix_stream(server).server(&guid).p2p());
+        // Both ends authenticate at once
+        let (connection, service) = runtime.block_on(async {
+            tokio::join!(
+                ConnectionBuilder::unix_stream(client).p2p().build(),
+                service.build()
+            )
+        });
+        let bus = Bus {
+            connection: connection.unwrap(),
+            runtime,
+            permits: Semaphore::new(permits),
+            timeout_ms: AtomicU64::new(timeout.as_millis() as u64),
+            session: OnceCell::new(),
+        };
+        (Arc::new(bus), service.unwrap())
+    }
+
+    fn element(bus: &Arc<Bus>, path: &str) -> LinuxElement {
+        LinuxElement::new(bus, STUB_NAME.to_string(), path.to_string())
+    }
+
+    #[test]
+    fn walk_skips_hung_subtrees_and_fails_on_a_hung_root() {
+        let tree = HashMap::from([
+            ("/root", (vec!["/ok", "/hung"], false)),
+            ("/ok", (vec!["/leaf"], false)),
+            ("/leaf", (vec![], false)),
+            ("/hung", (vec!["/never"], true)),
+        ]);
+        let (bus, _service) = stub_bus(tree, MAX_CONCURRENT_CALLS, Duration::from_millis(200));
+
+        let walked = bus
+            .runtime
+            .block_on(element(&bus, "/root").walk(Scope::Descendants, true, false))
+            .unwrap();
+        let names: Vec<Option<&str>> = walked.iter().map(|e| e.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("ok"), Some("leaf"), Some("hung")]);
+
+        let error = bus
+            .runtime
+            .block_on(element(&bus, "/hung").walk(Scope::Children, false, false))
+            .unwrap_err();
+        let timeout = error.downcast_ref::<CallTimeout>().unwrap();
+        assert_eq!(timeout.call, "get children");
+        assert_eq!(timeout.after, Duration::from_millis(200));
+    }
+
+    #[test]
+    fn calls_wait_for_a_permit_and_time_out_while_waiting_on_the_reply() {
+        let tree = HashMap::from([("/hung", (vec![], true))]);
+        let (bus, _service) = stub_bus(tree, 2, Duration::from_millis(100));
+
+        // Six calls through two permits: two at a time, never more
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        bus.runtime.block_on(async {
+            let mut tasks = JoinSet::new();
+            for _ in 0..6 {
+                let (bus, in_flight, most) = (bus.clone(), in_flight.clone(), most.clone());
+                tasks.spawn(async move {
+                    bus.call("probe", async {
+                        let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        most.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, ()>(())
+                    })
+                    .await
+                });
+            }
+            while let Some(joined) = tasks.join_next().await {
+                joined.unwrap().unwrap();
+            }
+        });
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+
+        // Three hung calls through two permits: the third only starts its
+        // timeout once a permit is free
+        let started = Instant::now();
+        let results: Vec<bool> = bus.runtime.block_on(async {
+            let mut tasks = JoinSet::new();
+            for _ in 0..3 {
+                let bus = bus.clone();
+                tasks.spawn(async move { element(&bus, "/hung").children_async().await });
+            }
+            let mut results = Vec::new();
+            while let Some(joined) = tasks.join_next().await {
+                results.push(is_timeout(&joined.unwrap().unwrap_err()));
+            }
+            results
+        });
+        assert_eq!(results, vec![true; 3]);
+        assert!(started.elapsed() >= Duration::from_millis(200));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn keys_are_synthesized_with_modifiers_locked_around_them() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let controller = StubController {
+            events: events.clone(),
+        };
+        let (bus, _service) = stub_service(
+            |service| {
+                service
+                    .serve_at("/org/a11y/atspi/registry/deviceeventcontroller", controller)
+                    .unwrap()
+            },
+            MAX_CONCURRENT_CALLS,
+            DEFAULT_CALL_TIMEOUT,
+        );
+
+        let backend = LinuxBackend { bus };
+        backend
+            .send_keys(&"Ctrl+Shift+A, Enter".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (0b101, KeySynthType::Lockmodifiers),
+                (0x61, KeySynthType::Sym),
+                (0b101, KeySynthType::Unlockmodifiers),
+                (0xff0d, KeySynthType::Sym),
+            ]
+        );
+    }
+}
@@ -1,4 +1,8 @@
+use crate::keys::KeySequence;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Scope {
@@ -7,6 +11,94 @@ pub enum Scope {
     Descendants,
 }
 
+/// Screen-space bounding rectangle of an element.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Bounds {
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    /// Shortest distance between the edges of two rectangles; 0 when they
+    /// touch or overlap.
+    pub fn distance_to(&self, other: &Bounds) -> f64 {
+        let gap = |a: i32, a_len: i32, b: i32, b_len: i32| {
+            (b - (a + a_len)).max(a - (b + b_len)).max(0) as f64
+        };
+        let dx = gap(self.x, self.width, other.x, other.width);
+        let dy = gap(self.y, self.height, other.y, other.height);
+        dx.hypot(dy)
+    }
+}
+
+/// Properties of one element, fetched together: in a single round-trip with
+/// a UIA cache request, as concurrent calls on one AT-SPI proxy on Linux.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ElementInfo {
+    pub name: String,
+    pub control_type: String,
+    pub bounds: Option<Bounds>,
+    pub enabled: bool,
+    pub offscreen: bool,
+    /// UIA AutomationId, AT-SPI accessible ID (or toolkit `id` attribute).
+    pub automation_id: String,
+    /// UIA HelpText, AT-SPI description.
+    pub help_text: String,
+    pub value: Option<String>,
+    /// Supported UIA patterns (Windows) or AT-SPI interfaces (Linux).
+    pub patterns: Vec<String>,
+}
+
+/// Narrows `Element::find_elements_filtered`. Backends may apply it only
+/// partially, so callers still check names and types of the results.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ElementFilter {
+    /// Skip elements with an empty name.
+    pub named_only: bool,
+    /// Only Button and Hyperlink elements.
+    pub clickable_only: bool,
+}
+
+/// Visual state of a top-level window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowState {
+    Normal,
+    Minimized,
+    /// Only reported by UIA.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Maximized,
+}
+
+/// Default for `Backend::set_call_timeout`.
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A call the target application did not answer in time, usually because
+/// it is hung.
+#[derive(Debug)]
+pub struct CallTimeout {
+    pub call: String,
+    pub after: Duration,
+}
+
+impl fmt::Display for CallTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timed out after {:?} trying to {}", self.after, self.call)
+    }
+}
+
+impl std::error::Error for CallTimeout {}
+
+/// True when `error` was caused by a `CallTimeout`.
+pub fn is_timeout(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| e.is::<CallTimeout>())
+}
+
 pub trait Element: Clone + Send + Sync + std::fmt::Debug {
     fn get_name(&self) -> Result<String>;
     fn get_control_type(&self) -> Result<String>;
@@ -15,25 +107,62 @@ pub trait Element: Clone + Send + Sync + std::fmt::Debug {
     fn set_focus(&self) -> Result<()>;
     // For debugging/logging
     fn get_clickable_point(&self) -> Result<(i32, i32)>;
+    fn get_bounds(&self) -> Result<Bounds>;
+    /// Identity of the underlying UI element (UIA runtime ID, AT-SPI bus+path).
+    /// Fails once the element is gone, even if cached properties still resolve.
+    fn runtime_id(&self) -> Result<String>;
+    /// True when both handles refer to the same UI element.
+    fn same_as(&self, other: &Self) -> bool;
+    fn is_enabled(&self) -> Result<bool>;
+    fn is_offscreen(&self) -> Result<bool>;
+    fn get_info(&self) -> Result<ElementInfo>;
     
     fn find_elements(&self, scope: Scope) -> Result<Vec<Self>>;
+    /// Like `find_elements`, but lets the backend filter on the provider side
+    /// and prefetch what a scan reads (name, control type, identity).
+    fn find_elements_filtered(&self, scope: Scope, _filter: &ElementFilter) -> Result<Vec<Self>> {
+        self.find_elements(scope)
+    }
+    /// State of a top-level window (UIA WindowPattern).
+    fn window_state(&self) -> Result<WindowState> {
+        Err(anyhow::anyhow!("Window state not supported"))
+    }
+    fn set_window_state(&self, _state: WindowState) -> Result<()> {
+        Err(anyhow::anyhow!("Window state not supported"))
+    }
+    /// State a minimized window returns to when restored: Maximized when it
+    /// was maximized before being minimized.
+    fn restored_state(&self) -> Result<WindowState> {
+        Ok(WindowState::Normal)
+    }
 }
 
+/// Siblings of an element as (previous, next), each in document order.
+pub type Siblings<E> = (Vec<E>, Vec<E>);
+
 pub trait Backend: Send + Sync {
     type Element: Element;
 
     fn new() -> Result<Self> where Self: Sized;
     fn get_root_element(&self) -> Result<Self::Element>;
     fn get_focused_element(&self) -> Result<Self::Element>;
+    /// Types `keys` into whatever has keyboard focus.
+    fn send_keys(&self, keys: &KeySequence) -> Result<()>;
+    /// Time since the user last pressed a key or moved the mouse.
+    fn idle_time(&self) -> Result<Duration>;
     fn get_all_windows(&self) -> Result<Vec<Self::Element>>;
+    /// Upper bound for each call into a target application. Calls that run
+    /// out fail with `CallTimeout`.
+    fn set_call_timeout(&mut self, _timeout: Duration) {}
     
     // Tree traversal abstractions
     fn get_parent(&self, element: &Self::Element) -> Result<Self::Element>;
-    #[allow(dead_code)]
     fn get_children(&self, element: &Self::Element) -> Result<Vec<Self::Element>>;
-    fn get_siblings(&self, element: &Self::Element) -> Result<(Vec<Self::Element>, Vec<Self::Element>)>; // (prev, next)
+    fn get_siblings(&self, element: &Self::Element) -> Result<Siblings<Self::Element>>;
 }
 
+pub mod fixture;
+
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
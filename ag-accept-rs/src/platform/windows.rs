@@ -1,76 +1,535 @@
-use super::{Backend, Element};
+use super::{
+    Backend, Bounds, CallTimeout, Element, ElementFilter, ElementInfo, Scope, Siblings,
+    WindowState, DEFAULT_CALL_TIMEOUT,
+};
+use crate::keys::{Key, KeyChord, KeySequence};
 use anyhow::{anyhow, Result};
-use uiautomation::types::TreeScope;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use uiautomation::controls::ControlType;
+use uiautomation::core::UICondition;
+use uiautomation::types::{Rect, TreeScope, UIProperty};
+use uiautomation::variants::Variant;
 use uiautomation::{UIAutomation, UIElement};
+use windows::core::ComInterface;
+use windows::Win32::UI::Accessibility::{
+    IUIAutomation, IUIAutomation2, IUIAutomationCacheRequest, IUIAutomationCondition,
+    IUIAutomationElement,
+};
 
 #[derive(Clone, Debug)]
 pub struct WindowsElement(UIElement);
 
+// SAFETY: `UIAutomation::new` joins the multithreaded apartment and
+// CUIAutomation is free-threaded, so its interface pointers may be used from
+// any thread.
+unsafe impl Send for WindowsElement {}
+unsafe impl Sync for WindowsElement {}
+
+/// Pattern availability properties cached by `get_info`, with the names
+/// reported in `ElementInfo::patterns`.
+fn pattern_properties() -> [(UIProperty, &'static str); 8] {
+    [
+        (UIProperty::IsInvokePatternAvailable, "Invoke"),
+        (UIProperty::IsTogglePatternAvailable, "Toggle"),
+        (UIProperty::IsValuePatternAvailable, "Value"),
+        (UIProperty::IsExpandCollapsePatternAvailable, "ExpandCollapse"),
+        (UIProperty::IsSelectionItemPatternAvailable, "SelectionItem"),
+        (UIProperty::IsScrollPatternAvailable, "Scroll"),
+        (UIProperty::IsTextPatternAvailable, "Text"),
+        (UIProperty::IsWindowPatternAvailable, "Window"),
+    ]
+}
+
+/// HRESULT for a provider that did not answer within the connection or
+/// transaction timeout.
+const UIA_E_TIMEOUT: u32 = 0x8013_1505;
+
+/// Applied to every `UIAutomation` instance, see `apply_timeouts`.
+static CALL_TIMEOUT_MS: AtomicU32 = AtomicU32::new(DEFAULT_CALL_TIMEOUT.as_millis() as u32);
+
+fn uia_error(error: uiautomation::Error) -> anyhow::Error {
+    if error.code() as u32 == UIA_E_TIMEOUT {
+        return CallTimeout {
+            call: format!("reach the UIA provider ({})", error),
+            after: Duration::from_millis(CALL_TIMEOUT_MS.load(Ordering::Relaxed) as u64),
+        }
+        .into();
+    }
+    anyhow!(error)
+}
+
+fn com_error(error: windows::core::Error) -> anyhow::Error {
+    uia_error(error.into())
+}
+
+/// Sets UIA's ConnectionTimeout and TransactionTimeout. Both need
+/// IUIAutomation2 (Windows 8+); older systems keep the UIA defaults.
+fn apply_timeouts(automation: &UIAutomation) {
+    let automation: &IUIAutomation = automation.as_ref();
+    let Ok(automation) = automation.cast::<IUIAutomation2>() else {
+        return;
+    };
+    let ms = CALL_TIMEOUT_MS.load(Ordering::Relaxed);
+    // SAFETY: plain COM calls on a live interface pointer
+    unsafe {
+        let _ = automation.SetConnectionTimeout(ms);
+        let _ = automation.SetTransactionTimeout(ms);
+    }
+}
+
+// The `uiautomation` crate does not wrap cache requests, so they go through
+// the COM interfaces it exposes with `AsRef`.
+fn build_request(
+    automation: &UIAutomation,
+    properties: &[UIProperty],
+) -> Result<IUIAutomationCacheRequest> {
+    let automation: &IUIAutomation = automation.as_ref();
+    // SAFETY: plain COM calls on live interface pointers
+    unsafe {
+        let request = automation.CreateCacheRequest().map_err(com_error)?;
+        for property in properties {
+            request.AddProperty((*property).into()).map_err(com_error)?;
+        }
+        Ok(request)
+    }
+}
+
+fn com(element: &UIElement) -> &IUIAutomationElement {
+    element.as_ref()
+}
+
+/// A property prefetched by a cache request; fails when it was not.
+fn cached_value(element: &UIElement, property: UIProperty) -> Result<Variant> {
+    // SAFETY: plain COM call on a live interface pointer
+    unsafe { com(element).GetCachedPropertyValue(property.into()) }
+        .map(Variant::from)
+        .map_err(com_error)
+}
+
+fn cached_name(element: &UIElement) -> Result<String> {
+    // SAFETY: plain COM call on a live interface pointer
+    unsafe { com(element).CachedName() }
+        .map(|name| name.to_string())
+        .map_err(com_error)
+}
+
+fn cached_control_type(element: &UIElement) -> Result<ControlType> {
+    // SAFETY: plain COM call on a live interface pointer
+    unsafe { com(element).CachedControlType() }
+        .map(ControlType::from)
+        .map_err(com_error)
+}
+
+/// UIA objects used by element methods. COM objects are apartment-bound, so
+/// each thread builds its own.
+struct ThreadUia {
+    automation: UIAutomation,
+    /// Everything in `ElementInfo`.
+    info_request: IUIAutomationCacheRequest,
+    /// What a scan reads from every element.
+    scan_request: IUIAutomationCacheRequest,
+}
+
+impl ThreadUia {
+    fn new() -> Result<Self> {
+        let automation = UIAutomation::new().map_err(uia_error)?;
+        apply_timeouts(&automation);
+        let mut info_properties = vec![
+            UIProperty::Name,
+            UIProperty::ControlType,
+            UIProperty::BoundingRectangle,
+            UIProperty::IsEnabled,
+            UIProperty::IsOffscreen,
+            UIProperty::AutomationId,
+            UIProperty::HelpText,
+            UIProperty::ValueValue,
+        ];
+        info_properties.extend(pattern_properties().into_iter().map(|(p, _)| p));
+        let info_request = build_request(&automation, &info_properties)?;
+        let scan_request = build_request(
+            &automation,
+            &[UIProperty::Name, UIProperty::ControlType, UIProperty::RuntimeId],
+        )?;
+        Ok(Self {
+            automation,
+            info_request,
+            scan_request,
+        })
+    }
+
+    /// Provider-side equivalent of `filter`.
+    fn condition(&self, filter: &ElementFilter) -> Result<UICondition> {
+        let a = &self.automation;
+        let mut condition = a.create_true_condition().map_err(uia_error)?;
+        if filter.named_only {
+            let unnamed = a
+                .create_property_condition(UIProperty::Name, Variant::from(""), None)
+                .map_err(uia_error)?;
+            condition = a.create_not_condition(unnamed).map_err(uia_error)?;
+        }
+        if filter.clickable_only {
+            let button = a
+                .create_property_condition(
+                    UIProperty::ControlType,
+                    Variant::from(ControlType::Button as i32),
+                    None,
+                )
+                .map_err(uia_error)?;
+            let hyperlink = a
+                .create_property_condition(
+                    UIProperty::ControlType,
+                    Variant::from(ControlType::Hyperlink as i32),
+                    None,
+                )
+                .map_err(uia_error)?;
+            let clickable = a
+                .create_or_condition(button, hyperlink)
+                .map_err(uia_error)?;
+            condition = a
+                .create_and_condition(condition, clickable)
+                .map_err(uia_error)?;
+        }
+        Ok(condition)
+    }
+}
+
+thread_local! {
+    static UIA: Option<ThreadUia> = ThreadUia::new().ok();
+}
+
+fn with_uia<T>(f: impl FnOnce(&ThreadUia) -> Result<T>) -> Result<T> {
+    UIA.with(|uia| match uia {
+        Some(uia) => f(uia),
+        None => Err(anyhow!("UI Automation unavailable on this thread")),
+    })
+}
+
+fn tree_scope(scope: Scope) -> TreeScope {
+    match scope {
+        Scope::Children => TreeScope::Children,
+        Scope::Descendants => TreeScope::Descendants,
+    }
+}
+
+fn to_bounds(rect: Rect) -> Bounds {
+    Bounds {
+        x: rect.get_left(),
+        y: rect.get_top(),
+        width: rect.get_right() - rect.get_left(),
+        height: rect.get_bottom() - rect.get_top(),
+    }
+}
+
 impl Element for WindowsElement {
+    // Elements from a cached FindAll answer from the cache without a
+    // cross-process call; others fall back to a live read.
     fn get_name(&self) -> Result<String> {
-        self.0.get_name().map_err(|e| anyhow!(e))
+        cached_name(&self.0).or_else(|_| self.0.get_name().map_err(uia_error))
     }
 
     fn get_control_type(&self) -> Result<String> {
-        self.0.get_control_type().map(|t| format!("{:?}", t)).map_err(|e| anyhow!(e))
+        cached_control_type(&self.0)
+            .or_else(|_| self.0.get_control_type().map_err(uia_error))
+            .map(|t| format!("{:?}", t))
     }
 
     fn click(&self) -> Result<()> {
-        self.0.click().map_err(|e| anyhow!(e))
+        self.0.click().map_err(uia_error)
     }
 
     fn invoke(&self) -> Result<()> {
         use uiautomation::patterns::UIInvokePattern;
         if let Ok(pattern) = self.0.get_pattern::<UIInvokePattern>() {
-            pattern.invoke().map_err(|e| anyhow!(e))
+            pattern.invoke().map_err(uia_error)
         } else {
             Err(anyhow!("Invoke pattern not supported"))
         }
     }
 
     fn set_focus(&self) -> Result<()> {
-        self.0.set_focus().map_err(|e| anyhow!(e))
+        self.0.set_focus().map_err(uia_error)
+    }
+
+    fn window_state(&self) -> Result<WindowState> {
+        use uiautomation::patterns::UIWindowPattern;
+        use uiautomation::types::WindowVisualState;
+        let pattern = self.0.get_pattern::<UIWindowPattern>().map_err(uia_error)?;
+        Ok(match pattern.get_window_visual_state().map_err(uia_error)? {
+            WindowVisualState::Minimized => WindowState::Minimized,
+            WindowVisualState::Maximized => WindowState::Maximized,
+            _ => WindowState::Normal,
+        })
+    }
+
+    fn set_window_state(&self, state: WindowState) -> Result<()> {
+        use uiautomation::patterns::UIWindowPattern;
+        use uiautomation::types::WindowVisualState;
+        let pattern = self.0.get_pattern::<UIWindowPattern>().map_err(uia_error)?;
+        pattern
+            .set_window_visual_state(match state {
+                WindowState::Normal => WindowVisualState::Normal,
+                WindowState::Minimized => WindowVisualState::Minimized,
+                WindowState::Maximized => WindowVisualState::Maximized,
+            })
+            .map_err(uia_error)
+    }
+
+    // UIA reports a minimized window as just Minimized; its placement
+    // remembers whether it was maximized before
+    fn restored_state(&self) -> Result<WindowState> {
+        use windows::Win32::Foundation::HWND;
+        use windows::Win32::UI::WindowsAndMessaging::{
+            GetWindowPlacement, WINDOWPLACEMENT, WPF_RESTORETOMAXIMIZED,
+        };
+        let hwnd: HWND = self.0.get_native_window_handle().map_err(uia_error)?.into();
+        let mut placement = WINDOWPLACEMENT {
+            length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+            ..Default::default()
+        };
+        // SAFETY: `placement` is a valid WINDOWPLACEMENT with length set
+        if !unsafe { GetWindowPlacement(hwnd, &mut placement) }.as_bool() {
+            return Err(anyhow!("GetWindowPlacement failed"));
+        }
+        Ok(if placement.flags.contains(WPF_RESTORETOMAXIMIZED) {
+            WindowState::Maximized
+        } else {
+            WindowState::Normal
+        })
     }
 
     fn get_clickable_point(&self) -> Result<(i32, i32)> {
-        self.0.get_clickable_point().map(|p| (p.x, p.y)).map_err(|e| anyhow!(e))
+        self.0
+            .get_clickable_point()
+            .map_err(uia_error)?
+            .map(|p| (p.get_x(), p.get_y()))
+            .ok_or_else(|| anyhow!("No clickable point"))
     }
 
-    fn find_elements(&self, scope: super::Scope) -> Result<Vec<Self>> {
-        let condition = self.0.create_true_condition().map_err(|e| anyhow!(e))?;
-        let tree_scope = match scope {
-            super::Scope::Children => TreeScope::Children,
-            super::Scope::Descendants => TreeScope::Descendants,
+    fn get_bounds(&self) -> Result<Bounds> {
+        let rect = self.0.get_bounding_rectangle().map_err(uia_error)?;
+        Ok(to_bounds(rect))
+    }
+
+    fn runtime_id(&self) -> Result<String> {
+        let id = self.0.get_runtime_id().map_err(uia_error)?;
+        Ok(id
+            .iter()
+            .map(|part| part.to_string())
+            .collect::<Vec<_>>()
+            .join("."))
+    }
+
+    fn same_as(&self, other: &Self) -> bool {
+        // Runtime IDs are what IUIAutomation::CompareElements compares.
+        // Identity does not need liveness, so prefetched IDs are fine here.
+        let id = |e: &UIElement| {
+            cached_value(e, UIProperty::RuntimeId)
+                .ok()
+                .and_then(|v| v.get_array().ok())
+                .and_then(|ids| TryInto::<Vec<i32>>::try_into(ids).ok())
+                .map_or_else(|| e.get_runtime_id(), Ok)
         };
-        let elements = self.0.find_all(tree_scope, &condition).map_err(|e| anyhow!(e))?;
+        match (id(&self.0), id(&other.0)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn is_enabled(&self) -> Result<bool> {
+        self.0.is_enabled().map_err(uia_error)
+    }
+
+    fn is_offscreen(&self) -> Result<bool> {
+        self.0.is_offscreen().map_err(uia_error)
+    }
+
+    fn get_info(&self) -> Result<ElementInfo> {
+        // One cross-process call for everything below
+        let cached = with_uia(|uia| {
+            // SAFETY: plain COM call on live interface pointers
+            unsafe { com(&self.0).BuildUpdatedCache(&uia.info_request) }
+                .map(UIElement::from)
+                .map_err(com_error)
+        })?;
+
+        let patterns = pattern_properties()
+            .into_iter()
+            .filter(|(property, _)| {
+                cached_value(&cached, *property)
+                    .ok()
+                    .and_then(|v| v.try_into().ok())
+                    .unwrap_or(false)
+            })
+            .map(|(_, name)| name.to_string())
+            .collect();
+        let value = cached_value(&cached, UIProperty::ValueValue)
+            .ok()
+            .and_then(|v| v.get_string().ok());
+
+        let element = com(&cached);
+        // SAFETY: plain COM calls on a live interface pointer
+        let (bounds, enabled, offscreen, automation_id, help_text) = unsafe {
+            (
+                element.CachedBoundingRectangle().ok(),
+                element.CachedIsEnabled().ok(),
+                element.CachedIsOffscreen().ok(),
+                element.CachedAutomationId().ok(),
+                element.CachedHelpText().ok(),
+            )
+        };
+        Ok(ElementInfo {
+            name: cached_name(&cached)?,
+            control_type: cached_control_type(&cached)
+                .map(|t| format!("{:?}", t))
+                .unwrap_or_default(),
+            bounds: bounds.map(|rect| to_bounds(rect.into())),
+            enabled: enabled.is_some_and(|b| b.as_bool()),
+            offscreen: offscreen.is_none_or(|b| b.as_bool()),
+            automation_id: automation_id.map(|s| s.to_string()).unwrap_or_default(),
+            help_text: help_text.map(|s| s.to_string()).unwrap_or_default(),
+            value,
+            patterns,
+        })
+    }
+
+    fn find_elements(&self, scope: Scope) -> Result<Vec<Self>> {
+        let elements = with_uia(|uia| {
+            let condition = uia.automation.create_true_condition().map_err(uia_error)?;
+            self.0.find_all(tree_scope(scope), &condition).map_err(uia_error)
+        })?;
         Ok(elements.into_iter().map(WindowsElement).collect())
     }
+
+    fn find_elements_filtered(&self, scope: Scope, filter: &ElementFilter) -> Result<Vec<Self>> {
+        // One cross-process call: filtered on the provider side, with name,
+        // control type and runtime ID prefetched for every result
+        with_uia(|uia| {
+            let condition = uia.condition(filter)?;
+            let condition: &IUIAutomationCondition = condition.as_ref();
+            // SAFETY: plain COM calls on live interface pointers
+            unsafe {
+                let found = com(&self.0)
+                    .FindAllBuildCache(tree_scope(scope).into(), condition, &uia.scan_request)
+                    .map_err(com_error)?;
+                (0..found.Length().map_err(com_error)?)
+                    .map(|i| {
+                        found
+                            .GetElement(i)
+                            .map(|e| WindowsElement(e.into()))
+                            .map_err(com_error)
+                    })
+                    .collect()
+            }
+        })
+    }
+}
+
+/// `chord` in the `Keyboard::send_keys` syntax (SendInput): held modifiers
+/// such as `{alt}` followed by the key in parentheses.
+fn uia_keys(chord: &KeyChord) -> String {
+    let key = match chord.key {
+        Key::Enter => "{enter}".to_string(),
+        Key::Tab => "{tab}".to_string(),
+        Key::Escape => "{esc}".to_string(),
+        Key::Space => " ".to_string(),
+        Key::Backspace => "{back}".to_string(),
+        Key::Delete => "{del}".to_string(),
+        Key::Up => "{up}".to_string(),
+        Key::Down => "{down}".to_string(),
+        Key::Left => "{left}".to_string(),
+        Key::Right => "{right}".to_string(),
+        Key::Home => "{home}".to_string(),
+        Key::End => "{end}".to_string(),
+        Key::PageUp => "{pgup}".to_string(),
+        Key::PageDown => "{pgdn}".to_string(),
+        Key::F(n) => format!("{{F{}}}", n),
+        // Braces and parentheses are syntax and must be escaped
+        Key::Char(c) if "{}()".contains(c) => format!("{{{}}}", c),
+        Key::Char(c) => c.to_string(),
+    };
+    let held: String = [
+        (chord.modifiers.ctrl, "{ctrl}"),
+        (chord.modifiers.alt, "{alt}"),
+        (chord.modifiers.shift, "{shift}"),
+        (chord.modifiers.meta, "{win}"),
+    ]
+    .into_iter()
+    .filter(|(down, _)| *down)
+    .map(|(_, name)| name)
+    .collect();
+    if held.is_empty() {
+        key
+    } else {
+        format!("{}({})", held, key)
+    }
 }
 
 pub struct WindowsBackend {
     automation: UIAutomation,
 }
 
+// SAFETY: see `WindowsElement`
+unsafe impl Send for WindowsBackend {}
+unsafe impl Sync for WindowsBackend {}
+
 impl Backend for WindowsBackend {
     type Element = WindowsElement;
 
     fn new() -> Result<Self> {
-        Ok(Self {
-            automation: UIAutomation::new().map_err(|e| anyhow!(e))?,
-        })
+        let automation = UIAutomation::new().map_err(uia_error)?;
+        apply_timeouts(&automation);
+        Ok(Self { automation })
+    }
+
+    fn set_call_timeout(&mut self, timeout: Duration) {
+        CALL_TIMEOUT_MS.store(timeout.as_millis() as u32, Ordering::Relaxed);
+        apply_timeouts(&self.automation);
+        // The element methods' own instance on this thread
+        UIA.with(|uia| {
+            if let Some(uia) = uia {
+                apply_timeouts(&uia.automation);
+            }
+        });
     }
 
     fn get_root_element(&self) -> Result<Self::Element> {
-        self.automation.get_root_element().map(WindowsElement).map_err(|e| anyhow!(e))
+        self.automation.get_root_element().map(WindowsElement).map_err(uia_error)
     }
 
     fn get_focused_element(&self) -> Result<Self::Element> {
-        self.automation.get_focused_element().map(WindowsElement).map_err(|e| anyhow!(e))
+        self.automation.get_focused_element().map(WindowsElement).map_err(uia_error)
+    }
+
+    fn idle_time(&self) -> Result<Duration> {
+        use windows::Win32::System::SystemInformation::GetTickCount;
+        use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        // SAFETY: `info` is a valid LASTINPUTINFO with cbSize set
+        if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+            return Err(anyhow!("GetLastInputInfo failed"));
+        }
+        // Both are tick counts that wrap every 49.7 days
+        // SAFETY: GetTickCount takes no arguments and has no preconditions
+        let now = unsafe { GetTickCount() };
+        Ok(Duration::from_millis(u64::from(now.wrapping_sub(info.dwTime))))
+    }
+
+    fn send_keys(&self, keys: &KeySequence) -> Result<()> {
+        use uiautomation::inputs::Keyboard;
+        let keyboard = Keyboard::new();
+        for chord in &keys.chords {
+            keyboard.send_keys(&uia_keys(chord)).map_err(uia_error)?;
+        }
+        Ok(())
     }
 
     fn get_all_windows(&self) -> Result<Vec<Self::Element>> {
-        let root = self.automation.get_root_element().map_err(|e| anyhow!(e))?;
-        let walker = self.automation.create_tree_walker().map_err(|e| anyhow!(e))?;
+        let root = self.automation.get_root_element().map_err(uia_error)?;
+        let walker = self.automation.create_tree_walker().map_err(uia_error)?;
         let mut windows = Vec::new();
 
         let mut result = walker.get_first_child(&root);
@@ -82,20 +541,20 @@ impl Backend for WindowsBackend {
     }
 
     fn get_parent(&self, element: &Self::Element) -> Result<Self::Element> {
-        let walker = self.automation.create_tree_walker().map_err(|e| anyhow!(e))?;
-        walker.get_parent(&element.0).map(WindowsElement).map_err(|e| anyhow!(e))
+        let walker = self.automation.create_tree_walker().map_err(uia_error)?;
+        walker.get_parent(&element.0).map(WindowsElement).map_err(uia_error)
     }
 
     fn get_children(&self, element: &Self::Element) -> Result<Vec<Self::Element>> {
         // Optimization: Use FindAll for descendants if needed, but for direct children walker is safer?
         // Actually, FindAll with TreeScope::Children is better.
-        let condition = self.automation.create_true_condition().map_err(|e| anyhow!(e))?;
-        let children = element.0.find_all(TreeScope::Children, &condition).map_err(|e| anyhow!(e))?;
+        let condition = self.automation.create_true_condition().map_err(uia_error)?;
+        let children = element.0.find_all(TreeScope::Children, &condition).map_err(uia_error)?;
         Ok(children.into_iter().map(WindowsElement).collect())
     }
 
-    fn get_siblings(&self, element: &Self::Element) -> Result<(Vec<Self::Element>, Vec<Self::Element>)> {
-        let walker = self.automation.create_tree_walker().map_err(|e| anyhow!(e))?;
+    fn get_siblings(&self, element: &Self::Element) -> Result<Siblings<Self::Element>> {
+        let walker = self.automation.create_tree_walker().map_err(uia_error)?;
         
         let mut prev_list = Vec::new();
         let mut curr = element.0.clone();
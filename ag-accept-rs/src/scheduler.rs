@@ -0,0 +1,217 @@
+use crate::config::AppConfig;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A window with a prompt seen this recently is scanned at the fast rate.
+const HOT_FOR: Duration = Duration::from_secs(30);
+/// A window without prompts for this long is scanned at the slow rate.
+const IDLE_AFTER: Duration = Duration::from_secs(120);
+const HOT_FACTOR: f64 = 0.5;
+const IDLE_FACTOR: f64 = 4.0;
+/// A window's scans may take at most 1/COST_FACTOR of the time.
+const COST_FACTOR: f64 = 5.0;
+/// Weight of the newest scan in the smoothed cost.
+const COST_SMOOTHING: f64 = 0.3;
+
+/// Why a window is scanned at its current rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScheduleReason {
+    Focused,
+    Hot,
+    Normal,
+    Idle,
+    Slow,
+}
+
+impl ScheduleReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScheduleReason::Focused => "focused",
+            ScheduleReason::Hot => "hot",
+            ScheduleReason::Normal => "normal",
+            ScheduleReason::Idle => "idle",
+            ScheduleReason::Slow => "slow",
+        }
+    }
+}
+
+/// Schedule of one window as shown in the TUI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScheduleInfo {
+    pub interval: Duration,
+    /// Time until the next full scan; zero when due.
+    pub next_in: Duration,
+    pub reason: ScheduleReason,
+    /// Smoothed scan cost.
+    pub cost_ms: f64,
+}
+
+struct WindowSchedule {
+    cost_ms: f64,
+    first_seen: Instant,
+    last_prompt: Option<Instant>,
+    focused: bool,
+    last_scan: Option<Instant>,
+}
+
+/// Decides which target windows get a full scan on each cycle.
+///
+/// Focused windows and windows that recently showed a prompt are scanned
+/// more often than `interval`, idle ones less; expensive windows are slowed
+/// down so their scans stay a small share of the time.
+pub struct Scheduler {
+    base: f64,
+    min: f64,
+    max: f64,
+    windows: HashMap<String, WindowSchedule>,
+}
+
+impl Scheduler {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            base: config.interval.max(0.0),
+            min: config.min_interval_secs.max(0.0),
+            max: config.max_interval_secs.max(0.0),
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Registers the current target windows and their focus, forgetting
+    /// windows that are gone.
+    pub fn update_targets<'a>(
+        &mut self,
+        targets: impl IntoIterator<Item = (&'a str, bool)>,
+        now: Instant,
+    ) {
+        let mut seen = Vec::new();
+        for (title, focused) in targets {
+            let window = self
+                .windows
+                .entry(title.to_string())
+                .or_insert_with(|| WindowSchedule {
+                    cost_ms: 0.0,
+                    first_seen: now,
+                    last_prompt: None,
+                    focused,
+                    last_scan: None,
+                });
+            window.focused = focused;
+            seen.push(title);
+        }
+        self.windows
+            .retain(|title, _| seen.contains(&title.as_str()));
+    }
+
+    pub fn record_scan(&mut self, title: &str, cost: Duration, prompt_seen: bool, now: Instant) {
+        let Some(window) = self.windows.get_mut(title) else {
+            return;
+        };
+        let cost_ms = cost.as_secs_f64() * 1000.0;
+        window.cost_ms = match window.last_scan {
+            Some(_) => window.cost_ms * (1.0 - COST_SMOOTHING) + cost_ms * COST_SMOOTHING,
+            None => cost_ms,
+        };
+        window.last_scan = Some(now);
+        if prompt_seen {
+            window.last_prompt = Some(now);
+        }
+    }
+
+    pub fn is_due(&self, title: &str, now: Instant) -> bool {
+        self.info(title, now)
+            .is_none_or(|info| info.next_in.is_zero())
+    }
+
+    /// When the next window becomes due, if any is known.
+    pub fn next_due(&self, now: Instant) -> Option<Instant> {
+        self.windows
+            .keys()
+            .filter_map(|title| self.info(title, now))
+            .map(|info| now + info.next_in)
+            .min()
+    }
+
+    pub fn info(&self, title: &str, now: Instant) -> Option<ScheduleInfo> {
+        let window = self.windows.get(title)?;
+        let (factor, mut reason) = if window.focused {
+            (HOT_FACTOR, ScheduleReason::Focused)
+        } else if window
+            .last_prompt
+            .is_some_and(|at| now.duration_since(at) < HOT_FOR)
+        {
+            (HOT_FACTOR, ScheduleReason::Hot)
+        } else if now.duration_since(window.last_prompt.unwrap_or(window.first_seen)) >= IDLE_AFTER
+        {
+            (IDLE_FACTOR, ScheduleReason::Idle)
+        } else {
+            (1.0, ScheduleReason::Normal)
+        };
+
+        let mut secs = self.base * factor;
+        let cost_secs = window.cost_ms / 1000.0 * COST_FACTOR;
+        if cost_secs > secs {
+            secs = cost_secs;
+            reason = ScheduleReason::Slow;
+        }
+        let interval = Duration::from_secs_f64(secs.min(self.max).max(self.min));
+        let next_in = window
+            .last_scan
+            .map(|at| (at + interval).saturating_duration_since(now))
+            .unwrap_or_default();
+        Some(ScheduleInfo {
+            interval,
+            next_in,
+            reason,
+            cost_ms: window.cost_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler() -> Scheduler {
+        Scheduler::new(&AppConfig {
+            interval: 1.0,
+            min_interval_secs: 0.25,
+            max_interval_secs: 10.0,
+            ..AppConfig::default()
+        })
+    }
+
+    #[test]
+    fn slow_window_does_not_delay_fast_ones() {
+        let mut scheduler = scheduler();
+        let start = Instant::now();
+        scheduler.update_targets([("Slow", false), ("Fast", false)], start);
+        scheduler.record_scan("Slow", Duration::from_millis(1200), false, start);
+        scheduler.record_scan("Fast", Duration::from_millis(20), false, start);
+
+        let later = start + Duration::from_millis(1500);
+        assert!(scheduler.is_due("Fast", later));
+        assert!(!scheduler.is_due("Slow", later));
+        let slow = scheduler.info("Slow", later).unwrap();
+        assert_eq!(slow.reason, ScheduleReason::Slow);
+        assert_eq!(slow.interval, Duration::from_secs(6));
+    }
+
+    #[test]
+    fn focused_and_hot_windows_are_scanned_more_often_than_idle_ones() {
+        let mut scheduler = scheduler();
+        let start = Instant::now();
+        scheduler.update_targets([("Focused", true), ("Hot", false), ("Idle", false)], start);
+        for title in ["Focused", "Hot", "Idle"] {
+            scheduler.record_scan(title, Duration::ZERO, title == "Hot", start);
+        }
+
+        let later = start + Duration::from_secs(150);
+        scheduler.record_scan("Hot", Duration::ZERO, true, later);
+        let interval = |title| scheduler.info(title, later).unwrap();
+        assert_eq!(interval("Focused").reason, ScheduleReason::Focused);
+        assert_eq!(interval("Hot").reason, ScheduleReason::Hot);
+        assert_eq!(interval("Idle").reason, ScheduleReason::Idle);
+        assert_eq!(interval("Hot").interval, Duration::from_millis(500));
+        assert_eq!(interval("Idle").interval, Duration::from_secs(4));
+    }
+}
@@ -0,0 +1,406 @@
+use crate::platform::ElementInfo;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Element property a selector condition reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attribute {
+    Name,
+    Type,
+    /// UIA AutomationId, AT-SPI accessible ID.
+    Id,
+    HelpText,
+    Value,
+}
+
+impl Attribute {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(Attribute::Name),
+            "type" => Some(Attribute::Type),
+            "id" | "automation_id" => Some(Attribute::Id),
+            "help" | "help_text" => Some(Attribute::HelpText),
+            "value" => Some(Attribute::Value),
+            _ => None,
+        }
+    }
+
+    fn read<'a>(&self, info: &'a ElementInfo) -> Option<&'a str> {
+        match self {
+            Attribute::Name => Some(&info.name),
+            Attribute::Type => Some(&info.control_type),
+            Attribute::Id => Some(&info.automation_id),
+            Attribute::HelpText => Some(&info.help_text),
+            Attribute::Value => info.value.as_deref(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    /// `=`
+    Equals,
+    /// `~=`
+    Contains,
+    /// `^=`
+    StartsWith,
+    /// `$=`
+    EndsWith,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub attribute: Attribute,
+    pub operator: Operator,
+    pub value: String,
+}
+
+impl Condition {
+    fn matches(&self, info: &ElementInfo) -> bool {
+        let Some(actual) = self.attribute.read(info) else {
+            return false;
+        };
+        match self.operator {
+            Operator::Equals => actual == self.value,
+            Operator::Contains => actual.contains(self.value.as_str()),
+            Operator::StartsWith => actual.starts_with(self.value.as_str()),
+            Operator::EndsWith => actual.ends_with(self.value.as_str()),
+        }
+    }
+}
+
+/// One `Type[attr="value"]...` part of a selector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// Exact control type; `None` for `*` or a bare `[...]`.
+    pub control_type: Option<String>,
+    pub conditions: Vec<Condition>,
+}
+
+impl Step {
+    pub fn matches(&self, info: &ElementInfo) -> bool {
+        self.control_type
+            .as_ref()
+            .is_none_or(|t| *t == info.control_type)
+            && self.conditions.iter().all(|c| c.matches(info))
+    }
+
+    /// True when matching needs more than name and control type.
+    pub fn needs_info(&self) -> bool {
+        self.conditions
+            .iter()
+            .any(|c| !matches!(c.attribute, Attribute::Name | Attribute::Type))
+    }
+}
+
+/// How a step relates to the one before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Combinator {
+    /// `>`: direct child.
+    Child,
+    /// `>>` or whitespace: any descendant.
+    Descendant,
+}
+
+/// A structural path over the accessibility tree, e.g.
+/// `Window > Pane[name~="Run command"] >> Button[name="Accept"]`.
+///
+/// The last step selects elements; the steps before it constrain their
+/// ancestors, up to and including the root the selector is evaluated on.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Selector {
+    source: String,
+    steps: Vec<Step>,
+    /// `combinators[i]` joins `steps[i]` and `steps[i + 1]`.
+    combinators: Vec<Combinator>,
+}
+
+impl Selector {
+    pub fn last_step(&self) -> &Step {
+        &self.steps[self.steps.len() - 1]
+    }
+
+    pub fn needs_info(&self) -> bool {
+        self.steps.iter().any(Step::needs_info)
+    }
+
+    /// True when `element` and its `ancestors` (parent first, ending with the
+    /// root) satisfy every step. `step_matches` tests one node against one
+    /// step.
+    pub fn matches_chain<T>(
+        &self,
+        element: &T,
+        ancestors: &[T],
+        step_matches: impl Fn(&Step, &T) -> bool,
+    ) -> bool {
+        let last = self.steps.len() - 1;
+        if !step_matches(&self.steps[last], element) {
+            return false;
+        }
+        let node = |i: usize| if i == 0 { element } else { &ancestors[i - 1] };
+        self.match_from(last, 0, ancestors.len() + 1, &node, &step_matches)
+    }
+
+    /// `step` is matched by chain node `at`; checks the steps before it
+    /// against the nodes above.
+    fn match_from<'a, T: 'a>(
+        &self,
+        step: usize,
+        at: usize,
+        len: usize,
+        node: &impl Fn(usize) -> &'a T,
+        step_matches: &impl Fn(&Step, &T) -> bool,
+    ) -> bool {
+        if step == 0 {
+            return true;
+        }
+        let above = match self.combinators[step - 1] {
+            Combinator::Child => (at + 1)..(at + 2).min(len),
+            Combinator::Descendant => (at + 1)..len,
+        };
+        above.into_iter().any(|i| {
+            step_matches(&self.steps[step - 1], node(i))
+                && self.match_from(step - 1, i, len, node, step_matches)
+        })
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl From<Selector> for String {
+    fn from(selector: Selector) -> Self {
+        selector.source
+    }
+}
+
+impl TryFrom<String> for Selector {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Self> {
+        source.parse()
+    }
+}
+
+impl FromStr for Selector {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        Parser {
+            source,
+            chars: source.char_indices().collect(),
+            pos: 0,
+        }
+        .parse()
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<(usize, char)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).map(|(_, c)| *c)
+    }
+
+    fn error(&self, expected: &str) -> anyhow::Error {
+        let column = self
+            .chars
+            .get(self.pos)
+            .map_or(self.source.len(), |(i, _)| *i);
+        anyhow!(
+            "Invalid selector '{}': expected {} at column {}",
+            self.source,
+            expected,
+            column + 1
+        )
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse(mut self) -> Result<Selector> {
+        let mut steps = Vec::new();
+        let mut combinators = Vec::new();
+        self.skip_whitespace();
+        loop {
+            steps.push(self.step()?);
+            let spaced = self.skip_whitespace();
+            let combinator = if self.eat('>') {
+                if self.eat('>') {
+                    Combinator::Descendant
+                } else {
+                    Combinator::Child
+                }
+            } else if self.peek().is_none() {
+                break;
+            } else if spaced {
+                Combinator::Descendant
+            } else {
+                return Err(self.error("'>', '>>' or whitespace"));
+            };
+            self.skip_whitespace();
+            combinators.push(combinator);
+        }
+        Ok(Selector {
+            source: self.source.trim().to_string(),
+            steps,
+            combinators,
+        })
+    }
+
+    fn identifier(&mut self) -> String {
+        let mut ident = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_alphanumeric() || *c == '_') {
+            ident.push(c);
+            self.pos += 1;
+        }
+        ident
+    }
+
+    fn step(&mut self) -> Result<Step> {
+        let control_type = if self.eat('*') {
+            None
+        } else {
+            let ident = self.identifier();
+            if ident.is_empty() && self.peek() != Some('[') {
+                return Err(self.error("a control type, '*' or '['"));
+            }
+            (!ident.is_empty()).then_some(ident)
+        };
+
+        let mut conditions = Vec::new();
+        while self.eat('[') {
+            self.skip_whitespace();
+            let name = self.identifier();
+            let attribute = Attribute::parse(&name)
+                .ok_or_else(|| self.error("name, type, id, help or value"))?;
+            self.skip_whitespace();
+            let operator = if self.eat('=') {
+                Operator::Equals
+            } else {
+                let operator = match self.peek() {
+                    Some('~') => Operator::Contains,
+                    Some('^') => Operator::StartsWith,
+                    Some('$') => Operator::EndsWith,
+                    _ => return Err(self.error("'=', '~=', '^=' or '$='")),
+                };
+                self.pos += 1;
+                if !self.eat('=') {
+                    return Err(self.error("'='"));
+                }
+                operator
+            };
+            self.skip_whitespace();
+            let value = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(']') {
+                return Err(self.error("']'"));
+            }
+            conditions.push(Condition {
+                attribute,
+                operator,
+                value,
+            });
+        }
+        Ok(Step {
+            control_type,
+            conditions,
+        })
+    }
+
+    /// A single- or double-quoted string; `\` escapes the next character.
+    fn string(&mut self) -> Result<String> {
+        let quote = match self.peek() {
+            Some(q @ ('"' | '\'')) => q,
+            _ => return Err(self.error("a quoted value")),
+        };
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("closing quote")),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    let escaped = self.peek().ok_or_else(|| self.error("escaped character"))?;
+                    value.push(escaped);
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(control_type: &str, name: &str) -> ElementInfo {
+        ElementInfo {
+            name: name.to_string(),
+            control_type: control_type.to_string(),
+            ..ElementInfo::default()
+        }
+    }
+
+    #[test]
+    fn parses_and_matches_structural_paths() {
+        let selector: Selector = r#"Window > Pane[name~="Run"] >> Button[name="Accept"]"#
+            .parse()
+            .unwrap();
+        let button = info("Button", "Accept");
+        let group = info("Group", "");
+        let pane = info("Pane", "Run command?");
+        let window = info("Window", "Agent Manager");
+        let step_matches = |step: &Step, node: &ElementInfo| step.matches(node);
+
+        assert!(selector.matches_chain(
+            &button,
+            &[group.clone(), pane.clone(), window.clone()],
+            step_matches
+        ));
+        // `>` needs the pane directly under the window
+        assert!(!selector.matches_chain(
+            &button,
+            &[pane.clone(), group.clone(), window.clone()],
+            step_matches
+        ));
+        assert!(!selector.matches_chain(&info("Button", "Reject"), &[pane, window], step_matches));
+
+        let error = "Button[name=Accept]".parse::<Selector>().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("expected a quoted value at column 13"));
+    }
+}
@@ -1,18 +1,108 @@
-use crate::platform::{Backend, PlatformBackend, PlatformElement, Scope};
+use crate::config::{Rule, Traversal};
+use crate::platform::{
+    Backend, ElementFilter, ElementInfo, PlatformBackend, PlatformElement, Scope,
+};
+use crate::selector::{Selector, Step};
+use crate::text::{self, Needles};
 use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-pub struct QueryService {
-    backend: PlatformBackend,
+/// A rule satisfied by a scan, with the button to act on.
+pub struct ScanMatch<E = PlatformElement> {
+    pub rule: Rule,
+    pub button: E,
+    /// Name of the element that satisfied the rule's context, if it has one.
+    pub context: Option<String>,
+    /// Where that context sits relative to the button.
+    pub relation: Option<ContextRelation>,
 }
 
-impl QueryService {
+/// How a matched context relates to its button in the tree and on screen.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContextRelation {
+    /// Hops from the button up to the closest common ancestor and down to
+    /// the context; `None` when none was found below the scan root.
+    pub hops: Option<usize>,
+    /// Control type of that ancestor.
+    pub common_ancestor: Option<String>,
+    /// Gap between the bounding boxes in pixels, when both are known.
+    pub distance: Option<f64>,
+}
+
+impl fmt::Display for ContextRelation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.hops, &self.common_ancestor) {
+            (Some(hops), Some(ancestor)) => write!(f, "{} hops via {}", hops, ancestor)?,
+            (Some(hops), None) => write!(f, "{} hops", hops)?,
+            (None, _) => f.write_str("no common ancestor")?,
+        }
+        match self.distance {
+            Some(distance) => write!(f, ", {:.0}px apart", distance),
+            None => f.write_str(", bounds unknown"),
+        }
+    }
+}
+
+/// Where one scan spent its time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    /// Elements returned by the provider query.
+    pub elements: usize,
+    /// Nodes visited by bounded walks, or returned by whole-subtree queries.
+    pub visited: usize,
+    /// Time in the provider query (FindAll).
+    pub fetch_ms: u64,
+    /// Time reading properties and matching rules.
+    pub match_ms: u64,
+}
+
+/// Depth guard when walking up from a selector candidate, a context or an
+/// anchored button.
+const MAX_SELECTOR_DEPTH: usize = 64;
+
+/// Where an anchored rule last matched: child indices (with the expected
+/// control type) from the scan root down to the button's parent.
+type AnchorPath = Vec<(usize, String)>;
+
+pub struct QueryService<B: Backend = PlatformBackend> {
+    backend: B,
+    batched: bool,
+    /// Keyed by scan root identity and rule name.
+    anchors: Mutex<HashMap<(String, String), AnchorPath>>,
+}
+
+impl<B: Backend> QueryService<B> {
     pub fn new() -> Result<Self> {
         Ok(Self {
-            backend: PlatformBackend::new()?,
+            backend: B::new()?,
+            batched: true,
+            anchors: Mutex::new(HashMap::new()),
         })
     }
 
-    fn get_element_repr(&self, element: &PlatformElement) -> String {
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            batched: true,
+            anchors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Switches between the filtered, prefetching query and the original
+    /// unfiltered one with per-element property reads.
+    pub fn set_batched(&mut self, batched: bool) {
+        self.batched = batched;
+    }
+
+    pub fn set_call_timeout(&mut self, timeout: Duration) {
+        self.backend.set_call_timeout(timeout);
+    }
+
+    fn get_element_repr(&self, element: &B::Element) -> String {
         use crate::platform::Element;
         let name = element.get_name().unwrap_or_default();
         if !name.trim().is_empty() {
@@ -25,14 +115,33 @@ impl QueryService {
         "<Unknown>".to_string()
     }
 
-    pub fn inspect_siblings(&self, element: &PlatformElement) -> Result<Vec<String>> {
+    /// Text shown with `button` in its prompt, such as the command a "Run
+    /// command?" prompt asks about: the names of its siblings other than
+    /// buttons and links, one per line.
+    pub fn prompt_text(&self, button: &B::Element) -> Result<String> {
+        use crate::platform::Element;
+        let (before, after) = self.backend.get_siblings(button)?;
+        let lines: Vec<String> = before
+            .iter()
+            .chain(&after)
+            .filter(|e| {
+                e.get_control_type()
+                    .is_ok_and(|t| !t.contains("Button") && !t.contains("Hyperlink"))
+            })
+            .filter_map(|e| e.get_name().ok())
+            .filter(|name| !name.trim().is_empty())
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    pub fn inspect_siblings(&self, element: &B::Element) -> Result<Vec<String>> {
         let mut siblings = Vec::new();
         let (prev_list, next_list) = self.backend.get_siblings(element)?;
 
         for prev in prev_list {
             siblings.push(format!("Prev: {}", self.get_element_repr(&prev)));
         }
-        
+
         // Current
         siblings.push(format!("*MATCH*: {}", self.get_element_repr(element)));
 
@@ -43,59 +152,567 @@ impl QueryService {
         Ok(siblings)
     }
 
-    pub fn scan_for_context_and_button(
+    /// The first prompt below `start`, by rule priority and then config
+    /// order, whose context (if any) and button are both present. Selectors
+    /// and proximity are still evaluated up to `window`.
+    pub fn scan_within(
         &self,
-        root: &PlatformElement,
-        context_text: &[String],
-        button_text: &[String],
-    ) -> Result<(bool, Option<PlatformElement>)> {
+        window: &B::Element,
+        start: &B::Element,
+        rules: &[Rule],
+    ) -> Result<Option<ScanMatch<B::Element>>> {
+        self.first_match(window, start, rules)
+            .map(|(found, _)| found)
+    }
+
+    /// Tries one rule at a time, in order. An anchored rule first tries the
+    /// container it last matched in; rules with the same traversal share one
+    /// walk of `start`.
+    fn first_match(
+        &self,
+        window: &B::Element,
+        start: &B::Element,
+        rules: &[Rule],
+    ) -> Result<(Option<ScanMatch<B::Element>>, ScanStats)> {
+        let mut stats = ScanStats::default();
+        let mut walks: Vec<(&Traversal, Vec<B::Element>)> = Vec::new();
+        for rule in Self::by_priority(rules) {
+            let anchored = self.scan_anchor(window, start, rule, &mut stats);
+            if let Some(found) = anchored.into_iter().next() {
+                return Ok((Some(found), stats));
+            }
+
+            let walk = match walks.iter().position(|(t, _)| t.same_walk(&rule.traversal)) {
+                Some(walk) => walk,
+                None => {
+                    let elements = self.fetch(start, rules, &rule.traversal, &mut stats)?;
+                    walks.push((&rule.traversal, elements));
+                    walks.len() - 1
+                }
+            };
+            let match_start = Instant::now();
+            let found = self.match_rules(&walks[walk].1, std::slice::from_ref(rule), window, false);
+            stats.match_ms += match_start.elapsed().as_millis() as u64;
+            if let Some((_, found)) = found.into_iter().next() {
+                if rule.traversal.anchor {
+                    self.remember_anchor(start, &found);
+                }
+                return Ok((Some(found), stats));
+            }
+        }
+        Ok((None, stats))
+    }
+
+    /// Every prompt under `root`, highest rule priority first, then top to
+    /// bottom and left to right. A button matched by several rules counts
+    /// for the first of them.
+    pub fn scan_all_with_stats(
+        &self,
+        root: &B::Element,
+        rules: &[Rule],
+    ) -> Result<(Vec<ScanMatch<B::Element>>, ScanStats)> {
+        self.scan_all_within(root, root, rules)
+    }
+
+    /// Like `scan_all_with_stats`, but only below `start`. Selectors and
+    /// proximity are still evaluated up to `window`. Anchors do not shorten
+    /// this scan: a rule's prompts outside its last container still count.
+    pub fn scan_all_within(
+        &self,
+        window: &B::Element,
+        start: &B::Element,
+        rules: &[Rule],
+    ) -> Result<(Vec<ScanMatch<B::Element>>, ScanStats)> {
+        use crate::platform::Element;
+        let mut stats = ScanStats::default();
+        let rules = Self::by_priority(rules);
+        // Indexed by position in `rules`
+        let mut found: Vec<(usize, ScanMatch<B::Element>)> = Vec::new();
+        let mut pending: Vec<usize> = (0..rules.len()).collect();
+
+        // Rules with the same traversal share one walk
+        while let Some(&first) = pending.first() {
+            let traversal = &rules[first].traversal;
+            let (group, rest): (Vec<usize>, Vec<usize>) = pending
+                .iter()
+                .partition(|&&i| rules[i].traversal.same_walk(traversal));
+            pending = rest;
+            let group_rules: Vec<Rule> = group.iter().map(|&i| rules[i].clone()).collect();
+            let walked =
+                self.scan_walk(start, window, &group_rules, traversal, true, &mut stats)?;
+            for (g, m) in walked {
+                let i = group[g];
+                if rules[i].traversal.anchor && !found.iter().any(|(j, _)| *j == i) {
+                    self.remember_anchor(start, &m);
+                }
+                found.push((i, m));
+            }
+        }
+
+        found.sort_by_key(|(i, _)| *i);
+        let mut matches: Vec<ScanMatch<B::Element>> = Vec::new();
+        for (_, m) in found {
+            if !matches.iter().any(|other| other.button.same_as(&m.button)) {
+                matches.push(m);
+            }
+        }
+        matches.sort_by_cached_key(|m| {
+            let position = m
+                .button
+                .get_bounds()
+                .ok()
+                .filter(|b| !b.is_empty())
+                .map_or((i32::MAX, i32::MAX), |b| (b.y, b.x));
+            (Reverse(m.rule.priority), position)
+        });
+        Ok((matches, stats))
+    }
+
+    /// Rules by descending priority, keeping config order among equals.
+    fn by_priority(rules: &[Rule]) -> Vec<&Rule> {
+        let mut ordered: Vec<&Rule> = rules.iter().collect();
+        ordered.sort_by_key(|r| Reverse(r.priority));
+        ordered
+    }
+
+    /// The first match of an anchored `rule` in the container it last
+    /// matched in, if that still exists. Empty for rules without an anchor.
+    fn scan_anchor(
+        &self,
+        window: &B::Element,
+        start: &B::Element,
+        rule: &Rule,
+        stats: &mut ScanStats,
+    ) -> Vec<ScanMatch<B::Element>> {
+        if !rule.traversal.anchor {
+            return Vec::new();
+        }
+        let Some(container) = self.resolve_anchor(start, rule) else {
+            return Vec::new();
+        };
+        let rules = std::slice::from_ref(rule);
+        // A vanished container just falls back to the full scan
+        self.scan_walk(&container, window, rules, &rule.traversal, false, stats)
+            .map(|found| found.into_iter().map(|(_, m)| m).collect())
+            .unwrap_or_default()
+    }
+
+    /// Fetches the elements `traversal` allows under `root` and matches
+    /// `rules` against them, adding to `stats`. Selectors are evaluated up
+    /// to `window`. Matches carry the index of their rule.
+    fn scan_walk(
+        &self,
+        root: &B::Element,
+        window: &B::Element,
+        rules: &[Rule],
+        traversal: &Traversal,
+        all: bool,
+        stats: &mut ScanStats,
+    ) -> Result<Vec<(usize, ScanMatch<B::Element>)>> {
+        let elements = self.fetch(root, rules, traversal, stats)?;
+        let match_start = Instant::now();
+        let found = self.match_rules(&elements, rules, window, all);
+        stats.match_ms += match_start.elapsed().as_millis() as u64;
+        Ok(found)
+    }
+
+    /// The elements `traversal` allows under `root`, for those of `rules`
+    /// walking that way, adding to `stats`.
+    fn fetch(
+        &self,
+        root: &B::Element,
+        rules: &[Rule],
+        traversal: &Traversal,
+        stats: &mut ScanStats,
+    ) -> Result<Vec<B::Element>> {
+        let fetch_start = Instant::now();
+        let elements = if traversal.is_unbounded() {
+            let rules: Vec<&Rule> = rules
+                .iter()
+                .filter(|r| r.traversal.same_walk(traversal))
+                .collect();
+            let elements = self.fetch_all(root, &rules)?;
+            stats.visited += elements.len();
+            elements
+        } else {
+            let (elements, visited) = self.walk(root, traversal)?;
+            stats.visited += visited;
+            elements
+        };
+        stats.elements += elements.len();
+        stats.fetch_ms += fetch_start.elapsed().as_millis() as u64;
+        Ok(elements)
+    }
+
+    /// Every descendant of `root` in one query.
+    fn fetch_all(&self, root: &B::Element, rules: &[&Rule]) -> Result<Vec<B::Element>> {
         use crate::platform::Element;
         // Bulk Optimization: Get ALL descendants in one COM call (on Windows)
-        let elements = root.find_elements(Scope::Descendants)?;
+        if self.batched {
+            // Contexts can be any control type, so only button-only default
+            // rules allow restricting the query to clickable elements
+            let filter = ElementFilter {
+                named_only: true,
+                clickable_only: rules.iter().all(|r| {
+                    !r.needs_context() && r.control_types.is_empty() && r.button_selector.is_none()
+                }),
+            };
+            root.find_elements_filtered(Scope::Descendants, &filter)
+        } else {
+            root.find_elements(Scope::Descendants)
+        }
+    }
+
+    /// Breadth-first walk below `root`, one children query per node, up to
+    /// `max_depth` levels and without entering pruned control types.
+    /// Returns the elements and the number of nodes visited.
+    fn walk(&self, root: &B::Element, traversal: &Traversal) -> Result<(Vec<B::Element>, usize)> {
+        use crate::platform::Element;
+        let max_depth = traversal.max_depth.unwrap_or(usize::MAX);
+        let mut elements = Vec::new();
+        if max_depth == 0 {
+            return Ok((elements, 0));
+        }
+        // Only a failing root fails the walk (e.g. a hung window)
+        let mut level = vec![self.backend.get_children(root)?];
+        let mut depth = 1;
+
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            for children in level {
+                for child in children {
+                    let pruned = !traversal.prune_control_types.is_empty()
+                        && child
+                            .get_control_type()
+                            .is_ok_and(|t| traversal.prune_control_types.contains(&t));
+                    if !pruned && depth < max_depth {
+                        if let Ok(grandchildren) = self.backend.get_children(&child) {
+                            next.push(grandchildren);
+                        }
+                    }
+                    elements.push(child);
+                }
+            }
+            level = next;
+            depth += 1;
+        }
+        let visited = elements.len();
+        Ok((elements, visited))
+    }
+
+    fn anchor_key(root: &B::Element, rule: &Rule) -> (String, String) {
+        use crate::platform::Element;
+        let root_id = root
+            .runtime_id()
+            .or_else(|_| root.get_name())
+            .unwrap_or_default();
+        (root_id, rule.name.clone())
+    }
+
+    /// Follows the remembered path of `rule`, checking control types on the
+    /// way. Forgets it once it no longer leads anywhere.
+    fn resolve_anchor(&self, root: &B::Element, rule: &Rule) -> Option<B::Element> {
+        use crate::platform::Element;
+        let key = Self::anchor_key(root, rule);
+        let path = self.anchors.lock().ok()?.get(&key).cloned()?;
+
+        let mut current = root.clone();
+        for (index, control_type) in &path {
+            let child = self
+                .backend
+                .get_children(&current)
+                .ok()
+                .and_then(|mut children| {
+                    (*index < children.len()).then(|| children.swap_remove(*index))
+                })
+                .filter(|c| c.get_control_type().is_ok_and(|t| t == *control_type));
+            match child {
+                Some(child) => current = child,
+                None => {
+                    if let Ok(mut anchors) = self.anchors.lock() {
+                        anchors.remove(&key);
+                    }
+                    return None;
+                }
+            }
+        }
+        Some(current)
+    }
+
+    /// Records the path from `root` to the parent of the matched button.
+    fn remember_anchor(&self, root: &B::Element, found: &ScanMatch<B::Element>) {
+        use crate::platform::Element;
+        // Ancestors run from the parent up to the root; keep those below it
+        let ancestors = self.ancestors_to(root, &found.button);
+        let Some((last, below)) = ancestors.split_last() else {
+            return;
+        };
+        if !last.same_as(root) {
+            return;
+        }
+        let mut path = Vec::new();
+        let mut parent = root.clone();
+        for ancestor in below.iter().rev() {
+            let Ok(children) = self.backend.get_children(&parent) else {
+                return;
+            };
+            let Some(index) = children.iter().position(|c| c.same_as(ancestor)) else {
+                return;
+            };
+            let Ok(control_type) = ancestor.get_control_type() else {
+                return;
+            };
+            path.push((index, control_type));
+            parent = ancestor.clone();
+        }
+        if let Ok(mut anchors) = self.anchors.lock() {
+            anchors.insert(Self::anchor_key(root, &found.rule), path);
+        }
+    }
+
+    /// Pairs buttons with nearby contexts. Stops at the first match unless
+    /// `all` is set; a button matched by several rules is returned once, for
+    /// the first of them. Matches carry the index of their rule.
+    fn match_rules(
+        &self,
+        elements: &[B::Element],
+        rules: &[Rule],
+        window: &B::Element,
+        all: bool,
+    ) -> Vec<(usize, ScanMatch<B::Element>)> {
+        use crate::platform::Element;
 
-        let mut ctx_found = false;
-        let mut btn_found = None;
+        // Per-rule candidates, indexed like `rules`. A button and a context
+        // only count together when they are close enough to each other.
+        let mut contexts: Vec<Vec<(B::Element, String)>> = vec![Vec::new(); rules.len()];
+        let mut buttons: Vec<Vec<B::Element>> = vec![Vec::new(); rules.len()];
+        // Ancestor chains, computed once per candidate
+        let mut chains: Vec<(B::Element, Vec<B::Element>)> = Vec::new();
+        let mut found: Vec<(usize, ScanMatch<B::Element>)> = Vec::new();
+        let texts: Vec<(Needles, Needles)> = rules
+            .iter()
+            .map(|r| {
+                (
+                    Needles::new(&r.button_texts),
+                    Needles::new(&r.context_texts),
+                )
+            })
+            .collect();
 
-        for element in elements {
+        'scan: for element in elements {
             if let Ok(name) = element.get_name() {
                 if name.trim().is_empty() {
                     continue;
                 }
 
-                // Check Context
-                if !context_text.is_empty() && !ctx_found {
-                    for part in context_text {
-                        if name.contains(part) {
-                            ctx_found = true;
-                            // Don't break, need button
+                // Control type is fetched lazily, only for name matches
+                let mut ctype: Option<String> = None;
+                let normalized = text::normalize(&name);
+
+                for (i, rule) in rules.iter().enumerate() {
+                    let (button_texts, context_texts) = &texts[i];
+                    // Check Context
+                    if rule.needs_context()
+                        && (context_texts.found_in(&normalized)
+                            || rule
+                                .context_selector
+                                .as_ref()
+                                .is_some_and(|s| self.selector_matches(window, s, element)))
+                    {
+                        let mut paired = Vec::new();
+                        for (b, button) in buttons[i].iter().enumerate() {
+                            if let Some(relation) =
+                                self.relate(window, &mut chains, button, element, rule)
+                            {
+                                found.push((
+                                    i,
+                                    ScanMatch {
+                                        rule: rule.clone(),
+                                        button: button.clone(),
+                                        context: Some(name.clone()),
+                                        relation: Some(relation),
+                                    },
+                                ));
+                                if !all {
+                                    break 'scan;
+                                }
+                                paired.push(b);
+                            }
+                        }
+                        for b in paired.into_iter().rev() {
+                            buttons[i].remove(b);
                         }
+                        contexts[i].push((element.clone(), name.clone()));
                     }
-                }
 
-                // Check Button with Safe String Type Check
-                if !button_text.is_empty() && btn_found.is_none() {
-                    for part in button_text {
-                        if name.contains(part) {
-                            if let Ok(ctype) = element.get_control_type() {
-                                // ctype is already a String in our abstraction
-                                if ctype.contains("Button") || ctype.contains("Hyperlink") {
-                                    btn_found = Some(element.clone());
+                    // Check Button with Safe String Type Check
+                    if rule.matches_button_name(&normalized, button_texts) {
+                        if ctype.is_none() {
+                            ctype = element.get_control_type().ok();
+                        }
+                        if ctype
+                            .as_deref()
+                            .is_some_and(|t| rule.matches_control_type(t))
+                            && (rule.properties.is_empty()
+                                || element
+                                    .get_info()
+                                    .is_ok_and(|info| rule.properties.matches(&info)))
+                            && rule
+                                .button_selector
+                                .as_ref()
+                                .is_none_or(|s| self.selector_matches(window, s, element))
+                        {
+                            let context = if rule.needs_context() {
+                                contexts[i].iter().find_map(|(context, context_name)| {
+                                    self.relate(window, &mut chains, element, context, rule)
+                                        .map(|relation| Some((context_name.clone(), relation)))
+                                })
+                            } else {
+                                Some(None)
+                            };
+                            match context {
+                                Some(context) => {
+                                    let (context, relation) = context.unzip();
+                                    found.push((
+                                        i,
+                                        ScanMatch {
+                                            rule: rule.clone(),
+                                            button: element.clone(),
+                                            context,
+                                            relation,
+                                        },
+                                    ));
+                                    if !all {
+                                        break 'scan;
+                                    }
                                 }
+                                None => buttons[i].push(element.clone()),
                             }
                         }
                     }
                 }
+            }
+        }
+
+        found.sort_by_key(|(i, _)| *i);
+        let mut matches: Vec<(usize, ScanMatch<B::Element>)> = Vec::new();
+        for (i, candidate) in found {
+            if !matches
+                .iter()
+                .any(|(_, m)| m.button.same_as(&candidate.button))
+            {
+                matches.push((i, candidate));
+            }
+        }
+        matches
+    }
+
+    /// Relation of `context` to `button`, if close enough for `rule`.
+    fn relate(
+        &self,
+        root: &B::Element,
+        chains: &mut Vec<(B::Element, Vec<B::Element>)>,
+        button: &B::Element,
+        context: &B::Element,
+        rule: &Rule,
+    ) -> Option<ContextRelation> {
+        use crate::platform::Element;
+        let mut chain = |element: &B::Element| {
+            if let Some((_, chain)) = chains.iter().find(|(e, _)| e.same_as(element)) {
+                return chain.clone();
+            }
+            let mut chain = vec![element.clone()];
+            chain.extend(self.ancestors_to(root, element));
+            chains.push((element.clone(), chain.clone()));
+            chain
+        };
+        let button_chain = chain(button);
+        let context_chain = chain(context);
 
-                if ctx_found && btn_found.is_some() {
-                    return Ok((true, btn_found));
+        let mut relation = ContextRelation::default();
+        'outer: for (up, ancestor) in button_chain.iter().enumerate() {
+            for (down, other) in context_chain.iter().enumerate() {
+                if ancestor.same_as(other) {
+                    relation.hops = Some(up + down);
+                    relation.common_ancestor = ancestor.get_control_type().ok();
+                    break 'outer;
                 }
             }
         }
+        let bounds = |e: &B::Element| e.get_bounds().ok().filter(|b| !b.is_empty());
+        if let (Some(a), Some(b)) = (bounds(button), bounds(context)) {
+            relation.distance = Some(a.distance_to(&b));
+        }
+        rule.proximity
+            .allows(relation.hops, relation.distance)
+            .then_some(relation)
+    }
+
+    /// Ancestors of `element`, parent first, up to and including `root`.
+    fn ancestors_to(&self, root: &B::Element, element: &B::Element) -> Vec<B::Element> {
+        use crate::platform::Element;
+        let mut ancestors = Vec::new();
+        if element.same_as(root) {
+            return ancestors;
+        }
+        let mut current = element.clone();
+        for _ in 0..MAX_SELECTOR_DEPTH {
+            let Ok(parent) = self.backend.get_parent(&current) else {
+                break;
+            };
+            let at_root = parent.same_as(root);
+            ancestors.push(parent.clone());
+            if at_root {
+                break;
+            }
+            current = parent;
+        }
+        ancestors
+    }
+
+    /// All elements under `root` (and `root` itself) matching `selector`,
+    /// in document order.
+    pub fn select(&self, root: &B::Element, selector: &Selector) -> Result<Vec<B::Element>> {
+        use crate::platform::Element;
+        let mut elements = vec![root.clone()];
+        elements.extend(root.find_elements(Scope::Descendants)?);
+        Ok(elements
+            .into_iter()
+            .filter(|e| self.selector_matches(root, selector, e))
+            .collect())
+    }
+
+    /// Matches right to left: the element against the last step first, then
+    /// its ancestors up to `root` against the rest.
+    fn selector_matches(
+        &self,
+        root: &B::Element,
+        selector: &Selector,
+        element: &B::Element,
+    ) -> bool {
+        if !Self::step_matches(selector.last_step(), element) {
+            return false;
+        }
+        let ancestors = self.ancestors_to(root, element);
+        selector.matches_chain(element, &ancestors, |step, node| {
+            Self::step_matches(step, node)
+        })
+    }
 
-        Ok((ctx_found, btn_found))
+    fn step_matches(step: &Step, element: &B::Element) -> bool {
+        use crate::platform::Element;
+        if step.needs_info() {
+            return element.get_info().is_ok_and(|info| step.matches(&info));
+        }
+        let info = ElementInfo {
+            name: element.get_name().unwrap_or_default(),
+            control_type: element.get_control_type().unwrap_or_default(),
+            ..ElementInfo::default()
+        };
+        step.matches(&info)
     }
 
-    pub fn get_ancestry(&self, element: &PlatformElement) -> Result<Vec<PlatformElement>> {
+    pub fn get_ancestry(&self, element: &B::Element) -> Result<Vec<B::Element>> {
         let mut ancestors = Vec::new();
         let mut curr = element.clone();
 
@@ -111,3 +728,361 @@ impl QueryService {
         Ok(ancestors)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, PropertyFilter, Proximity, Traversal};
+    use crate::platform::fixture::{
+        load_fixture, load_test_fixture, FixtureBackend, FixtureElement,
+    };
+    use crate::platform::Element;
+    use crate::services::snapshot::SnapshotNode;
+
+    fn scan(fixture: &str) -> Option<ScanMatch<FixtureElement>> {
+        scan_with(fixture, &AppConfig::default().effective_rules())
+    }
+
+    fn scan_with(fixture: &str, rules: &[Rule]) -> Option<ScanMatch<FixtureElement>> {
+        let backend = load_test_fixture(fixture);
+        let service = QueryService::with_backend(backend.clone());
+        let window = backend.get_all_windows().unwrap().remove(0);
+        service.scan_within(&window, &window, rules).unwrap()
+    }
+
+    #[test]
+    fn finds_accept_next_to_run_command() {
+        let found = scan("agent_manager_run_command.json").expect("prompt should match");
+        assert_eq!(found.rule.name, "Agent Manager");
+        assert_eq!(found.button.get_name().unwrap(), "Accept");
+        assert_eq!(found.button.get_control_type().unwrap(), "Button");
+    }
+
+    #[test]
+    fn ignores_accept_without_context() {
+        assert!(scan("agent_manager_idle.json").is_none());
+    }
+
+    #[test]
+    fn property_filter_narrows_button_match() {
+        let rule = |properties| Rule {
+            name: "Accept by id".to_string(),
+            button_texts: vec!["Accept".to_string()],
+            properties,
+            ..Rule::default()
+        };
+        let by_id = rule(PropertyFilter {
+            automation_id: Some("run-command-accept".to_string()),
+            enabled: Some(true),
+            patterns: vec!["Invoke".to_string()],
+            ..PropertyFilter::default()
+        });
+        let found = scan_with("agent_manager_run_command.json", &[by_id]).expect("id matches");
+        assert_eq!(found.button.get_name().unwrap(), "Accept");
+
+        let wrong_id = rule(PropertyFilter {
+            automation_id: Some("other".to_string()),
+            ..PropertyFilter::default()
+        });
+        assert!(scan_with("agent_manager_run_command.json", &[wrong_id]).is_none());
+    }
+
+    #[test]
+    fn batched_scan_reads_fewer_elements_with_same_result() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let rules = AppConfig::default().effective_rules();
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let mut service = QueryService::with_backend(backend);
+
+        let (batched, batched_stats) = service.first_match(&window, &window, &rules).unwrap();
+        service.set_batched(false);
+        let (full, full_stats) = service.first_match(&window, &window, &rules).unwrap();
+
+        assert!(batched.unwrap().button.same_as(&full.unwrap().button));
+        assert!(batched_stats.elements < full_stats.elements);
+    }
+
+    fn run_command_rule(traversal: Traversal) -> Rule {
+        Rule {
+            name: "Run command".to_string(),
+            button_texts: vec!["Accept".to_string()],
+            context_texts: vec!["Run command?".to_string()],
+            traversal,
+            ..Rule::default()
+        }
+    }
+
+    #[test]
+    fn bounded_walk_respects_depth_and_pruning() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let service = QueryService::with_backend(backend);
+        let scan = |traversal| {
+            service
+                .first_match(&window, &window, &[run_command_rule(traversal)])
+                .unwrap()
+        };
+
+        // The button sits three levels below the window
+        let (found, _) = scan(Traversal {
+            max_depth: Some(2),
+            ..Traversal::default()
+        });
+        assert!(found.is_none());
+        let (found, _) = scan(Traversal {
+            max_depth: Some(3),
+            ..Traversal::default()
+        });
+        assert!(found.is_some());
+
+        let (found, _) = scan(Traversal {
+            prune_control_types: vec!["Group".to_string()],
+            ..Traversal::default()
+        });
+        assert!(found.is_none());
+        let (found, pruned) = scan(Traversal {
+            prune_control_types: vec!["Pane".to_string()],
+            max_depth: Some(3),
+            ..Traversal::default()
+        });
+        assert!(found.is_none());
+        assert_eq!(pruned.visited, 3);
+    }
+
+    #[test]
+    fn anchored_rule_rescans_only_its_container() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let service = QueryService::with_backend(backend);
+        let rules = [run_command_rule(Traversal {
+            anchor: true,
+            ..Traversal::default()
+        })];
+
+        let (first, first_stats) = service.first_match(&window, &window, &rules).unwrap();
+        let (second, second_stats) = service.first_match(&window, &window, &rules).unwrap();
+        assert!(first.unwrap().button.same_as(&second.unwrap().button));
+        assert!(second_stats.visited < first_stats.visited);
+    }
+
+    #[test]
+    fn full_scan_finds_prompts_outside_the_anchor() {
+        let backend = load_test_fixture("agent_manager_two_prompts.json");
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let service = QueryService::with_backend(backend);
+        let rules = [run_command_rule(Traversal {
+            anchor: true,
+            ..Traversal::default()
+        })];
+
+        // Anchors the rule at the first prompt's group
+        let first = service.scan_within(&window, &window, &rules).unwrap();
+        let first = first.unwrap().button;
+
+        let (found, _) = service.scan_all_with_stats(&window, &rules).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().any(|m| m.button.same_as(&first)));
+        assert!(found.iter().any(|m| !m.button.same_as(&first)));
+    }
+
+    #[test]
+    fn selector_rule_matches_by_structure() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let service = QueryService::with_backend(backend);
+        let rule = |selector: &str| Rule {
+            name: "Run command".to_string(),
+            context_texts: vec!["Run command?".to_string()],
+            button_selector: Some(selector.parse().unwrap()),
+            ..Rule::default()
+        };
+
+        let found = service
+            .scan_within(
+                &window,
+                &window,
+                &[rule(r#"Window >> Group > Button[name="Accept"]"#)],
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.button.get_name().unwrap(), "Accept");
+        assert!(service
+            .scan_within(
+                &window,
+                &window,
+                &[rule(r#"Window > Group > Button[name="Accept"]"#)]
+            )
+            .unwrap()
+            .is_none());
+
+        let selector: Selector = r#"Pane[name="Conversation"] >> Button"#.parse().unwrap();
+        assert_eq!(service.select(&window, &selector).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn context_must_be_near_the_button() {
+        let rule = |context: &str, proximity| Rule {
+            name: "Accept".to_string(),
+            button_texts: vec!["Accept".to_string()],
+            context_texts: vec![context.to_string()],
+            proximity,
+            ..Rule::default()
+        };
+
+        let found = scan_with(
+            "agent_manager_run_command.json",
+            &[rule("Run command?", Proximity::default())],
+        )
+        .unwrap();
+        let relation = found.relation.unwrap();
+        assert_eq!(relation.hops, Some(2));
+        assert_eq!(relation.common_ancestor.as_deref(), Some("Group"));
+
+        // The header text is five hops and hundreds of pixels away
+        assert!(scan_with(
+            "agent_manager_run_command.json",
+            &[rule("Agent Manager", Proximity::default())]
+        )
+        .is_none());
+        let far = Proximity {
+            max_hops: Some(5),
+            max_distance: None,
+        };
+        let found = scan_with(
+            "agent_manager_run_command.json",
+            &[rule("Agent Manager", far)],
+        )
+        .unwrap();
+        assert_eq!(
+            found.relation.unwrap().to_string(),
+            "5 hops via Window, 785px apart"
+        );
+    }
+
+    fn button_rule(name: &str, button: &str) -> Rule {
+        Rule {
+            name: name.to_string(),
+            button_texts: vec![button.to_string()],
+            context_texts: vec!["Run command?".to_string()],
+            ..Rule::default()
+        }
+    }
+
+    fn found_button(found: Option<ScanMatch<FixtureElement>>) -> String {
+        found.unwrap().button.get_name().unwrap()
+    }
+
+    #[test]
+    fn rules_are_tried_by_priority_then_config_order() {
+        let fixture = "agent_manager_run_command.json";
+        let accept = button_rule("Accept", "Accept");
+        let reject = button_rule("Reject", "Reject");
+
+        // Reject comes first in the document, Accept first in the config
+        let found = scan_with(fixture, &[accept.clone(), reject.clone()]);
+        assert_eq!(found_button(found), "Accept");
+
+        // A higher priority wins even from a rule with its own walk
+        let bounded_reject = Rule {
+            traversal: Traversal {
+                max_depth: Some(3),
+                ..Traversal::default()
+            },
+            ..reject
+        };
+        let urgent_accept = Rule {
+            priority: 5,
+            ..accept
+        };
+        let found = scan_with(fixture, &[bounded_reject, urgent_accept]);
+        assert_eq!(found_button(found), "Accept");
+    }
+
+    #[test]
+    fn anchor_of_a_later_rule_does_not_jump_the_queue() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let service = QueryService::with_backend(backend);
+        let reject = Rule {
+            traversal: Traversal {
+                anchor: true,
+                ..Traversal::default()
+            },
+            ..button_rule("Reject", "Reject")
+        };
+
+        let found = service
+            .scan_within(&window, &window, std::slice::from_ref(&reject))
+            .unwrap();
+        assert_eq!(found_button(found), "Reject");
+        let found = service
+            .scan_within(&window, &window, &[button_rule("Accept", "Accept"), reject])
+            .unwrap();
+        assert_eq!(found_button(found), "Accept");
+    }
+
+    #[test]
+    fn zero_depth_walk_visits_nothing() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let service = QueryService::with_backend(backend);
+        let rule = run_command_rule(Traversal {
+            max_depth: Some(0),
+            ..Traversal::default()
+        });
+
+        let (found, stats) = service.first_match(&window, &window, &[rule]).unwrap();
+        assert!(found.is_none());
+        assert_eq!(stats.visited, 0);
+    }
+
+    #[test]
+    fn anchors_are_kept_for_deeply_nested_prompts() {
+        // Wrap the prompt group in a dozen more groups
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        let conversation = &mut fixture.windows[0].root.children[2];
+        let mut prompt = conversation.children.remove(1);
+        for _ in 0..12 {
+            prompt = SnapshotNode {
+                children: vec![prompt.clone()],
+                ..prompt
+            };
+        }
+        conversation.children.insert(1, prompt);
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let service = QueryService::with_backend(backend);
+        let rules = [run_command_rule(Traversal {
+            anchor: true,
+            ..Traversal::default()
+        })];
+
+        let (first, first_stats) = service.first_match(&window, &window, &rules).unwrap();
+        let (second, second_stats) = service.first_match(&window, &window, &rules).unwrap();
+        assert!(first.unwrap().button.same_as(&second.unwrap().button));
+        assert!(second_stats.visited < first_stats.visited);
+    }
+
+    #[test]
+    fn scan_below_an_ancestor_matches_selectors_from_the_window() {
+        let backend = load_test_fixture("agent_manager_run_command.json");
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let service = QueryService::with_backend(backend);
+        let group: Selector = "Window > Pane > Group".parse().unwrap();
+        let group = service.select(&window, &group).unwrap().remove(0);
+        let rule = Rule {
+            name: "Run command".to_string(),
+            context_texts: vec!["Run command?".to_string()],
+            button_selector: Some(
+                r#"Window > Pane > Group > Button[name="Accept"]"#.parse().unwrap(),
+            ),
+            ..Rule::default()
+        };
+
+        let found = service.scan_within(&window, &group, std::slice::from_ref(&rule));
+        assert_eq!(found_button(found.unwrap()), "Accept");
+        // From the group alone the selector has no window to start at
+        let found = service.scan_within(&group, &group, &[rule]).unwrap();
+        assert!(found.is_none());
+    }
+}
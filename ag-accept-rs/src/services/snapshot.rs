@@ -0,0 +1,434 @@
+use crate::platform::{Backend, Bounds, Element, ElementInfo, PlatformBackend, PlatformElement};
+use crate::text;
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum SnapshotFormat {
+    Json,
+    Text,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotNode {
+    pub name: String,
+    pub control_type: String,
+    pub bounds: Option<Bounds>,
+    /// Slash-separated `ControlType[index]` segments from the snapshot root.
+    pub path: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub automation_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub help_text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default = "default_enabled", skip_serializing_if = "is_true")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub offscreen: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    pub children: Vec<SnapshotNode>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl SnapshotNode {
+    /// Properties of this node as `Element::get_info` would report them.
+    pub fn info(&self) -> ElementInfo {
+        ElementInfo {
+            name: self.name.clone(),
+            control_type: self.control_type.clone(),
+            bounds: self.bounds,
+            enabled: self.enabled,
+            offscreen: self.offscreen,
+            automation_id: self.automation_id.clone(),
+            help_text: self.help_text.clone(),
+            value: self.value.clone(),
+            patterns: self.patterns.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    pub window: String,
+    pub captured_at: String,
+    pub root: SnapshotNode,
+}
+
+impl TreeSnapshot {
+    pub fn node_count(&self) -> usize {
+        fn count(node: &SnapshotNode) -> usize {
+            1 + node.children.iter().map(count).sum::<usize>()
+        }
+        count(&self.root)
+    }
+
+    pub fn to_text(&self) -> String {
+        fn write(node: &SnapshotNode, depth: usize, out: &mut String) {
+            let id = if node.automation_id.is_empty() {
+                String::new()
+            } else {
+                format!(" #{}", node.automation_id)
+            };
+            out.push_str(&format!(
+                "{}{}{} \"{}\" {}  [{}]\n",
+                "  ".repeat(depth),
+                node.control_type,
+                id,
+                node.name,
+                format_bounds(node.bounds),
+                node.path
+            ));
+            for child in &node.children {
+                write(child, depth + 1, out);
+            }
+        }
+
+        let mut out = format!("# {} @ {}\n", self.window, self.captured_at);
+        write(&self.root, 0, &mut out);
+        out
+    }
+
+    pub fn save(&self, path: &Path, format: SnapshotFormat) -> Result<()> {
+        let content = match format {
+            SnapshotFormat::Json => serde_json::to_string_pretty(self)?,
+            SnapshotFormat::Text => self.to_text(),
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("{} is not a JSON tree snapshot: {}", path.display(), e))
+    }
+
+    /// Default file name for a snapshot of `window` taken now.
+    pub fn default_path(window: &str, format: SnapshotFormat) -> PathBuf {
+        let slug: String = window
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .take(40)
+            .collect();
+        let ext = match format {
+            SnapshotFormat::Json => "json",
+            SnapshotFormat::Text => "txt",
+        };
+        PathBuf::from(format!(
+            "tree_{}_{}.{}",
+            slug,
+            Local::now().format("%Y%m%d_%H%M%S"),
+            ext
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Clone, Debug)]
+pub struct DiffEntry {
+    pub kind: DiffKind,
+    pub path: String,
+    pub description: String,
+}
+
+/// Compares two snapshots. Children are aligned by (control type, name) so an
+/// inserted node shows up as one addition rather than shifting its siblings.
+pub fn diff(before: &TreeSnapshot, after: &TreeSnapshot) -> Vec<DiffEntry> {
+    let mut out = Vec::new();
+    diff_node(&before.root, &after.root, &mut out);
+    out
+}
+
+pub fn format_bounds(bounds: Option<Bounds>) -> String {
+    bounds
+        .map(|b| format!("({},{} {}x{})", b.x, b.y, b.width, b.height))
+        .unwrap_or_else(|| "(no bounds)".to_string())
+}
+
+fn describe(node: &SnapshotNode) -> String {
+    format!("{} \"{}\"", node.control_type, node.name)
+}
+
+fn same_identity(a: &SnapshotNode, b: &SnapshotNode) -> bool {
+    a.control_type == b.control_type && a.name == b.name
+}
+
+fn push_subtree(node: &SnapshotNode, kind: DiffKind, out: &mut Vec<DiffEntry>) {
+    out.push(DiffEntry {
+        kind,
+        path: node.path.clone(),
+        description: describe(node),
+    });
+    for child in &node.children {
+        push_subtree(child, kind, out);
+    }
+}
+
+fn diff_node(before: &SnapshotNode, after: &SnapshotNode, out: &mut Vec<DiffEntry>) {
+    if !same_identity(before, after) {
+        out.push(DiffEntry {
+            kind: DiffKind::Changed,
+            path: after.path.clone(),
+            description: format!("{} -> {}", describe(before), describe(after)),
+        });
+    } else if before.bounds != after.bounds {
+        out.push(DiffEntry {
+            kind: DiffKind::Changed,
+            path: after.path.clone(),
+            description: format!(
+                "{} moved {} -> {}",
+                describe(after),
+                format_bounds(before.bounds),
+                format_bounds(after.bounds)
+            ),
+        });
+    }
+
+    // Longest common subsequence over child identities
+    let (a, b) = (&before.children, &after.children);
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if same_identity(&a[i], &b[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same_identity(&a[i], &b[j]) {
+            diff_node(&a[i], &b[j], out);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            push_subtree(&a[i], DiffKind::Removed, out);
+            i += 1;
+        } else {
+            push_subtree(&b[j], DiffKind::Added, out);
+            j += 1;
+        }
+    }
+    for node in &a[i..] {
+        push_subtree(node, DiffKind::Removed, out);
+    }
+    for node in &b[j..] {
+        push_subtree(node, DiffKind::Added, out);
+    }
+}
+
+pub struct SnapshotService {
+    backend: PlatformBackend,
+}
+
+impl SnapshotService {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            backend: PlatformBackend::new()?,
+        })
+    }
+
+    /// All top-level windows whose title contains `title` (normalized, see
+    /// `text::normalize`).
+    pub fn find_windows(&self, title: &str) -> Result<Vec<(String, PlatformElement)>> {
+        Ok(self
+            .backend
+            .get_all_windows()?
+            .into_iter()
+            .filter_map(|w| {
+                let name = w.get_name().ok()?;
+                text::title_matches(&name, title).then_some((name, w))
+            })
+            .collect())
+    }
+
+    /// Finds the first top-level window whose title contains `title`.
+    pub fn find_window(&self, title: &str) -> Result<(String, PlatformElement)> {
+        self.find_windows(title)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No window matching '{}'", title))
+    }
+
+    /// Serializes the subtree under `root`, descending at most `max_depth` levels.
+    pub fn capture(
+        &self,
+        root: &PlatformElement,
+        window: &str,
+        max_depth: usize,
+    ) -> Result<TreeSnapshot> {
+        let info = Self::info(root);
+        let path = info.control_type.clone();
+        Ok(TreeSnapshot {
+            window: window.to_string(),
+            captured_at: Local::now().to_rfc3339(),
+            root: self.capture_node(root, info, path, max_depth),
+        })
+    }
+
+    /// All properties at once (`Element::get_info`), falling back to name and
+    /// type.
+    fn info(element: &PlatformElement) -> ElementInfo {
+        element.get_info().unwrap_or_else(|_| ElementInfo {
+            name: element.get_name().unwrap_or_default(),
+            control_type: element
+                .get_control_type()
+                .unwrap_or_else(|_| "?".to_string()),
+            enabled: true,
+            ..ElementInfo::default()
+        })
+    }
+
+    fn capture_node(
+        &self,
+        element: &PlatformElement,
+        info: ElementInfo,
+        path: String,
+        remaining_depth: usize,
+    ) -> SnapshotNode {
+        let mut children = Vec::new();
+        if remaining_depth > 0 {
+            // A child that vanishes mid-capture just ends that branch
+            let elements = self.backend.get_children(element).unwrap_or_default();
+            for (i, child) in elements.iter().enumerate() {
+                let child_info = Self::info(child);
+                let child_path = format!("{}/{}[{}]", path, child_info.control_type, i);
+                children.push(self.capture_node(
+                    child,
+                    child_info,
+                    child_path,
+                    remaining_depth - 1,
+                ));
+            }
+        }
+
+        SnapshotNode {
+            name: info.name,
+            control_type: info.control_type,
+            bounds: info.bounds,
+            path,
+            automation_id: info.automation_id,
+            help_text: info.help_text,
+            value: info.value,
+            enabled: info.enabled,
+            offscreen: info.offscreen,
+            patterns: info.patterns,
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture::load_fixture;
+
+    fn agent_manager(fixture: &str) -> TreeSnapshot {
+        load_fixture(fixture)
+            .windows
+            .into_iter()
+            .find(|w| w.window == "Agent Manager - Antigravity")
+            .unwrap()
+    }
+
+    fn entries(diff: &[DiffEntry]) -> Vec<(DiffKind, &str, &str)> {
+        diff.iter()
+            .map(|e| (e.kind, e.path.as_str(), e.description.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_nodes() {
+        let before = agent_manager("agent_manager_run_command.json");
+        let mut after = before.clone();
+        after.root.name = "Agent Manager - Antigravity (2)".to_string();
+        let conversation = &mut after.root.children[2];
+        let group = &mut conversation.children[1];
+        let reject = group.children.remove(2);
+        group.children[2].bounds = Some(Bounds {
+            x: 1000,
+            y: 200,
+            width: 80,
+            height: 30,
+        });
+        let mut waiting = reject.clone();
+        waiting.control_type = "Text".to_string();
+        waiting.name = "Waiting for approval".to_string();
+        waiting.path = "Window/Pane[2]/Text[3]".to_string();
+        conversation.children.push(waiting);
+
+        assert_eq!(
+            entries(&diff(&before, &after)),
+            vec![
+                (
+                    DiffKind::Changed,
+                    "Window",
+                    "Window \"Agent Manager - Antigravity\" -> Window \"Agent Manager - Antigravity (2)\""
+                ),
+                (
+                    DiffKind::Removed,
+                    "Window/Pane[2]/Group[1]/Button[2]",
+                    "Button \"Reject\""
+                ),
+                (
+                    DiffKind::Changed,
+                    "Window/Pane[2]/Group[1]/Button[3]",
+                    "Button \"Accept\" moved (1110,190 80x28) -> (1000,200 80x30)"
+                ),
+                (
+                    DiffKind::Added,
+                    "Window/Pane[2]/Text[3]",
+                    "Text \"Waiting for approval\""
+                ),
+            ]
+        );
+        assert!(diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn text_lists_nodes_indented_with_ids_and_paths() {
+        let mut snapshot = agent_manager("agent_manager_run_command.json");
+        snapshot.captured_at = "2026-10-18T12:00:00+00:00".to_string();
+        snapshot.root.children.truncate(1);
+        let text = snapshot.to_text();
+        assert_eq!(
+            text.lines().take(4).collect::<Vec<_>>(),
+            vec![
+                "# Agent Manager - Antigravity @ 2026-10-18T12:00:00+00:00",
+                "Window \"Agent Manager - Antigravity\" (0,0 1280x800)  [Window]",
+                "  Pane \"\" (0,0 1280x40)  [Window/Pane[0]]",
+                "    Button \"New Conversation\" (10,5 120x30)  [Window/Pane[0]/Button[0]]",
+            ]
+        );
+
+        // One line per node after the header
+        let prompt = agent_manager("agent_manager_run_command.json");
+        let text = prompt.to_text();
+        assert_eq!(text.lines().count(), 1 + prompt.node_count());
+        assert!(text.contains("Button #run-command-accept \"Accept\""));
+    }
+}
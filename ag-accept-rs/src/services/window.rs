@@ -1,93 +1,238 @@
-use crate::platform::{Backend, PlatformBackend, PlatformElement};
-use anyhow::Result;
+use crate::keys::KeySequence;
+use crate::platform::{Backend, Element, PlatformBackend, WindowState};
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+use tracing::debug;
 
-pub struct WindowService {
-    backend: PlatformBackend,
-    previous_focus: Option<PlatformElement>,
+/// Focus to return to once a window's prompts are handled.
+struct SavedFocus<E> {
+    element: E,
+    /// Outermost ancestor of `element` below the desktop root, focused
+    /// instead when the element itself is gone.
+    window: Option<E>,
+    title: String,
 }
 
-impl WindowService {
+/// How far `restore_previous_focus` got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FocusRestore {
+    /// Nothing was focused by `focus_window`.
+    Nothing,
+    /// The previously focused element has focus again.
+    Element,
+    /// The element was gone or refused focus; its window has focus.
+    Window,
+}
+
+pub struct WindowService<B: Backend = PlatformBackend> {
+    backend: B,
+    previous_focus: Option<SavedFocus<B::Element>>,
+    /// Windows whose visual state `focus_window` changed, with the state to
+    /// put back on restore.
+    changed_states: Vec<(B::Element, WindowState)>,
+}
+
+impl<B: Backend> WindowService<B> {
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            backend: PlatformBackend::new()?,
+        Ok(Self::with_backend(B::new()?))
+    }
+
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
             previous_focus: None,
-        })
+            changed_states: Vec::new(),
+        }
+    }
+
+    pub fn set_call_timeout(&mut self, timeout: Duration) {
+        self.backend.set_call_timeout(timeout);
     }
 
     #[allow(dead_code)]
-    pub fn get_root(&self) -> Result<PlatformElement> {
+    pub fn get_root(&self) -> Result<B::Element> {
         self.backend.get_root_element()
     }
 
-    pub fn focus_window(&mut self, window: &PlatformElement) -> Result<()> {
-        use crate::platform::Element;
-        if let Ok(current) = self.backend.get_focused_element() {
-            self.previous_focus = Some(current);
-        } else {
-            self.previous_focus = None;
+    /// Focuses `window`, first restoring it if it is minimized, maximized
+    /// again if it was before. The focus it replaces is kept until
+    /// `restore_previous_focus`, so focusing again in between does not
+    /// overwrite it.
+    pub fn focus_window(&mut self, window: &B::Element) -> Result<()> {
+        if self.previous_focus.is_none() {
+            self.previous_focus = self.save_focus();
         }
 
+        if window.window_state().ok() == Some(WindowState::Minimized)
+            && !self.changed_states.iter().any(|(w, _)| w.same_as(window))
+        {
+            let restored = window.restored_state().unwrap_or(WindowState::Normal);
+            window.set_window_state(restored)?;
+            self.changed_states
+                .push((window.clone(), WindowState::Minimized));
+        }
         window.set_focus()
     }
 
-    pub fn restore_previous_focus(&mut self) -> Result<()> {
-        use crate::platform::Element;
-        if let Some(prev) = &self.previous_focus {
-            let _ = prev.set_focus();
+    fn save_focus(&self) -> Option<SavedFocus<B::Element>> {
+        let chain = self.focus_chain().ok()?;
+        let element = chain.first()?.clone();
+        let window = (chain.len() > 1).then(|| chain[chain.len() - 1].clone());
+        let title = window
+            .as_ref()
+            .unwrap_or(&element)
+            .get_name()
+            .unwrap_or_default();
+        Some(SavedFocus {
+            element,
+            window,
+            title,
+        })
+    }
+
+    /// Puts the windows `focus_window` restored back in their previous
+    /// visual state, then returns focus to the element that had it, or to
+    /// its window when the element is gone. Each is retried with a growing
+    /// delay until focus is verified there.
+    pub fn restore_previous_focus(&mut self) -> Result<FocusRestore> {
+        for (window, state) in std::mem::take(&mut self.changed_states) {
+            if let Err(e) = window.set_window_state(state) {
+                debug!("Failed to return window to {:?}: {}", state, e);
+            }
+        }
+
+        let Some(saved) = self.previous_focus.take() else {
+            return Ok(FocusRestore::Nothing);
+        };
+
+        if saved.element.runtime_id().is_ok()
+            && self.focus_with_retries(&saved.element, |chain| {
+                chain.first().is_some_and(|f| f.same_as(&saved.element))
+            })
+        {
+            return Ok(FocusRestore::Element);
+        }
+        if let Some(window) = &saved.window {
+            if self.focus_with_retries(window, |chain| chain.iter().any(|e| e.same_as(window))) {
+                return Ok(FocusRestore::Window);
+            }
+        }
+        Err(anyhow!(
+            "Focus did not return to '{}' after {} attempts",
+            saved.title,
+            RESTORE_ATTEMPTS
+        ))
+    }
+
+    /// Focuses `target` until `verify` accepts the focus chain.
+    fn focus_with_retries(
+        &self,
+        target: &B::Element,
+        verify: impl Fn(&[B::Element]) -> bool,
+    ) -> bool {
+        for attempt in 1..=RESTORE_ATTEMPTS {
+            if attempt > 1 {
+                std::thread::sleep(RESTORE_DELAY * (attempt - 1));
+            }
+            if let Err(e) = target.set_focus() {
+                debug!("Focus restore attempt {} failed: {}", attempt, e);
+                continue;
+            }
+            if self.focus_chain().is_ok_and(|chain| verify(&chain)) {
+                return true;
+            }
         }
-        self.previous_focus = None;
-        Ok(())
+        false
     }
 
-    pub fn get_all_windows(&self) -> Result<Vec<PlatformElement>> {
+    pub fn send_keys(&self, keys: &KeySequence) -> Result<()> {
+        self.backend.send_keys(keys)
+    }
+
+    pub fn idle_time(&self) -> Result<Duration> {
+        self.backend.idle_time()
+    }
+
+    pub fn get_all_windows(&self) -> Result<Vec<B::Element>> {
         self.backend.get_all_windows()
     }
 
-    pub fn get_focused_window_name(&self) -> Result<String> {
-        use crate::platform::Element;
-        // 1. Get focused element
-        let focused = self.backend.get_focused_element()?;
-
-        // 2. Walk up to find top-level window
-        // This logic might need to be in the backend if it's too specific,
-        // but for now let's try to keep it here using generic backend methods.
-        
-        let _root = self.backend.get_root_element()?;
-        // Note: Comparing elements might need a trait method or Eq implementation.
-        // For now, let's just assume we can get the name and if it's not empty/desktop...
-        // Actually, the original code compared elements.
-        // We might need an `is_same` method on Element trait?
-        // Or just rely on the backend to provide "get_top_level_parent"?
-        
-        // Let's implement a simple walk up.
-        let mut current = focused;
-        
-        for _ in 0..20 {
-            if let Ok(parent) = self.backend.get_parent(&current) {
-                 // Check if parent is root.
-                 // We don't have equality check yet.
-                 // Let's assume if parent name is "Desktop" or similar?
-                 // Or just check if we can't go higher?
-                 
-                 // Better: Check if parent is root.
-                 // We need equality.
-                 // Let's add `partial_eq` to Element trait or just `is_root`?
-                 
-                 // Workaround: Just return current name if parent fails or is root.
-                 // But how do we know it's root?
-                 
-                 // Let's assume the backend `get_parent` returns error or special value for root?
-                 // In uiautomation, get_parent of root might fail or return null.
-                 
-                 // Let's try to get name.
-                 current = parent;
-            } else {
-                break;
+    /// The focused element followed by its ancestors, up to but excluding
+    /// the desktop root.
+    fn focus_chain(&self) -> Result<Vec<B::Element>> {
+        let root = self.backend.get_root_element()?;
+        let mut chain = vec![self.backend.get_focused_element()?];
+
+        // Depth guard in case a provider reports a cyclic tree
+        for _ in 0..MAX_FOCUS_DEPTH {
+            let Some(current) = chain.last() else { break };
+            match self.backend.get_parent(current) {
+                Ok(parent) if !parent.same_as(&root) => chain.push(parent),
+                _ => break,
             }
         }
-        
-        // This logic is a bit flawed without equality.
-        // But for now, let's just return the name of what we found.
-        current.get_name()
+        Ok(chain)
+    }
+
+    /// Returns the index of the entry in `windows` that contains keyboard focus.
+    ///
+    /// Walks up from the focused element to the desktop root and matches the
+    /// ancestors against `windows` by identity, outermost first (UIA windows
+    /// sit directly under the root, AT-SPI ones under an application node).
+    pub fn find_focused_window(&self, windows: &[B::Element]) -> Result<Option<usize>> {
+        let chain = self.focus_chain()?;
+        Ok(chain
+            .iter()
+            .rev()
+            .find_map(|ancestor| windows.iter().position(|w| w.same_as(ancestor))))
+    }
+}
+
+const MAX_FOCUS_DEPTH: usize = 64;
+const RESTORE_ATTEMPTS: u32 = 3;
+/// Delay before the second restore attempt, growing with each one after.
+const RESTORE_DELAY: Duration = Duration::from_millis(50);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture::{load_fixture, FixtureBackend};
+
+    #[test]
+    fn focused_window_is_matched_by_identity_not_title() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        // Two windows with the same title; the recorded focus is the last one
+        fixture.windows.push(fixture.windows[0].clone());
+        fixture.focused_window = Some(fixture.windows[0].window.clone());
+        let backend = FixtureBackend::from_fixture(&fixture);
+
+        let service = WindowService::with_backend(backend.clone());
+        let windows = backend.get_all_windows().unwrap();
+        assert_eq!(service.find_focused_window(&windows).unwrap(), Some(1));
+        assert_eq!(service.find_focused_window(&windows[..1]).unwrap(), None);
+    }
+
+    #[test]
+    fn minimized_window_is_restored_to_its_previous_state_and_back() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        let title = fixture.windows[0].window.clone();
+        fixture.minimized_windows = vec![title.clone()];
+        fixture.maximized_windows = vec![title];
+        let backend = FixtureBackend::from_fixture(&fixture);
+        let window = backend.get_all_windows().unwrap().remove(0);
+        let mut service = WindowService::with_backend(backend.clone());
+
+        service.focus_window(&window).unwrap();
+        assert_eq!(window.window_state().unwrap(), WindowState::Maximized);
+        service.restore_previous_focus().unwrap();
+        assert_eq!(window.window_state().unwrap(), WindowState::Minimized);
+        assert_eq!(window.restored_state().unwrap(), WindowState::Maximized);
+        let kinds: Vec<&str> = backend
+            .actions()
+            .iter()
+            .map(|a| a.kind)
+            .filter(|k| *k != "focus")
+            .collect();
+        assert_eq!(kinds, vec!["maximize", "minimize"]);
     }
 }
@@ -0,0 +1,261 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+/// Bundled translations of the default button and context texts, keyed by
+/// locale code. Selected with `locales` in the config.
+const LOCALE_PACKS: [(&str, &str); 6] = [
+    ("ja", include_str!("locales/ja.json")),
+    ("zh-CN", include_str!("locales/zh-CN.json")),
+    ("ko", include_str!("locales/ko.json")),
+    ("de", include_str!("locales/de.json")),
+    ("fr", include_str!("locales/fr.json")),
+    ("es", include_str!("locales/es.json")),
+];
+
+/// Canonical form of UI text for matching: NFKC (full-width letters and
+/// punctuation become ASCII), access-key markers removed, lowercased and
+/// whitespace collapsed.
+pub fn normalize(text: &str) -> String {
+    let composed: String = text.nfkc().collect();
+    strip_accelerators(&composed)
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// True when the window `title` contains `pattern`, both normalized.
+pub fn title_matches(title: &str, pattern: &str) -> bool {
+    normalize(title).contains(&normalize(pattern))
+}
+
+/// Removes Windows access-key markers: `&Accept` becomes `Accept`,
+/// `承認(&A)` becomes `承認` and `&&` a literal `&`. A lone `&` before a
+/// space (`Save & Exit`) is kept.
+fn strip_accelerators(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        match chars[i] {
+            '(' if next == Some('&')
+                && chars.get(i + 2).is_some_and(|c| c.is_alphanumeric())
+                && chars.get(i + 3) == Some(&')') =>
+            {
+                i += 4;
+            }
+            '&' if next == Some('&') => {
+                out.push('&');
+                i += 2;
+            }
+            '&' if next.is_some_and(|c| !c.is_whitespace()) => i += 1,
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Rule texts normalized once, searched for in normalized names.
+#[derive(Clone, Debug, Default)]
+pub struct Needles(Vec<String>);
+
+impl Needles {
+    pub fn new(texts: &[String]) -> Self {
+        Self(
+            texts
+                .iter()
+                .map(|t| normalize(t))
+                .filter(|t| !t.is_empty())
+                .collect(),
+        )
+    }
+
+    /// True when `normalized` contains any of the texts as whole words. In
+    /// scripts that separate words with spaces, a letter or digit right
+    /// before or after a match makes it part of a longer word, so `Accept`
+    /// is not found in `Accepted`. Chinese, Japanese and Korean words run
+    /// into each other, so there a match only fails next to a negation:
+    /// `承認` is found in `承認する` but not in `承認しない` or `不承認`.
+    pub fn found_in(&self, normalized: &str) -> bool {
+        self.0.iter().any(|n| contains_word(normalized, n))
+    }
+}
+
+/// Negations written straight before a CJK word, as in `不接受`.
+const CJK_NEGATION_PREFIXES: [&str; 6] = ["不", "未", "非", "无", "無", "没"];
+
+/// Negations written straight after a CJK word, as in `承認しない` or
+/// `수락하지 않음`, normalized.
+const CJK_NEGATION_SUFFIXES: [&str; 10] = [
+    "しない",
+    "しません",
+    "せず",
+    "できない",
+    "できません",
+    "されない",
+    "されません",
+    "不可",
+    "하지 않",
+    "안 함",
+];
+
+/// Han, kana and Hangul: scripts written without spaces between words (or,
+/// for Korean, with particles attached to them).
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{11FF}'     // Hangul Jamo
+        | '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{3130}'..='\u{318F}'   // Hangul Compatibility Jamo
+        | '\u{31F0}'..='\u{31FF}'   // Katakana Phonetic Extensions
+        | '\u{3400}'..='\u{4DBF}'   // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}'   // Hangul Syllables
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{3134F}' // CJK Extensions B-G
+    )
+}
+
+fn contains_word(haystack: &str, needle: &str) -> bool {
+    let (first, last) = (needle.chars().next(), needle.chars().next_back());
+    // Punctuation at the edge of the needle ends a word by itself
+    let spaced = |edge: Option<char>| edge.is_some_and(|c| c.is_alphanumeric() && !is_cjk(c));
+    haystack.match_indices(needle).any(|(start, _)| {
+        let (before, after) = (&haystack[..start], &haystack[start + needle.len()..]);
+        let joined_before = spaced(first)
+            && before
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric);
+        let joined_after = spaced(last) && after.chars().next().is_some_and(char::is_alphanumeric);
+        let negated_before =
+            first.is_some_and(is_cjk) && CJK_NEGATION_PREFIXES.iter().any(|p| before.ends_with(p));
+        let negated_after =
+            last.is_some_and(is_cjk) && CJK_NEGATION_SUFFIXES.iter().any(|s| after.starts_with(s));
+        !(joined_before || joined_after || negated_before || negated_after)
+    })
+}
+
+pub fn locale_codes() -> impl Iterator<Item = &'static str> {
+    LOCALE_PACKS.iter().map(|(code, _)| *code)
+}
+
+fn locale_pack(code: &str) -> Result<HashMap<String, Vec<String>>> {
+    let (_, source) = LOCALE_PACKS
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .ok_or_else(|| {
+            anyhow!(
+                "Unknown locale '{}' (available: {})",
+                code,
+                locale_codes().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+    let pack: HashMap<String, Vec<String>> = serde_json::from_str(source)?;
+    Ok(pack.into_iter().map(|(k, v)| (normalize(&k), v)).collect())
+}
+
+/// Fails on locale codes without a bundled pack.
+pub fn check_locales(locales: &[String]) -> Result<()> {
+    for code in locales {
+        locale_pack(code)?;
+    }
+    Ok(())
+}
+
+/// `texts` followed by their translations in `locales`, without
+/// duplicates. Unknown locales are skipped.
+pub fn with_translations(texts: &[String], locales: &[String]) -> Vec<String> {
+    let mut out = texts.to_vec();
+    for pack in locales.iter().filter_map(|code| locale_pack(code).ok()) {
+        for text in texts {
+            for translation in pack.get(&normalize(text)).into_iter().flatten() {
+                if !out.contains(translation) {
+                    out.push(translation.clone());
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_width_case_accelerators_and_whitespace() {
+        assert_eq!(normalize("&Accept"), "accept");
+        assert_eq!(normalize("  Run\u{00a0}\n command？ "), "run command?");
+        assert_eq!(normalize("Ａｃｃｅｐｔ"), "accept");
+        assert_eq!(normalize("承認(&A)"), "承認");
+        assert_eq!(normalize("Save && Exit"), "save & exit");
+        assert_eq!(normalize("Save & Exit"), "save & exit");
+
+        let texts = with_translations(&["Accept".to_string()], &["ja".to_string()]);
+        assert_eq!(texts, vec!["Accept", "承認", "同意する"]);
+        assert!(Needles::new(&texts).found_in(&normalize("承認(&A)")));
+        assert!(check_locales(&["xx".to_string()]).is_err());
+    }
+
+    #[test]
+    fn locale_packs_match_words_but_not_negations() {
+        // Labels that must match the pack's Accept, one that must not, and
+        // text leading into the pack's Run command?
+        let cases = [
+            ("ja", ["承認(&A)", "承認する"], "承認しない", "次の"),
+            ("zh-CN", ["接受(&A)", "接受并继续"], "不接受", "是否"),
+            ("ko", ["수락(&A)", "수락하기"], "수락하지 않음", "이 "),
+            (
+                "de",
+                ["&Annehmen", "Jetzt annehmen"],
+                "Akzeptierend",
+                "Diesen ",
+            ),
+            (
+                "fr",
+                ["&Accepter", "Accepter et continuer"],
+                "Accepterez-vous ?",
+                "Voulez-vous ",
+            ),
+            (
+                "es",
+                ["&Aceptar", "Aceptar y continuar"],
+                "Aceptarlo después",
+                "Pregunta: ",
+            ),
+        ];
+        for (locale, accepts, negated, lead) in cases {
+            let locales = [locale.to_string()];
+            let texts = with_translations(&["Accept".to_string()], &locales);
+            assert!(texts.len() > 1, "{} has no Accept translation", locale);
+            let needles = Needles::new(&texts);
+            for accept in accepts {
+                assert!(
+                    needles.found_in(&normalize(accept)),
+                    "{}: {}",
+                    locale,
+                    accept
+                );
+            }
+            assert!(
+                !needles.found_in(&normalize(negated)),
+                "{}: {}",
+                locale,
+                negated
+            );
+
+            let context = with_translations(&["Run command?".to_string()], &locales);
+            let shown = format!("{}{} npm test", lead, context[1].replace('?', "？"));
+            assert!(
+                Needles::new(&context).found_in(&normalize(&shown)),
+                "{}",
+                locale
+            );
+        }
+    }
+}
@@ -1,37 +1,105 @@
-use crate::automation::WindowStat;
+use crate::active_hours::ScheduleState;
+use crate::automation::{
+    ActionOutcome, Control, LogCategory, LogEntry, LogLevel, Metrics, QueuedPrompt, WindowStat,
+};
+use crate::breaker::Trip;
 use crate::config::AppConfig;
+use crate::pipeline::StepResult;
+use crate::platform::PlatformElement;
+use crate::services::query::ContextRelation;
+use crate::services::snapshot::{SnapshotFormat, SnapshotService, TreeSnapshot};
+use crate::ui::inspector::{Inspector, InspectorPane};
+use crate::ui::log_view::LogView;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::collections::BTreeMap;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tab {
+    Monitor,
+    Inspector,
+}
+
+/// Depth limit for trees dumped from the TUI.
+const DUMP_MAX_DEPTH: usize = 64;
 
 pub struct App {
-    pub logs: Vec<String>,
+    pub tab: Tab,
+    snapshot_service: Option<SnapshotService>,
+    pub inspector: Inspector,
+    pub logs: LogView,
     pub status: String,
     pub config: AppConfig,
     pub should_quit: bool,
     pub visible_windows: Vec<WindowStat>,
     pub all_windows: Vec<String>,
     pub context_data: Option<(String, Vec<String>)>, // button_name, neighbors
+    pub context_relation: Option<(String, ContextRelation)>,
     pub processing_window: Option<String>,
     pub last_scan_ms: u64,
     pub sleep_interval: f64,
+    /// (window, button, outcome) of the most recent action.
+    pub last_action: Option<(String, String, ActionOutcome)>,
+    /// Pipeline steps of the most recent action.
+    pub last_steps: Vec<StepResult>,
+    /// Failed focus restorations and the latest reason.
+    pub focus_restore_failures: u64,
+    pub last_focus_restore_failure: Option<String>,
+    /// Active hours state reported by the automation loop.
+    pub schedule: Option<ScheduleState>,
+    /// Windows whose circuit breaker tripped, by title.
+    pub tripped: BTreeMap<String, Trip>,
+    controls: Option<Sender<Control>>,
+    pub metrics: Metrics,
+    /// Latest prompt queue of each target window that had prompts.
+    pub prompt_queues: BTreeMap<String, Vec<QueuedPrompt>>,
 }
 
 impl App {
     pub fn new(config: AppConfig) -> Self {
         let sleep_interval = config.interval;
+        let mut logs = LogView::new(config.log_retention);
+        logs.push(LogEntry::new(
+            LogLevel::Info,
+            LogCategory::System,
+            "Welcome to Ag-Accept TUI".to_string(),
+        ));
         Self {
-            logs: vec!["Welcome to Ag-Accept TUI".to_string()],
+            tab: Tab::Monitor,
+            snapshot_service: None,
+            inspector: Inspector::new(),
+            logs,
             status: "Idle".to_string(),
             config,
             should_quit: false,
             visible_windows: Vec::new(),
             all_windows: Vec::new(),
             context_data: None,
+            context_relation: None,
             processing_window: None,
             last_scan_ms: 0,
             sleep_interval,
+            last_action: None,
+            last_steps: Vec::new(),
+            focus_restore_failures: 0,
+            last_focus_restore_failure: None,
+            schedule: None,
+            tripped: BTreeMap::new(),
+            controls: None,
+            metrics: Metrics::default(),
+            prompt_queues: BTreeMap::new(),
         }
     }
 
+    /// Sends requests such as breaker resets to the automation loop.
+    pub fn set_controls(&mut self, controls: Sender<Control>) {
+        self.controls = Some(controls);
+    }
+
     pub fn on_visible_windows(&mut self, windows: Vec<WindowStat>) {
+        self.prompt_queues
+            .retain(|title, _| windows.iter().any(|w| &w.title == title));
         self.visible_windows = windows;
     }
 
@@ -39,11 +107,214 @@ impl App {
         self.all_windows = windows;
     }
 
-    pub fn on_log(&mut self, message: String) {
-        self.logs.push(message);
-        if self.logs.len() > 100 {
-            self.logs.remove(0);
+    pub fn on_log(&mut self, entry: LogEntry) {
+        self.logs.push(entry);
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        match self.tab {
+            Tab::Monitor => self.on_monitor_key(key),
+            Tab::Inspector => self.on_inspector_key(key),
+        }
+    }
+
+    fn on_monitor_key(&mut self, key: KeyEvent) {
+        // Search prompt captures all input while open
+        if let Some(input) = self.logs.search_input.as_mut() {
+            match key.code {
+                KeyCode::Enter => self.logs.commit_search(),
+                KeyCode::Esc => self.logs.cancel_search(),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        if self.logs.show_detail {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                self.logs.show_detail = false;
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => self.quit(),
+            KeyCode::Tab => self.tab = Tab::Inspector,
+            KeyCode::Char('d') => self.dump_target_windows(),
+            KeyCode::Char('r') => self.reset_breakers(),
+            KeyCode::Esc => {
+                if self.logs.search.is_empty() {
+                    self.quit();
+                } else {
+                    self.logs.clear_search();
+                }
+            }
+            KeyCode::Char('/') => self.logs.begin_search(),
+            KeyCode::Up | KeyCode::Char('k') => self.logs.scroll(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.logs.scroll(1),
+            KeyCode::PageUp => self.logs.scroll(-10),
+            KeyCode::PageDown => self.logs.scroll(10),
+            KeyCode::Home => self.logs.scroll_to_newest(),
+            KeyCode::End => self.logs.scroll_to_oldest(),
+            KeyCode::Char('f') => self.logs.toggle_follow(),
+            KeyCode::Char('1') => self.logs.toggle_level(LogLevel::Error),
+            KeyCode::Char('2') => self.logs.toggle_level(LogLevel::Warn),
+            KeyCode::Char('3') => self.logs.toggle_level(LogLevel::Info),
+            KeyCode::Char('4') => self.logs.toggle_level(LogLevel::Debug),
+            KeyCode::Enter if self.logs.selected_entry().is_some() => {
+                self.logs.show_detail = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_inspector_key(&mut self, key: KeyEvent) {
+        match (self.inspector.pane, key.code) {
+            (_, KeyCode::Char('q')) => self.quit(),
+            (_, KeyCode::Tab) => self.tab = Tab::Monitor,
+            (InspectorPane::Windows, KeyCode::Esc) => self.tab = Tab::Monitor,
+            (InspectorPane::Windows, KeyCode::Up | KeyCode::Char('k')) => {
+                self.inspector.select_window(-1, self.all_windows.len())
+            }
+            (InspectorPane::Windows, KeyCode::Down | KeyCode::Char('j')) => {
+                self.inspector.select_window(1, self.all_windows.len())
+            }
+            (InspectorPane::Windows, KeyCode::Enter) => {
+                if let Some(title) = self.all_windows.get(self.inspector.window_index).cloned() {
+                    self.inspector.open_window(&title);
+                }
+            }
+            (InspectorPane::Tree, KeyCode::Esc | KeyCode::Backspace) => {
+                self.inspector.pane = InspectorPane::Windows
+            }
+            (InspectorPane::Tree, KeyCode::Up | KeyCode::Char('k')) => {
+                self.inspector.move_selection(-1)
+            }
+            (InspectorPane::Tree, KeyCode::Down | KeyCode::Char('j')) => {
+                self.inspector.move_selection(1)
+            }
+            (InspectorPane::Tree, KeyCode::PageUp) => self.inspector.move_selection(-10),
+            (InspectorPane::Tree, KeyCode::PageDown) => self.inspector.move_selection(10),
+            (InspectorPane::Tree, KeyCode::Right | KeyCode::Char('l')) => self.inspector.expand(),
+            (InspectorPane::Tree, KeyCode::Left | KeyCode::Char('h')) => self.inspector.collapse(),
+            (InspectorPane::Tree, KeyCode::Enter | KeyCode::Char(' ')) => self.inspector.toggle(),
+            (InspectorPane::Tree, KeyCode::Char('m')) => self.inspector.mark_button(),
+            (InspectorPane::Tree, KeyCode::Char('c')) => self.inspector.mark_context(),
+            (InspectorPane::Tree, KeyCode::Char('g')) => self.generate_rule(),
+            (InspectorPane::Tree, KeyCode::Char('d')) => {
+                if let Some(node) = self.inspector.selected_node() {
+                    let label = format!(
+                        "{} / {} \"{}\"",
+                        self.inspector.window_title.as_deref().unwrap_or("window"),
+                        node.control_type,
+                        node.name
+                    );
+                    let element = node.element.clone();
+                    self.dump_tree(&element, &label);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn generate_rule(&mut self) {
+        let rule = match self.inspector.build_rule() {
+            Ok(rule) => rule,
+            Err(e) => {
+                self.inspector.message = Some(e.to_string());
+                return;
+            }
+        };
+
+        let name = rule.name.clone();
+        // Kept only once it is on disk
+        let mut config = self.config.clone();
+        config.rules.push(rule);
+        let entry = match config.save() {
+            Ok(_) => {
+                self.config = config;
+                LogEntry::new(
+                    LogLevel::Info,
+                    LogCategory::System,
+                    format!("Added rule '{}' to config (restart to apply)", name),
+                )
+            }
+            Err(e) => LogEntry::new(
+                LogLevel::Error,
+                LogCategory::System,
+                format!("Failed to save rule '{}': {}", name, e),
+            ),
+        };
+        self.inspector.message = Some(entry.message.clone());
+        self.on_log(entry);
+    }
+
+    /// Dumps every monitored target window to a JSON snapshot file.
+    fn dump_target_windows(&mut self) {
+        let titles: Vec<String> = self
+            .visible_windows
+            .iter()
+            .map(|w| w.title.clone())
+            .collect();
+        if titles.is_empty() {
+            self.on_log(LogEntry::new(
+                LogLevel::Warn,
+                LogCategory::System,
+                "No target windows to dump".to_string(),
+            ));
+            return;
+        }
+        for title in titles {
+            let found = self.snapshot_service().and_then(|s| s.find_window(&title));
+            match found {
+                Ok((name, element)) => self.dump_tree(&element, &name),
+                Err(e) => self.on_log(
+                    LogEntry::new(
+                        LogLevel::Error,
+                        LogCategory::System,
+                        format!("Tree dump failed: {}", e),
+                    )
+                    .with_window(&title),
+                ),
+            }
+        }
+    }
+
+    fn dump_tree(&mut self, element: &PlatformElement, label: &str) {
+        let result = self.snapshot_service().and_then(|s| {
+            let tree = s.capture(element, label, DUMP_MAX_DEPTH)?;
+            let path = TreeSnapshot::default_path(label, SnapshotFormat::Json);
+            tree.save(&path, SnapshotFormat::Json)?;
+            Ok((tree.node_count(), path))
+        });
+        let entry = match result {
+            Ok((count, path)) => LogEntry::new(
+                LogLevel::Info,
+                LogCategory::System,
+                format!("Dumped {} nodes to {}", count, path.display()),
+            ),
+            Err(e) => LogEntry::new(
+                LogLevel::Error,
+                LogCategory::System,
+                format!("Tree dump failed: {}", e),
+            ),
+        };
+        if self.tab == Tab::Inspector {
+            self.inspector.message = Some(entry.message.clone());
+        }
+        self.on_log(entry.with_window(label));
+    }
+
+    fn snapshot_service(&mut self) -> anyhow::Result<&SnapshotService> {
+        if self.snapshot_service.is_none() {
+            self.snapshot_service = Some(SnapshotService::new()?);
         }
+        self.snapshot_service
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Snapshot service unavailable"))
     }
 
     pub fn on_status(&mut self, status: String) {
@@ -54,15 +325,78 @@ impl App {
         self.should_quit = true;
     }
 
-    pub fn on_context(&mut self, button: String, neighbors: Vec<String>) {
+    pub fn on_context(
+        &mut self,
+        button: String,
+        neighbors: Vec<String>,
+        context: Option<(String, ContextRelation)>,
+    ) {
         self.context_data = Some((button, neighbors));
+        self.context_relation = context;
     }
 
     pub fn on_processing(&mut self, window_title: String) {
         self.processing_window = Some(window_title);
     }
 
+    pub fn on_prompt_queue(&mut self, window: String, prompts: Vec<QueuedPrompt>) {
+        self.prompt_queues.insert(window, prompts);
+    }
+
+    pub fn on_action_result(
+        &mut self,
+        window: String,
+        button: String,
+        outcome: ActionOutcome,
+        steps: Vec<StepResult>,
+    ) {
+        self.last_action = Some((window, button, outcome));
+        self.last_steps = steps;
+    }
+
+    pub fn on_focus_restore_failed(&mut self, reason: String) {
+        self.focus_restore_failures += 1;
+        self.last_focus_restore_failure = Some(reason);
+    }
+
+    pub fn on_schedule(&mut self, state: ScheduleState) {
+        self.schedule = Some(state);
+    }
+
+    pub fn on_breaker_tripped(&mut self, window: String, trip: Trip) {
+        self.tripped.insert(window, trip);
+    }
+
+    pub fn on_breaker_reset(&mut self, window: String) {
+        self.tripped.remove(&window);
+    }
+
+    /// Asks the automation loop to clear every tripped breaker.
+    fn reset_breakers(&mut self) {
+        let message = if self.tripped.is_empty() {
+            "No circuit breaker is tripped".to_string()
+        } else if self
+            .controls
+            .as_ref()
+            .is_some_and(|tx| tx.send(Control::ResetBreaker(None)).is_ok())
+        {
+            format!("Resetting {} circuit breaker(s)", self.tripped.len())
+        } else {
+            "Automation loop is not running".to_string()
+        };
+        self.logs
+            .push(LogEntry::new(LogLevel::Info, LogCategory::System, message));
+    }
+
+    pub fn on_metrics(&mut self, metrics: Metrics) {
+        self.metrics = metrics;
+    }
+
     pub fn on_timing(&mut self, scan_ms: u64) {
         self.last_scan_ms = scan_ms;
     }
+
+    pub fn on_sleep(&mut self, duration: Duration) {
+        self.sleep_interval = duration.as_secs_f64();
+    }
 }
@@ -0,0 +1,383 @@
+use crate::config::{PropertyFilter, Proximity, Rule, Traversal};
+use crate::platform::{Backend, Element, ElementInfo, PlatformBackend, PlatformElement};
+use anyhow::{anyhow, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InspectorPane {
+    Windows,
+    Tree,
+}
+
+/// Control type shown for elements whose type could not be read.
+const UNKNOWN_TYPE: &str = "?";
+
+pub struct TreeNode<E: Element = PlatformElement> {
+    pub element: E,
+    pub name: String,
+    pub control_type: String,
+    pub depth: usize,
+    pub expanded: bool,
+    /// `None` until children were requested once.
+    pub child_count: Option<usize>,
+    /// Fetched only once the node is selected.
+    pub details: Option<ElementInfo>,
+}
+
+impl<E: Element> TreeNode<E> {
+    fn new(element: E, depth: usize) -> Self {
+        Self {
+            name: element.get_name().unwrap_or_default(),
+            control_type: element
+                .get_control_type()
+                .unwrap_or_else(|_| UNKNOWN_TYPE.to_string()),
+            element,
+            depth,
+            expanded: false,
+            child_count: None,
+            details: None,
+        }
+    }
+}
+
+/// Lazily-expanded accessibility tree browser for the Inspector tab.
+///
+/// Runs on the TUI thread with its own backend so browsing never blocks the
+/// automation loop.
+pub struct Inspector<B: Backend = PlatformBackend> {
+    backend: Option<B>,
+    pub pane: InspectorPane,
+    pub window_index: usize,
+    pub window_title: Option<String>,
+    /// Flattened visible tree, parents before children.
+    pub nodes: Vec<TreeNode<B::Element>>,
+    pub selected: usize,
+    pub marked_button: Option<usize>,
+    pub marked_context: Option<usize>,
+    pub message: Option<String>,
+}
+
+impl<B: Backend> Inspector<B> {
+    pub fn new() -> Self {
+        Self {
+            backend: None,
+            pane: InspectorPane::Windows,
+            window_index: 0,
+            window_title: None,
+            nodes: Vec::new(),
+            selected: 0,
+            marked_button: None,
+            marked_context: None,
+            message: None,
+        }
+    }
+
+    #[cfg(test)]
+    fn with_backend(backend: B) -> Self {
+        Self {
+            backend: Some(backend),
+            ..Self::new()
+        }
+    }
+
+    fn backend(&mut self) -> Result<&B> {
+        if self.backend.is_none() {
+            self.backend = Some(B::new()?);
+        }
+        self.backend
+            .as_ref()
+            .ok_or_else(|| anyhow!("Backend unavailable"))
+    }
+
+    pub fn select_window(&mut self, delta: isize, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let next = (self.window_index as isize + delta).clamp(0, count as isize - 1);
+        self.window_index = next as usize;
+    }
+
+    /// Opens the tree of the top-level window named `title`.
+    pub fn open_window(&mut self, title: &str) {
+        let window = match self.backend().and_then(|b| b.get_all_windows()) {
+            Ok(windows) => windows
+                .into_iter()
+                .find(|w| w.get_name().map(|n| n == title).unwrap_or(false)),
+            Err(e) => {
+                self.message = Some(format!("Failed to list windows: {}", e));
+                return;
+            }
+        };
+
+        let Some(window) = window else {
+            self.message = Some(format!("Window '{}' is gone", title));
+            return;
+        };
+
+        self.window_title = Some(title.to_string());
+        self.nodes = vec![TreeNode::new(window, 0)];
+        self.selected = 0;
+        self.marked_button = None;
+        self.marked_context = None;
+        self.message = None;
+        self.pane = InspectorPane::Tree;
+        self.expand();
+        self.load_details();
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let next = (self.selected as isize + delta).clamp(0, self.nodes.len() as isize - 1);
+        self.selected = next as usize;
+        self.load_details();
+    }
+
+    fn load_details(&mut self) {
+        if let Some(node) = self.nodes.get_mut(self.selected) {
+            if node.details.is_none() {
+                node.details = node.element.get_info().ok();
+            }
+        }
+    }
+
+    /// Expands the selected node, fetching its children on first use.
+    pub fn expand(&mut self) {
+        let Some(node) = self.nodes.get(self.selected) else {
+            return;
+        };
+        if node.expanded {
+            return;
+        }
+        let element = node.element.clone();
+        let depth = node.depth;
+
+        let children = match self.backend().and_then(|b| b.get_children(&element)) {
+            Ok(children) => children,
+            Err(e) => {
+                self.message = Some(format!("Failed to get children: {}", e));
+                return;
+            }
+        };
+
+        let count = children.len();
+        let new_nodes: Vec<TreeNode<B::Element>> = children
+            .into_iter()
+            .map(|c| TreeNode::new(c, depth + 1))
+            .collect();
+        let insert_at = self.selected + 1;
+        self.shift_marks(insert_at, count as isize);
+        self.nodes.splice(insert_at..insert_at, new_nodes);
+
+        let node = &mut self.nodes[self.selected];
+        node.expanded = true;
+        node.child_count = Some(count);
+    }
+
+    /// Collapses the selected node, or moves to its parent if already collapsed.
+    pub fn collapse(&mut self) {
+        let Some(node) = self.nodes.get(self.selected) else {
+            return;
+        };
+        let depth = node.depth;
+
+        if !node.expanded {
+            if let Some(parent) = self.nodes[..self.selected]
+                .iter()
+                .rposition(|n| n.depth < depth)
+            {
+                self.selected = parent;
+                self.load_details();
+            }
+            return;
+        }
+
+        let start = self.selected + 1;
+        let end = self.nodes[start..]
+            .iter()
+            .position(|n| n.depth <= depth)
+            .map(|p| start + p)
+            .unwrap_or(self.nodes.len());
+        for mark in [&mut self.marked_button, &mut self.marked_context] {
+            if mark.is_some_and(|m| m >= start && m < end) {
+                *mark = None;
+            }
+        }
+        self.shift_marks(end, -((end - start) as isize));
+        self.nodes.drain(start..end);
+        self.nodes[self.selected].expanded = false;
+    }
+
+    pub fn toggle(&mut self) {
+        if self.nodes.get(self.selected).is_some_and(|n| n.expanded) {
+            self.collapse();
+        } else {
+            self.expand();
+        }
+    }
+
+    fn shift_marks(&mut self, from: usize, delta: isize) {
+        for m in [&mut self.marked_button, &mut self.marked_context]
+            .into_iter()
+            .flatten()
+        {
+            if *m >= from {
+                *m = (*m as isize + delta) as usize;
+            }
+        }
+    }
+
+    pub fn selected_node(&self) -> Option<&TreeNode<B::Element>> {
+        self.nodes.get(self.selected)
+    }
+
+    pub fn mark_button(&mut self) {
+        self.marked_button = Some(self.selected);
+    }
+
+    pub fn mark_context(&mut self) {
+        self.marked_context = Some(self.selected);
+    }
+
+    /// Builds a rule from the marked button (and optional context) nodes.
+    pub fn build_rule(&self) -> Result<Rule> {
+        let button = self
+            .marked_button
+            .and_then(|i| self.nodes.get(i))
+            .ok_or_else(|| anyhow!("Mark a button node with 'm' first"))?;
+        if button.name.trim().is_empty() {
+            return Err(anyhow!("Marked button has no name to match on"));
+        }
+        let context = self.marked_context.and_then(|i| self.nodes.get(i));
+        if context.is_some_and(|c| c.name.trim().is_empty()) {
+            return Err(anyhow!("Marked context has no name to match on"));
+        }
+
+        Ok(Rule {
+            name: format!(
+                "{} in {}",
+                button.name,
+                self.window_title.as_deref().unwrap_or("window")
+            ),
+            button_texts: vec![button.name.clone()],
+            context_texts: context.map(|c| vec![c.name.clone()]).unwrap_or_default(),
+            // Unknown types fall back to matching buttons and links
+            control_types: Some(button.control_type.clone())
+                .filter(|t| !t.is_empty() && t != UNKNOWN_TYPE)
+                .into_iter()
+                .collect(),
+            button_selector: None,
+            context_selector: None,
+            proximity: Proximity::default(),
+            // A stable automation ID pins the rule to this exact button
+            properties: PropertyFilter {
+                automation_id: button
+                    .details
+                    .as_ref()
+                    .map(|d| d.automation_id.clone())
+                    .filter(|id| !id.is_empty()),
+                ..PropertyFilter::default()
+            },
+            traversal: Traversal::default(),
+            priority: 0,
+            pipeline: Vec::new(),
+            methods: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture::{load_fixture, FixtureBackend};
+    use crate::services::snapshot::SnapshotNode;
+
+    const WINDOW: &str = "Agent Manager - Antigravity";
+
+    fn select(inspector: &mut Inspector<FixtureBackend>, name: &str) {
+        inspector.selected = inspector
+            .nodes
+            .iter()
+            .position(|n| n.name == name)
+            .unwrap_or_else(|| panic!("no node '{}'", name));
+        inspector.load_details();
+    }
+
+    fn marked(inspector: &Inspector<FixtureBackend>) -> (Option<&str>, Option<&str>) {
+        let name = |mark: Option<usize>| mark.map(|i| inspector.nodes[i].name.as_str());
+        (
+            name(inspector.marked_button),
+            name(inspector.marked_context),
+        )
+    }
+
+    /// The Agent Manager window with its prompt group expanded.
+    fn open_prompt(backend: FixtureBackend) -> Inspector<FixtureBackend> {
+        let mut inspector = Inspector::with_backend(backend);
+        inspector.open_window(WINDOW);
+        select(&mut inspector, "Conversation");
+        inspector.expand();
+        // The unnamed group right below the first message
+        inspector.selected += 2;
+        inspector.expand();
+        inspector
+    }
+
+    #[test]
+    fn marks_follow_their_nodes_through_expand_and_collapse() {
+        let backend = FixtureBackend::from_fixture(&load_fixture("agent_manager_run_command.json"));
+        let mut inspector = open_prompt(backend);
+        select(&mut inspector, "Accept");
+        inspector.mark_button();
+        select(&mut inspector, "Run command?");
+        inspector.mark_context();
+
+        // Expanding and collapsing a node above the marks shifts them
+        inspector.selected = 1;
+        inspector.expand();
+        assert_eq!(inspector.nodes[2].name, "New Conversation");
+        assert_eq!(marked(&inspector), (Some("Accept"), Some("Run command?")));
+        inspector.collapse();
+        assert_eq!(marked(&inspector), (Some("Accept"), Some("Run command?")));
+
+        let rule = inspector.build_rule().unwrap();
+        assert_eq!(rule.name, format!("Accept in {}", WINDOW));
+        assert_eq!(rule.button_texts, vec!["Accept"]);
+        assert_eq!(rule.context_texts, vec!["Run command?"]);
+        assert_eq!(rule.control_types, vec!["Button"]);
+        assert_eq!(
+            rule.properties.automation_id.as_deref(),
+            Some("run-command-accept")
+        );
+
+        // Collapsing the group hides the marked nodes and drops the marks
+        select(&mut inspector, "Reject");
+        inspector.collapse();
+        assert_eq!(inspector.nodes[inspector.selected].control_type, "Group");
+        inspector.collapse();
+        assert_eq!(marked(&inspector), (None, None));
+        assert!(inspector.build_rule().is_err());
+        // Collapsing a collapsed node moves to its parent
+        inspector.collapse();
+        assert_eq!(inspector.selected_node().unwrap().name, "Conversation");
+    }
+
+    #[test]
+    fn rule_skips_unknown_control_types() {
+        let mut fixture = load_fixture("agent_manager_run_command.json");
+        fn unknown_type(node: &mut SnapshotNode) {
+            if node.name == "Accept" {
+                node.control_type = UNKNOWN_TYPE.to_string();
+            }
+            node.children.iter_mut().for_each(unknown_type);
+        }
+        unknown_type(&mut fixture.windows[0].root);
+        let mut inspector = open_prompt(FixtureBackend::from_fixture(&fixture));
+        select(&mut inspector, "Accept");
+        inspector.mark_button();
+
+        let rule = inspector.build_rule().unwrap();
+        assert!(rule.control_types.is_empty());
+        assert!(rule.matches_control_type("Button"));
+    }
+}
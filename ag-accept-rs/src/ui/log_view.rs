@@ -0,0 +1,222 @@
+use crate::automation::{LogEntry, LogLevel};
+use std::collections::{HashSet, VecDeque};
+
+/// State of the scrollable log pane.
+///
+/// Entries are stored oldest first; the pane shows them newest first, so
+/// `selected` is an index into the filtered, reversed view (0 = newest).
+pub struct LogView {
+    pub entries: VecDeque<LogEntry>,
+    pub retention: usize,
+    pub selected: usize,
+    /// When true the selection sticks to the newest entry.
+    pub follow: bool,
+    pub levels: HashSet<LogLevel>,
+    /// Committed search query (case-insensitive substring).
+    pub search: String,
+    /// Query being typed after pressing `/`; `None` when not in search mode.
+    pub search_input: Option<String>,
+    pub show_detail: bool,
+}
+
+impl LogView {
+    pub fn new(retention: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            retention: retention.max(1),
+            selected: 0,
+            follow: true,
+            levels: LogLevel::ALL.into_iter().collect(),
+            search: String::new(),
+            search_input: None,
+            show_detail: false,
+        }
+    }
+
+    pub fn push(&mut self, entry: LogEntry) {
+        let visible = self.matches(&entry);
+        self.entries.push_back(entry);
+        while self.entries.len() > self.retention {
+            self.entries.pop_front();
+        }
+
+        if self.follow {
+            self.selected = 0;
+        } else if visible {
+            // Keep the same entry selected while paused
+            self.selected += 1;
+        }
+        self.clamp();
+    }
+
+    fn matches(&self, entry: &LogEntry) -> bool {
+        if !self.levels.contains(&entry.level) {
+            return false;
+        }
+        if self.search.is_empty() {
+            return true;
+        }
+        let query = self.search.to_lowercase();
+        entry.message.to_lowercase().contains(&query)
+            || entry
+                .window
+                .as_deref()
+                .is_some_and(|w| w.to_lowercase().contains(&query))
+            || entry.category.label().contains(&query)
+    }
+
+    /// Filtered entries, newest first.
+    pub fn visible(&self) -> Vec<&LogEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|e| self.matches(e))
+            .collect()
+    }
+
+    pub fn selected_entry(&self) -> Option<&LogEntry> {
+        self.visible().get(self.selected).copied()
+    }
+
+    fn clamp(&mut self) {
+        let len = self.visible().len();
+        if len == 0 {
+            self.selected = 0;
+        } else if self.selected >= len {
+            self.selected = len - 1;
+        }
+    }
+
+    /// Moves the selection by `delta` rows (positive = older). Scrolling away
+    /// from the newest entry pauses follow; returning to it resumes.
+    pub fn scroll(&mut self, delta: isize) {
+        let len = self.visible().len();
+        if len == 0 {
+            return;
+        }
+        let next = (self.selected as isize + delta).clamp(0, len as isize - 1);
+        self.selected = next as usize;
+        self.follow = self.selected == 0;
+    }
+
+    pub fn scroll_to_newest(&mut self) {
+        self.selected = 0;
+        self.follow = true;
+    }
+
+    pub fn scroll_to_oldest(&mut self) {
+        self.selected = self.visible().len().saturating_sub(1);
+        self.follow = false;
+    }
+
+    pub fn toggle_follow(&mut self) {
+        self.follow = !self.follow;
+        if self.follow {
+            self.selected = 0;
+        }
+    }
+
+    pub fn toggle_level(&mut self, level: LogLevel) {
+        if !self.levels.remove(&level) {
+            self.levels.insert(level);
+        }
+        self.clamp();
+    }
+
+    pub fn begin_search(&mut self) {
+        self.search_input = Some(self.search.clone());
+    }
+
+    pub fn commit_search(&mut self) {
+        if let Some(input) = self.search_input.take() {
+            self.search = input;
+            self.selected = 0;
+            self.clamp();
+        }
+    }
+
+    pub fn cancel_search(&mut self) {
+        self.search_input = None;
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search.clear();
+        self.clamp();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automation::LogCategory;
+
+    fn entry(level: LogLevel, message: &str) -> LogEntry {
+        LogEntry::new(level, LogCategory::Scan, message.to_string())
+    }
+
+    fn messages(view: &LogView) -> Vec<&str> {
+        view.visible().iter().map(|e| e.message.as_str()).collect()
+    }
+
+    #[test]
+    fn filters_by_level_and_search_within_retention() {
+        let mut view = LogView::new(3);
+        view.push(entry(LogLevel::Info, "dropped"));
+        view.push(entry(LogLevel::Error, "scan failed"));
+        view.push(entry(LogLevel::Debug, "tree walked").with_window("Agent Manager"));
+        view.push(entry(LogLevel::Info, "Found button"));
+        assert_eq!(
+            messages(&view),
+            vec!["Found button", "tree walked", "scan failed"]
+        );
+
+        view.toggle_level(LogLevel::Debug);
+        assert_eq!(messages(&view), vec!["Found button", "scan failed"]);
+        view.toggle_level(LogLevel::Debug);
+
+        // Case-insensitive, over message and window
+        view.begin_search();
+        view.search_input = Some("AGENT".to_string());
+        assert_eq!(messages(&view).len(), 3, "not applied while typing");
+        view.commit_search();
+        assert_eq!(messages(&view), vec!["tree walked"]);
+        view.begin_search();
+        view.search_input = Some("nothing".to_string());
+        view.cancel_search();
+        assert_eq!(view.search, "AGENT");
+
+        // The category label matches too
+        view.search = "scan".to_string();
+        assert_eq!(messages(&view).len(), 3);
+        view.clear_search();
+        assert!(view.search.is_empty());
+    }
+
+    #[test]
+    fn scrolling_pauses_follow_and_keeps_the_selection() {
+        let mut view = LogView::new(10);
+        for message in ["one", "two", "three"] {
+            view.push(entry(LogLevel::Info, message));
+        }
+        assert_eq!(view.selected_entry().unwrap().message, "three");
+
+        view.scroll(1);
+        assert!(!view.follow);
+        view.push(entry(LogLevel::Info, "four"));
+        assert_eq!(view.selected_entry().unwrap().message, "two");
+        // Filtered-out entries do not move the selection
+        view.toggle_level(LogLevel::Debug);
+        view.push(entry(LogLevel::Debug, "hidden"));
+        assert_eq!(view.selected_entry().unwrap().message, "two");
+
+        view.scroll(10);
+        assert_eq!(view.selected_entry().unwrap().message, "one");
+        view.scroll(-10);
+        assert!(view.follow, "back at the newest entry");
+        view.scroll_to_oldest();
+        assert_eq!(view.selected_entry().unwrap().message, "one");
+        view.scroll_to_newest();
+        view.push(entry(LogLevel::Info, "five"));
+        assert_eq!(view.selected_entry().unwrap().message, "five");
+    }
+}
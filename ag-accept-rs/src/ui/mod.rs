@@ -1,3 +1,6 @@
 pub mod app;
+pub mod inspector;
+pub mod log_view;
 pub mod tui;
+#[allow(clippy::module_inception)]
 pub mod ui;
@@ -1,20 +1,29 @@
-use crate::ui::app::App;
+use crate::automation::{ActionOutcome, LogCategory, LogLevel, QueueState};
+use crate::pipeline;
+use crate::text;
+use crate::ui::app::{App, Tab};
+use crate::ui::inspector::InspectorPane;
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
 pub fn render(app: &mut App, frame: &mut Frame) {
+    if app.tab == Tab::Inspector {
+        render_inspector(app, frame);
+        return;
+    }
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints(
             [
                 Constraint::Length(3), // Title
-                Constraint::Length(3), // Stats (Timing)
+                Constraint::Length(4), // Stats (Timing)
                 Constraint::Length(6), // Target Windows (Stats)
                 Constraint::Min(5),    // Middle (Logs + All Windows) - Force visibility
                 Constraint::Length(8), // Context Pane (Reduced)
@@ -25,31 +34,7 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         .split(frame.size());
 
     // 1. Header
-    let status_color = if app.status.contains("Found") || app.status.contains("Clicked") {
-        Color::Green
-    } else {
-        Color::Yellow
-    };
-
-    let header_text = vec![Line::from(vec![
-        Span::styled(
-            "Ag-Accept ",
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::raw("| "),
-        Span::raw(format!("Target: {} | ", app.config.target_window_title)),
-        Span::styled(
-            format!("State: {}", app.status),
-            Style::default().fg(status_color),
-        ),
-    ])];
-
-    let header =
-        Paragraph::new(header_text).block(Block::default().borders(Borders::ALL).title("Info"));
-
-    frame.render_widget(header, chunks[0]);
+    render_header(app, frame, chunks[0]);
 
     // 1b. Stats Block
     let scan_color = if app.last_scan_ms < 500 {
@@ -60,13 +45,13 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         Color::Red
     };
 
-    let stats_text = vec![Line::from(vec![
+    let mut stats_text = vec![Line::from(vec![
         Span::raw("Last Scan: "),
         Span::styled(
             format!("{} ms", app.last_scan_ms),
             Style::default().fg(scan_color).add_modifier(Modifier::BOLD),
         ),
-        Span::raw(" | Sleep Interval: "),
+        Span::raw(" | Next Cycle In: "),
         Span::styled(
             format!("{:.1} s", app.sleep_interval),
             Style::default().fg(Color::Cyan),
@@ -85,6 +70,54 @@ pub fn render(app: &mut App, frame: &mut Frame) {
             }),
         ),
     ])];
+    if let Some((_, button, outcome)) = &app.last_action {
+        let color = match outcome {
+            ActionOutcome::Verified { .. } | ActionOutcome::Performed { .. } => Color::Green,
+            ActionOutcome::Unverified { .. } => Color::Yellow,
+            ActionOutcome::Failed { .. } => Color::Red,
+            ActionOutcome::Deferred { .. } => Color::Blue,
+        };
+        stats_text[0]
+            .spans
+            .push(Span::raw(format!(" | Last Action: '{}' ", button)));
+        stats_text[0]
+            .spans
+            .push(Span::styled(outcome.label(), Style::default().fg(color)));
+    }
+    stats_text.push(Line::from(vec![
+        Span::raw("Actions: "),
+        Span::styled(
+            app.metrics.actions.to_string(),
+            Style::default().fg(Color::Cyan),
+        ),
+        Span::raw(" | Scan: "),
+        Span::styled(
+            if app.config.batched_scan {
+                "batched"
+            } else {
+                "per-element"
+            },
+            Style::default().fg(Color::Cyan),
+        ),
+        Span::raw(" | Suppressed Repeats: "),
+        Span::styled(
+            app.metrics.suppressed.to_string(),
+            Style::default().fg(if app.metrics.suppressed > 0 {
+                Color::Yellow
+            } else {
+                Color::Gray
+            }),
+        ),
+    ]));
+    if let Some(reason) = &app.last_focus_restore_failure {
+        stats_text[1]
+            .spans
+            .push(Span::raw(" | Focus Restore Failures: "));
+        stats_text[1].spans.push(Span::styled(
+            format!("{} ({})", app.focus_restore_failures, reason),
+            Style::default().fg(Color::Red),
+        ));
+    }
 
     let stats = Paragraph::new(stats_text)
         .block(Block::default().borders(Borders::ALL).title("Timing Stats"));
@@ -97,7 +130,9 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         .iter()
         .map(|w| {
             // 1. Status Column (Fixed Width 6)
-            let (status_str, status_color) = if w.duration_ms > 1000 {
+            let (status_str, status_color) = if w.hung_for.is_some() {
+                ("HUNG", Color::Magenta)
+            } else if w.duration_ms > 1000 {
                 ("BUSY", Color::Red)
             } else {
                 ("OK", Color::Green)
@@ -145,6 +180,34 @@ pub fn render(app: &mut App, frame: &mut Frame) {
                     Style::default().fg(Color::Reset),
                 ), // Duration white
                 Span::styled("ms] ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    w.schedule
+                        .map(|s| {
+                            format!(
+                                "[every {:.1}s {}, next {:.1}s] ",
+                                s.interval.as_secs_f64(),
+                                s.reason.label(),
+                                s.next_in.as_secs_f64()
+                            )
+                        })
+                        .unwrap_or_default(),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    match w.hung_for {
+                        Some(remaining) => {
+                            format!(
+                                "(not responding, retry in {:.0}s) ",
+                                remaining.as_secs_f64().ceil()
+                            )
+                        }
+                        None => format!(
+                            "({} visited, {} el: fetch {}ms + match {}ms) ",
+                            w.scan.visited, w.scan.elements, w.scan.fetch_ms, w.scan.match_ms
+                        ),
+                    },
+                    Style::default().fg(Color::DarkGray),
+                ),
                 Span::styled(display_title, style), // Title with focus underline
             ];
 
@@ -166,44 +229,15 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         .split(chunks[3]);
 
     // 3a. Logs (Left)
-    let log_items: Vec<ListItem> = app
-        .logs
-        .iter()
-        .rev()
-        .map(|m| {
-            let style = if m.contains("ERROR") {
-                Style::default().fg(Color::Red)
-            } else if m.contains("Clicked") {
-                Style::default().fg(Color::Green)
-            } else if m.contains("Found button") {
-                Style::default().fg(Color::Green)
-            } else if m.contains("Found") {
-                Style::default().fg(Color::Magenta)
-            } else {
-                Style::default().fg(Color::Gray)
-            };
-            ListItem::new(Line::from(Span::styled(m, style)))
-        })
-        .collect();
-
-    let logs_list = List::new(log_items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Logs (Newest First)"),
-        )
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD));
-
-    frame.render_widget(logs_list, middle_chunks[0]);
+    render_logs(app, frame, middle_chunks[0]);
 
     // 3b. All Windows (Right) - Discovery List
-    let target_lower = app.config.target_window_title.to_lowercase();
     let all_window_items: Vec<ListItem> = app
         .all_windows
         .iter()
         .map(|w| {
             // Highlight if it matches target config (so user knows what's being picked up)
-            if w.to_lowercase().contains(&target_lower) {
+            if text::title_matches(w, &app.config.target_window_title) {
                 ListItem::new(Line::from(Span::styled(
                     w,
                     Style::default()
@@ -224,7 +258,13 @@ pub fn render(app: &mut App, frame: &mut Frame) {
 
     frame.render_widget(all_windows_list, middle_chunks[1]);
 
-    // 3. Context Pane (Bottom Split)
+    // 3. Context Pane and Prompt Queue (Bottom Split)
+    let bottom_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(chunks[4]);
+    render_prompt_queues(app, frame, bottom_chunks[1]);
+
     let context_block = Block::default().borders(Borders::ALL).title("Context Info");
     if let Some((btn, neighbors)) = &app.context_data {
         let mut lines = Vec::new();
@@ -237,6 +277,25 @@ pub fn render(app: &mut App, frame: &mut Frame) {
                     .add_modifier(Modifier::BOLD),
             ),
         ]));
+        if let Some((context, relation)) = &app.context_relation {
+            lines.push(Line::from(vec![
+                Span::raw("Context: "),
+                Span::styled(format!("'{}'", context), Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!(" ({})", relation),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
+        if !app.last_steps.is_empty() {
+            lines.push(Line::from(vec![
+                Span::raw("Steps: "),
+                Span::styled(
+                    pipeline::summarize(&app.last_steps),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
         lines.push(Line::from(""));
         lines.push(Line::from("Neighbors (Prev 2 -> Next 2):"));
 
@@ -253,17 +312,408 @@ pub fn render(app: &mut App, frame: &mut Frame) {
 
         // Only show last 6 lines if too many? No, just list them.
         let p = Paragraph::new(lines).block(context_block);
-        frame.render_widget(p, chunks[4]);
+        frame.render_widget(p, bottom_chunks[0]);
     } else {
         let p =
             Paragraph::new("No context data yet. Waiting for button match...").block(context_block);
-        frame.render_widget(p, chunks[4]);
+        frame.render_widget(p, bottom_chunks[0]);
     }
 
-    // 4. Footer
-    let help = Paragraph::new("Press 'q' or 'Esc' to quit.")
+    // 4. Footer (doubles as the search prompt)
+    let footer = if let Some(input) = &app.logs.search_input {
+        Paragraph::new(Line::from(vec![
+            Span::styled("/", Style::default().fg(Color::Yellow)),
+            Span::raw(input.as_str()),
+            Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+        ]))
+    } else {
+        Paragraph::new(
+            "q: quit | Tab: inspector | Up/Down/PgUp/PgDn: scroll | f: follow | /: search | 1-4: levels | Enter: details | d: dump trees | r: reset breaker",
+        )
         .style(Style::default().fg(Color::DarkGray))
-        .block(Block::default().borders(Borders::ALL));
+    };
+
+    frame.render_widget(footer, chunks[5]);
 
-    frame.render_widget(help, chunks[5]);
+    if app.logs.show_detail {
+        render_log_detail(app, frame);
+    }
+}
+
+fn render_prompt_queues(app: &App, frame: &mut Frame, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("Prompt Queue");
+    if app.prompt_queues.is_empty() {
+        let p = Paragraph::new("No prompts queued.").block(block);
+        frame.render_widget(p, area);
+        return;
+    }
+
+    let mut items = Vec::new();
+    for (window, prompts) in &app.prompt_queues {
+        items.push(ListItem::new(Line::from(Span::styled(
+            window.as_str(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))));
+        for (i, prompt) in prompts.iter().enumerate() {
+            let color = match &prompt.state {
+                QueueState::Pending => Color::Cyan,
+                QueueState::Suppressed | QueueState::Gone => Color::DarkGray,
+                QueueState::Done(outcome) if outcome.is_handled() => Color::Green,
+                QueueState::Done(ActionOutcome::Unverified { .. }) => Color::Yellow,
+                QueueState::Done(ActionOutcome::Deferred { .. }) => Color::Blue,
+                QueueState::Done(_) => Color::Red,
+            };
+            items.push(ListItem::new(Line::from(vec![
+                Span::raw(format!(" {}. '{}' ", i + 1, prompt.button)),
+                Span::styled(
+                    format!("({}) ", prompt.rule),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(prompt.state.label(), Style::default().fg(color)),
+            ])));
+        }
+    }
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+fn render_header(app: &App, frame: &mut Frame, area: Rect) {
+    let status_color = if app.status.contains("Found") || app.status.contains("Clicked") {
+        Color::Green
+    } else {
+        Color::Yellow
+    };
+
+    let mut header_text = vec![Line::from(vec![
+        Span::styled(
+            "Ag-Accept ",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("| "),
+        Span::raw(format!("Target: {} | ", app.config.target_window_title)),
+        Span::styled(
+            format!("State: {}", app.status),
+            Style::default().fg(status_color),
+        ),
+    ])];
+    // Only shown when active hours are configured
+    if let Some(schedule) = app
+        .schedule
+        .as_ref()
+        .filter(|_| !app.config.active_hours.ranges.is_empty())
+    {
+        let (label, color) = if schedule.active {
+            ("ACTIVE HOURS", Color::Green)
+        } else {
+            ("OBSERVE ONLY", Color::Blue)
+        };
+        header_text[0].spans.push(Span::raw(" | Schedule: "));
+        header_text[0].spans.push(Span::styled(
+            label,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+        if let Some(at) = schedule.next_change {
+            header_text[0]
+                .spans
+                .push(Span::raw(format!(" until {}", at.format("%a %H:%M"))));
+        }
+    }
+
+    let tab_title = match app.tab {
+        Tab::Monitor => "Info | [Monitor] Inspector",
+        Tab::Inspector => "Info | Monitor [Inspector]",
+    };
+    let mut block = Block::default().borders(Borders::ALL).title(tab_title);
+    if let Some((window, trip)) = app.tripped.iter().next() {
+        let alert = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+        let others = match app.tripped.len() {
+            1 => String::new(),
+            n => format!(" and {} more", n - 1),
+        };
+        header_text[0].spans.push(Span::styled(
+            format!(
+                " | BREAKER TRIPPED: {} ({}){} - r: reset",
+                window, trip.reason, others
+            ),
+            alert,
+        ));
+        block = block.border_style(alert);
+    }
+    let header = Paragraph::new(header_text).block(block);
+
+    frame.render_widget(header, area);
+}
+
+fn render_inspector(app: &mut App, frame: &mut Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(3),  // Title
+            Constraint::Min(5),     // Windows + Tree
+            Constraint::Length(11), // Node Details
+            Constraint::Length(1),  // Help
+        ])
+        .split(frame.size());
+
+    render_header(app, frame, chunks[0]);
+
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(chunks[1]);
+
+    let inspector = &app.inspector;
+    let focused = Style::default().fg(Color::Cyan);
+
+    // Window picker (Discovery list)
+    let window_items: Vec<ListItem> = app
+        .all_windows
+        .iter()
+        .map(|w| ListItem::new(Line::from(w.as_str())))
+        .collect();
+    let windows_block = Block::default()
+        .borders(Borders::ALL)
+        .title("Windows (Enter to open)")
+        .border_style(if inspector.pane == InspectorPane::Windows {
+            focused
+        } else {
+            Style::default()
+        });
+    let windows_list = List::new(window_items)
+        .block(windows_block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut windows_state = ListState::default().with_selected(Some(inspector.window_index));
+    frame.render_stateful_widget(windows_list, middle[0], &mut windows_state);
+
+    // Accessibility tree
+    let tree_items: Vec<ListItem> = inspector
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| {
+            let marker = match (n.expanded, n.child_count) {
+                (true, _) => "v",
+                (false, Some(0)) => " ",
+                (false, _) => ">",
+            };
+            let mut spans = vec![
+                Span::raw(format!("{}{} ", "  ".repeat(n.depth), marker)),
+                Span::styled(
+                    format!("[{}] ", n.control_type),
+                    Style::default().fg(Color::Magenta),
+                ),
+                Span::raw(format!("\"{}\"", n.name)),
+            ];
+            if inspector.marked_button == Some(i) {
+                spans.push(Span::styled(" <button>", Style::default().fg(Color::Green)));
+            }
+            if inspector.marked_context == Some(i) {
+                spans.push(Span::styled(
+                    " <context>",
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let tree_title = format!(
+        "Tree: {}",
+        inspector.window_title.as_deref().unwrap_or("(no window)")
+    );
+    let tree_block = Block::default()
+        .borders(Borders::ALL)
+        .title(tree_title)
+        .border_style(if inspector.pane == InspectorPane::Tree {
+            focused
+        } else {
+            Style::default()
+        });
+    let tree_list = List::new(tree_items)
+        .block(tree_block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut tree_state = ListState::default().with_selected(if inspector.nodes.is_empty() {
+        None
+    } else {
+        Some(inspector.selected)
+    });
+    frame.render_stateful_widget(tree_list, middle[1], &mut tree_state);
+
+    // Node details
+    let mut lines = Vec::new();
+    if let Some(node) = inspector.nodes.get(inspector.selected) {
+        lines.push(Line::from(format!("Name:         \"{}\"", node.name)));
+        lines.push(Line::from(format!("Control Type: {}", node.control_type)));
+        if let Some(details) = &node.details {
+            let bounds = details
+                .bounds
+                .map(|b| format!("x={} y={} w={} h={}", b.x, b.y, b.width, b.height))
+                .unwrap_or_else(|| "-".to_string());
+            lines.push(Line::from(format!("Bounds:       {}", bounds)));
+            lines.push(Line::from(format!(
+                "State:        {}, {}",
+                if details.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                },
+                if details.offscreen {
+                    "offscreen"
+                } else {
+                    "onscreen"
+                }
+            )));
+            if !details.automation_id.is_empty() {
+                lines.push(Line::from(format!(
+                    "Automation:   {}",
+                    details.automation_id
+                )));
+            }
+            if !details.help_text.is_empty() {
+                lines.push(Line::from(format!("Help Text:    {}", details.help_text)));
+            }
+            if let Some(value) = &details.value {
+                lines.push(Line::from(format!("Value:        {}", value)));
+            }
+            lines.push(Line::from(format!(
+                "Patterns:     {}",
+                details.patterns.join(", ")
+            )));
+        }
+        let children = node
+            .child_count
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "?".to_string());
+        lines.push(Line::from(format!("Children:     {}", children)));
+    }
+    if let Some(msg) = &inspector.message {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            msg.as_str(),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    let details = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title("Node"));
+    frame.render_widget(details, chunks[2]);
+
+    let help = Paragraph::new(
+        "Tab: monitor | Enter/Space: toggle | Left/Right: collapse/expand | m: mark button | c: mark context | g: add rule | d: dump subtree | Esc: windows",
+    )
+    .style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(help, chunks[3]);
+}
+
+fn level_style(level: LogLevel) -> Style {
+    match level {
+        LogLevel::Error => Style::default().fg(Color::Red),
+        LogLevel::Warn => Style::default().fg(Color::Yellow),
+        LogLevel::Info => Style::default().fg(Color::Gray),
+        LogLevel::Debug => Style::default().fg(Color::DarkGray),
+    }
+}
+
+fn render_logs(app: &mut App, frame: &mut Frame, area: Rect) {
+    let log_items: Vec<ListItem> = app
+        .logs
+        .visible()
+        .into_iter()
+        .map(|e| {
+            let message_style = if e.category == LogCategory::Action && e.level == LogLevel::Info {
+                Style::default().fg(Color::Green)
+            } else {
+                level_style(e.level)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", e.timestamp.format("%H:%M:%S")),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(format!("{:<5} ", e.level.label()), level_style(e.level)),
+                Span::styled(
+                    format!("[{}] ", e.category.label()),
+                    Style::default().fg(Color::Magenta),
+                ),
+                Span::styled(e.message.as_str(), message_style),
+            ]))
+        })
+        .collect();
+
+    let levels: String = LogLevel::ALL
+        .iter()
+        .map(|l| {
+            if app.logs.levels.contains(l) {
+                &l.label()[..1]
+            } else {
+                "-"
+            }
+        })
+        .collect();
+    let mut title = format!(
+        "Logs (Newest First) [{}] [{}]",
+        if app.logs.follow { "FOLLOW" } else { "PAUSED" },
+        levels
+    );
+    if !app.logs.search.is_empty() {
+        title.push_str(&format!(" [/{}]", app.logs.search));
+    }
+
+    let logs_list = List::new(log_items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = ListState::default().with_selected(Some(app.logs.selected));
+    frame.render_stateful_widget(logs_list, area, &mut state);
+}
+
+fn render_log_detail(app: &App, frame: &mut Frame) {
+    let Some(entry) = app.logs.selected_entry() else {
+        return;
+    };
+    let area = centered_rect(70, 50, frame.size());
+    let lines = vec![
+        Line::from(format!(
+            "Time:     {}",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S%.3f")
+        )),
+        Line::from(vec![
+            Span::raw("Level:    "),
+            Span::styled(entry.level.label(), level_style(entry.level)),
+        ]),
+        Line::from(format!("Category: {}", entry.category.label())),
+        Line::from(format!(
+            "Window:   {}",
+            entry.window.as_deref().unwrap_or("-")
+        )),
+        Line::from(""),
+        Line::from(entry.message.as_str()),
+    ];
+    let popup = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Log Entry (Esc to close)"),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
 }